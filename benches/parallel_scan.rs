@@ -0,0 +1,90 @@
+// Wall time of a full filesystem scan split across adb shells, against a backend with a
+// fixed per-call latency: `cargo bench --bench parallel_scan`
+
+use std::path::Path;
+use std::process::Output;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ro_grpc::error::Result;
+use ro_grpc::fs::{AdbBackend, AdbCommand, AdbHelper, MockBackend};
+
+// roughly one adb round trip to a local emulator
+const CALL_LATENCY: Duration = Duration::from_millis(20);
+const TOP_DIRS: usize = 8;
+const FILES_PER_DIR: usize = 200;
+
+/// `MockBackend` that takes `CALL_LATENCY` to answer, like a shell on the device would.
+struct SlowBackend(MockBackend);
+
+impl AdbBackend for SlowBackend {
+    fn run(&self, cmd: &AdbCommand) -> Result<Output> {
+        std::thread::sleep(CALL_LATENCY);
+        self.0.run(cmd)
+    }
+
+    fn run_lines(
+        &self,
+        cmd: &AdbCommand,
+        command: &str,
+        cancel: Option<&AtomicBool>,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<usize> {
+        std::thread::sleep(CALL_LATENCY);
+        self.0.run_lines(cmd, command, cancel, on_line)
+    }
+
+    fn pull(
+        &self,
+        cmd: &AdbCommand,
+        local: &Path,
+        max_bytes: u64,
+        timeout: Option<Duration>,
+        on_progress: &mut dyn FnMut(u64),
+    ) -> Result<u64> {
+        std::thread::sleep(CALL_LATENCY);
+        self.0.pull(cmd, local, max_bytes, timeout, on_progress)
+    }
+}
+
+fn stat(inode: usize, mode: &str, path: &str) -> String {
+    format!(
+        "{}|{}|1700000000|1700000100|1700000200|root|root|0|'{}'\n",
+        inode, mode, path
+    )
+}
+
+fn device() -> AdbHelper {
+    let tops: Vec<String> = (0..TOP_DIRS).map(|dir| format!("/dir{}", dir)).collect();
+    let mut mock = MockBackend::new()
+        .with_response("shell find / -mindepth 1 -maxdepth 1", tops.join("\n"))
+        .with_response("shell stat -c", stat(1, "drwxr-xr-x", "/"));
+    let mut inode = 2;
+    for top in &tops {
+        let mut listing = stat(inode, "drwxr-xr-x", top);
+        for file in 0..FILES_PER_DIR {
+            inode += 1;
+            listing += &stat(inode, "-rw-r--r--", &format!("{}/f{}", top, file));
+        }
+        inode += 1;
+        mock = mock.with_response(format!("shell find '{}'", top), listing);
+    }
+    AdbHelper::new(None).with_backend(Arc::new(SlowBackend(mock)))
+}
+
+fn scan(c: &mut Criterion) {
+    let adb = device();
+    let mut group = c.benchmark_group("load_all_parallel");
+    group.sample_size(10);
+    for jobs in [1, 2, 4, 8] {
+        group.bench_with_input(BenchmarkId::from_parameter(jobs), &jobs, |b, &jobs| {
+            b.iter(|| adb.load_all_parallel(jobs).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
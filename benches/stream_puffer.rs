@@ -0,0 +1,101 @@
+// Push throughput of the StreamPuffer ring buffers: `cargo bench --bench stream_puffer`
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use ro_grpc::proto::{AudioPacket, Image};
+use ro_grpc::video::StreamPuffer;
+
+const WIDTH: u32 = 1080;
+const HEIGHT: u32 = 1920;
+// a full ring, so every push also evicts the oldest entry
+const MAX_FRAMES: usize = 120;
+const MAX_AUDIO_CHUNKS: usize = 500;
+
+fn puffer() -> StreamPuffer {
+    StreamPuffer::new(MAX_FRAMES, MAX_AUDIO_CHUNKS, 60, 44100, 2, WIDTH, HEIGHT)
+}
+
+fn frame(seq: u64) -> Image {
+    Image {
+        image: vec![0x80; (WIDTH * HEIGHT * 3) as usize],
+        timestamp_us: seq * 16_666,
+        ..Default::default()
+    }
+}
+
+fn packet(seq: u64) -> AudioPacket {
+    AudioPacket {
+        // 10 ms of 44.1 kHz stereo s16
+        audio: vec![0; 1764],
+        timestamp: seq * 10_000,
+        ..Default::default()
+    }
+}
+
+fn push(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_all()
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("stream_puffer");
+    group.throughput(Throughput::Elements(1));
+
+    let video = puffer();
+    rt.block_on(async {
+        for seq in 0..MAX_FRAMES as u64 {
+            video.push_video(frame(seq)).await;
+        }
+    });
+    group.bench_function("push_video", |b| {
+        b.to_async(&rt).iter_batched(
+            || frame(0),
+            |img| video.push_video(img),
+            BatchSize::SmallInput,
+        )
+    });
+
+    let audio = puffer();
+    group.bench_function("push_audio", |b| {
+        b.to_async(&rt).iter_batched(
+            || packet(0),
+            |pkt| audio.push_audio(pkt),
+            BatchSize::SmallInput,
+        )
+    });
+
+    // 60 fps video and 100 packets/s of audio pushed from two tasks at once,
+    // the way a recording session feeds the puffer
+    group.throughput(Throughput::Elements(160));
+    group.sample_size(20);
+    let shared = puffer();
+    group.bench_function("push_video_and_audio_concurrently", |b| {
+        b.to_async(&rt).iter_batched(
+            || {
+                let frames: Vec<Image> = (0..60).map(frame).collect();
+                let packets: Vec<AudioPacket> = (0..100).map(packet).collect();
+                (frames, packets)
+            },
+            |(frames, packets)| {
+                let (video, audio) = (shared.clone(), shared.clone());
+                async move {
+                    let video = tokio::spawn(async move {
+                        for img in frames {
+                            video.push_video(img).await;
+                        }
+                    });
+                    let audio = tokio::spawn(async move {
+                        for pkt in packets {
+                            audio.push_audio(pkt).await;
+                        }
+                    });
+                    let _ = tokio::join!(video, audio);
+                }
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, push);
+criterion_main!(benches);
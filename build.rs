@@ -3,7 +3,8 @@ fn main() {
     println!("cargo:rerun-if-changed=proto/emulator_controller.proto");
 
     tonic_build::configure()
-        .build_server(false) // client-only library by default
+        // client-only library by default, the fake emulator needs the server traits
+        .build_server(std::env::var_os("CARGO_FEATURE_TEST_SERVER").is_some())
         .protoc_arg("--experimental_allow_proto3_optional") // for newer protoc compatibility
         .compile(&["proto/emulator_controller.proto"], &["proto"])
         .expect("Failed to compile proto files");
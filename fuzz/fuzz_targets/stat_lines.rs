@@ -0,0 +1,15 @@
+// Arbitrary `find | stat` output through the scan and into the tree must never panic
+#![no_main]
+
+use std::path::Path;
+use std::sync::Arc;
+
+use libfuzzer_sys::fuzz_target;
+use ro_grpc::fs::{AdbHelper, FileSystem, MockBackend};
+
+fuzz_target!(|data: &[u8]| {
+    let backend = Arc::new(MockBackend::new().with_response("shell find", data));
+    let mut fs = FileSystem::from_adb(AdbHelper::new(None).with_backend(backend));
+    let _ = fs.refresh_path(Path::new("/"));
+    let _ = fs.to_json(true, true);
+});
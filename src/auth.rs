@@ -0,0 +1,33 @@
+// Auth token metadata for secured emulator controllers
+
+use crate::error::{Error, Result};
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// Attaches `authorization: Bearer <token>` to every request when a token is set.
+#[derive(Clone, Default)]
+pub struct AuthInterceptor {
+    header: Option<MetadataValue<Ascii>>,
+}
+
+impl AuthInterceptor {
+    /// Interceptor sending `token`. Fails if the token cannot be sent as gRPC metadata.
+    pub fn with_token(token: &str) -> Result<Self> {
+        let header = format!("Bearer {}", token.trim())
+            .parse()
+            .map_err(|_| Error::Auth("token contains characters not allowed in metadata".into()))?;
+        Ok(Self {
+            header: Some(header),
+        })
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut req: Request<()>) -> std::result::Result<Request<()>, Status> {
+        if let Some(header) = &self.header {
+            req.metadata_mut().insert("authorization", header.clone());
+        }
+        Ok(req)
+    }
+}
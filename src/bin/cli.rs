@@ -1,25 +1,130 @@
+use ro_grpc::fs::is_probably_binary;
 use ro_grpc::DeviceGrpcClient;
 use std::env;
 
+const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:50051";
+
+const USAGE: &str = concat!(
+    "usage: cli [ENDPOINT] [clipboard get [--out PATH] | clipboard set (TEXT | --file PATH)]\n",
+    "       cli mux RAW_DIR OUT.(mp4|webm)"
+);
+
 #[tokio::main]
 async fn main() {
-    let args: Vec<String> = env::args().collect();
-    let endpoint = args.get(1).map(|s| s.as_str()).unwrap_or("http://127.0.0.1:50051");
-
-    match DeviceGrpcClient::connect(endpoint.to_string()).await {
-        Ok(mut client) => {
-            println!("Connected to {}", endpoint);
-            match client.get_clipboard().await {
-                Ok(text) => println!("Clipboard: {}", text),
-                Err(e) => eprintln!("GetClipboard failed: {}", e),
+    #[cfg(feature = "log-stderr")]
+    ro_grpc::logging::init();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let endpoint = if args.first().is_some_and(|arg| arg.contains("://")) {
+        args.remove(0)
+    } else {
+        DEFAULT_ENDPOINT.to_string()
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    // offline, no emulator needed
+    if let ["mux", rest @ ..] = args.as_slice() {
+        if let Err(e) = mux(rest).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    match DeviceGrpcClient::connect(endpoint.clone()).await {
+        Ok(mut client) => match args.as_slice() {
+            [] => {
+                println!("Connected to {}", endpoint);
+                demo(&mut client).await;
+            }
+            ["clipboard", rest @ ..] => {
+                if let Err(e) = clipboard(&mut client, rest).await {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
             }
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        },
+        Err(e) => eprintln!("Failed to connect: {}", e),
+    }
+}
+
+async fn demo(client: &mut DeviceGrpcClient) {
+    match client.get_clipboard().await {
+        Ok(text) => println!("Clipboard: {}", text),
+        Err(e) => eprintln!("GetClipboard failed: {}", e),
+    }
 
-            // Demonstration: perform a tap at 100,200
-            match client.tap(100, 200).await {
-                Ok(()) => println!("Tap sent: (100,200)"),
-                Err(e) => eprintln!("Tap failed: {}", e),
+    // Demonstration: perform a tap at 100,200
+    match client.tap(100, 200).await {
+        Ok(()) => println!("Tap sent: (100,200)"),
+        Err(e) => eprintln!("Tap failed: {}", e),
+    }
+}
+
+/// `clipboard get [--out PATH]` / `clipboard set (TEXT | --file PATH)`
+async fn clipboard(client: &mut DeviceGrpcClient, args: &[&str]) -> Result<(), String> {
+    match args {
+        ["get"] | ["get", "--out", _] => {
+            let text = client
+                .get_clipboard()
+                .await
+                .map_err(|e| format!("GetClipboard failed: {}", e))?;
+            match args.get(2) {
+                Some(path) => std::fs::write(path, text)
+                    .map_err(|e| format!("cannot write {}: {}", path, e))?,
+                None => println!("{}", text),
             }
         }
-        Err(e) => eprintln!("Failed to connect: {}", e),
+        ["set", "--file", path] => {
+            let bytes = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+            let text = clipboard_text(bytes).map_err(|e| format!("{}: {}", path, e))?;
+            client
+                .set_clipboard(text)
+                .await
+                .map_err(|e| format!("SetClipboard failed: {}", e))?;
+        }
+        ["set", text] => client
+            .set_clipboard(*text)
+            .await
+            .map_err(|e| format!("SetClipboard failed: {}", e))?,
+        _ => return Err(USAGE.to_string()),
+    }
+    Ok(())
+}
+
+/// `mux RAW_DIR OUT`: encode a `record_raw` capture directory
+#[cfg(feature = "video")]
+async fn mux(args: &[&str]) -> Result<(), String> {
+    let [dir, out] = args else {
+        return Err(USAGE.to_string());
+    };
+    let report = ro_grpc::video::mux_raw_capture(dir, out)
+        .await
+        .map_err(|e| format!("mux failed: {}", e))?;
+    println!(
+        "Wrote {} ({}x{}, {} frames, {} skipped)",
+        out, report.width, report.height, report.frames_written, report.frames_skipped
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "video"))]
+async fn mux(_args: &[&str]) -> Result<(), String> {
+    Err("mux needs FFmpeg, rebuild with the `video` feature".to_string())
+}
+
+/// The emulator clipboard only carries UTF-8 text, refuse anything else up front.
+fn clipboard_text(bytes: Vec<u8>) -> Result<String, String> {
+    if is_probably_binary(&bytes) {
+        return Err("looks like a binary file, the clipboard only holds text".to_string());
     }
+    String::from_utf8(bytes).map_err(|e| {
+        format!(
+            "not valid UTF-8 (at byte {}), the clipboard only holds text",
+            e.utf8_error().valid_up_to()
+        )
+    })
 }
@@ -0,0 +1,40 @@
+use ro_grpc::DeviceGrpcClient;
+use std::env;
+use std::net::SocketAddr;
+
+const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:50051";
+const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+
+const USAGE: &str = "usage: ro-grpc-web [ENDPOINT] [LISTEN_ADDR]";
+
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "log-stderr")]
+    ro_grpc::logging::init();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() > 2 {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+    let endpoint = args.first().map_or(DEFAULT_ENDPOINT, String::as_str);
+    let listen: SocketAddr = match args.get(1).map_or(DEFAULT_LISTEN, String::as_str).parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("invalid listen address: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let client = match DeviceGrpcClient::connect(endpoint.to_string()).await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Failed to connect: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("Connected to {}, open http://{}/", endpoint, listen);
+    if let Err(e) = ro_grpc::web::serve(client, listen).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,150 @@
+// Running emulators, found through the `pid_<pid>.ini` files they publish
+
+use crate::error::Result;
+use std::path::{Path, PathBuf};
+
+/// gRPC endpoint advertised by a running emulator in its discovery file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmulatorEndpoint {
+    /// Process id of the emulator, taken from the file name
+    pub pid: u32,
+    /// gRPC port on localhost (`grpc.port`)
+    pub port: u16,
+    /// Token expected in the `authorization` metadata (`grpc.token`), if any
+    pub token: Option<String>,
+    /// Name of the running AVD (`avd.name`)
+    pub avd_name: Option<String>,
+}
+
+impl EmulatorEndpoint {
+    /// Endpoint to pass to `DeviceGrpcClient::connect`.
+    pub fn uri(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+}
+
+/// Directory where running emulators publish their discovery files (`pid_<pid>.ini`).
+pub fn discovery_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .or_else(|| {
+            // the emulator falls back to a per-user temp directory
+            let user = std::env::var("USER").ok()?;
+            Some(std::env::temp_dir().join(format!("android-{}", user)))
+        });
+    #[cfg(target_os = "macos")]
+    let base = std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join("Library/Caches/TemporaryItems"));
+    #[cfg(windows)]
+    let base = std::env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("Temp"));
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    let base: Option<PathBuf> = None;
+
+    base.map(|dir| dir.join("avd").join("running"))
+}
+
+/// Emulators currently running on this machine, ordered by pid.
+/// Empty when the platform has no discovery directory or no emulator is running.
+pub fn discover() -> Result<Vec<EmulatorEndpoint>> {
+    match discovery_dir() {
+        Some(dir) => discover_in(&dir),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// `discover` over the discovery files in `dir`.
+/// Files left behind by emulators that are no longer running are skipped.
+pub fn discover_in(dir: &Path) -> Result<Vec<EmulatorEndpoint>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // no emulator has ever run
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut endpoints = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(pid) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("pid_")?.strip_suffix(".ini"))
+            .and_then(|pid| pid.parse().ok())
+        else {
+            continue;
+        };
+        if !is_alive(pid) {
+            continue;
+        }
+        // the emulator may exit and remove its file while we scan
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Some(endpoint) = parse_discovery_file(pid, &contents) {
+            endpoints.push(endpoint);
+        }
+    }
+    endpoints.sort_by_key(|e| e.pid);
+    Ok(endpoints)
+}
+
+/// gRPC token of the running emulator serving on `grpc_port`, read from its discovery file.
+/// `Ok(None)` when no emulator advertises that port or it runs without a token.
+pub fn discover_token(grpc_port: u16) -> Result<Option<String>> {
+    Ok(find_token(discover()?, grpc_port))
+}
+
+/// `discover_token` over the discovery files in `dir`.
+pub fn discover_token_in(dir: &Path, grpc_port: u16) -> Result<Option<String>> {
+    Ok(find_token(discover_in(dir)?, grpc_port))
+}
+
+fn find_token(endpoints: Vec<EmulatorEndpoint>, grpc_port: u16) -> Option<String> {
+    endpoints
+        .into_iter()
+        .find(|e| e.port == grpc_port)
+        .and_then(|e| e.token)
+}
+
+/// Endpoint described by a discovery file; `None` when it advertises no gRPC port.
+fn parse_discovery_file(pid: u32, contents: &str) -> Option<EmulatorEndpoint> {
+    Some(EmulatorEndpoint {
+        pid,
+        port: ini_value(contents, "grpc.port")?.parse().ok()?,
+        token: ini_value(contents, "grpc.token").map(str::to_string),
+        avd_name: ini_value(contents, "avd.name").map(str::to_string),
+    })
+}
+
+/// Value of `key` in a flat `key=value` ini file.
+fn ini_value<'a>(contents: &'a str, key: &str) -> Option<&'a str> {
+    contents.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k.trim() == key)
+            .then_some(v.trim())
+            .filter(|v| !v.is_empty())
+    })
+}
+
+/// Whether process `pid` still exists.
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    // pid 0 and negative values address process groups, never a single emulator
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid <= 0 {
+        return false;
+    }
+    // signal 0 only checks for existence; EPERM means it exists but belongs to another user
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether process `pid` still exists. Not checked here, stale files fail to connect instead.
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
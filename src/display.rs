@@ -0,0 +1,53 @@
+// Friendly view over the emulator's display configurations
+
+use crate::error::{Error, Result};
+use crate::proto::{DisplayConfiguration, DisplayConfigurations};
+
+/// One display of the emulator, as returned by `DeviceGrpcClient::displays`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Display {
+    /// Position in the emulator's display list (what `RecordingConfig::display` selects).
+    pub index: u32,
+    /// Display id used by the emulator (0 is the main display).
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub dpi: u32,
+    /// `DisplayConfiguration.DisplayFlags` bits.
+    pub flags: u32,
+}
+
+impl Display {
+    fn new(index: usize, config: &DisplayConfiguration) -> Self {
+        Self {
+            index: index as u32,
+            id: config.display,
+            width: config.width,
+            height: config.height,
+            dpi: config.dpi,
+            flags: config.flags,
+        }
+    }
+
+    /// All displays of `configs`, in emulator order.
+    pub fn list(configs: &DisplayConfigurations) -> Vec<Display> {
+        configs
+            .displays
+            .iter()
+            .enumerate()
+            .map(|(index, config)| Display::new(index, config))
+            .collect()
+    }
+
+    /// The display at `index`, failing with `Error::DisplayOutOfRange` when there is none.
+    pub fn select(configs: &DisplayConfigurations, index: u32) -> Result<Display> {
+        configs
+            .displays
+            .get(index as usize)
+            .map(|config| Display::new(index as usize, config))
+            .ok_or(Error::DisplayOutOfRange {
+                index,
+                count: configs.displays.len(),
+            })
+    }
+}
@@ -0,0 +1,81 @@
+// Crate-wide error type
+
+#[cfg(feature = "video")]
+use ffmpeg_next as ffmpeg;
+
+/// Errors returned by `DeviceGrpcClient`, `AdbHelper`, `FileSystem` and `StreamPuffer`.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The gRPC channel could not be established or was dropped.
+    #[error("transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    /// The endpoint passed to `connect` is not a valid URI.
+    #[error("invalid endpoint: {0}")]
+    InvalidEndpoint(#[from] tonic::codegen::http::uri::InvalidUri),
+    /// The emulator answered a call with a non-OK status.
+    /// Boxed to keep `Error` (and every `Result`) small.
+    #[error("gRPC error: {0}")]
+    Status(Box<tonic::Status>),
+    /// An `adb` invocation failed or produced unusable output.
+    #[error("ADB error: {0}")]
+    Adb(String),
+    /// The auth token is unusable or could not be discovered.
+    #[error("auth error: {0}")]
+    Auth(String),
+    /// The emulated modem refused a call or SMS operation, or the number is malformed.
+    #[error("telephony error: {0}")]
+    Phone(String),
+    /// A parameter is outside the range the call accepts.
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    /// The requested display does not exist (`count` is 0 when the emulator reports none).
+    #[error("display index {index} out of range ({count} display(s) configured)")]
+    DisplayOutOfRange { index: u32, count: usize },
+    /// An FFmpeg call failed while encoding.
+    #[cfg(feature = "video")]
+    #[error("{context}: {source}")]
+    Ffmpeg {
+        context: &'static str,
+        #[source]
+        source: ffmpeg::Error,
+    },
+    /// Encoding could not proceed (no data, missing encoder, image/GIF writer failure).
+    #[error("encoding error: {0}")]
+    Encode(String),
+    /// A screenshot or frame payload could not be decoded.
+    #[error("decoding error: {0}")]
+    Decode(String),
+    /// An operation did not complete within its deadline.
+    #[error("timed out: {0}")]
+    Timeout(String),
+    /// A saved filesystem snapshot could not be loaded.
+    #[error("snapshot error: {0}")]
+    Snapshot(#[from] crate::fs::SnapshotError),
+    /// The caller's cancellation flag was raised before the operation finished.
+    #[error("operation cancelled")]
+    Cancelled,
+    /// A blocking worker task panicked or was cancelled.
+    #[error("task join error: {0}")]
+    Join(#[from] tokio::task::JoinError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Other(String),
+}
+
+/// Result alias defaulting to the crate `Error`.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(feature = "video")]
+impl Error {
+    /// Wrap an FFmpeg error with a short description of the failed step.
+    pub(crate) fn ffmpeg(context: &'static str, source: ffmpeg::Error) -> Self {
+        Error::Ffmpeg { context, source }
+    }
+}
+
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        Error::Status(Box::new(status))
+    }
+}
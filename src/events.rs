@@ -0,0 +1,60 @@
+// Typed view over the emulator's notification stream
+
+use crate::proto::notification::Type;
+use crate::proto::{BrightnessValue, DisplayConfigurations, Notification, Posture, XrOptions};
+
+/// An event received through `DeviceGrpcClient::stream_notifications`.
+///
+/// Every `oneof type` kind of the proto `Notification` maps to a variant.
+/// Notifications that carry no detailed type (older emulators only set the
+/// deprecated `event` field, newer ones may send kinds this crate doesn't
+/// know yet) are passed through untouched as `Event::Unknown`.
+///
+/// Clipboard changes are not part of the notification stream, use
+/// `DeviceGrpcClient::stream_clipboard` for those.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The virtual scene camera was activated or deactivated on `display`.
+    Camera { active: bool, display: i32 },
+    /// Displays were changed through the extended UI (not through gRPC or the console).
+    DisplayConfigurationsChanged(DisplayConfigurations),
+    /// The foldable posture changed.
+    Posture(Posture),
+    /// The guest finished booting, `time_ms` is 0 when loaded from a snapshot.
+    BootCompleted { time_ms: i32 },
+    /// A brightness value changed.
+    Brightness(BrightnessValue),
+    /// A TextView gained or lost focus on `display`.
+    TextViewFocus { has_focus: bool, display: i32 },
+    /// XR options changed.
+    XrOptions(XrOptions),
+    /// A notification without a recognized detailed type.
+    Unknown(Notification),
+}
+
+impl From<Notification> for Event {
+    fn from(notification: Notification) -> Self {
+        match notification.r#type {
+            Some(Type::CameraNotification(camera)) => Event::Camera {
+                active: camera.active,
+                display: camera.display,
+            },
+            Some(Type::DisplayConfigurationsChangedNotification(changed)) => {
+                Event::DisplayConfigurationsChanged(
+                    changed.display_configurations.unwrap_or_default(),
+                )
+            }
+            Some(Type::Posture(posture)) => Event::Posture(posture),
+            Some(Type::Booted(booted)) => Event::BootCompleted {
+                time_ms: booted.time,
+            },
+            Some(Type::Brightness(brightness)) => Event::Brightness(brightness),
+            Some(Type::TextViewFocus(focus)) => Event::TextViewFocus {
+                has_focus: focus.text_view_has_focus,
+                display: focus.display,
+            },
+            Some(Type::XrOptions(options)) => Event::XrOptions(options),
+            None => Event::Unknown(notification),
+        }
+    }
+}
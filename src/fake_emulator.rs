@@ -0,0 +1,637 @@
+// In-process stand-in for the emulator gRPC service (feature `test-server`)
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::{Stream, StreamExt};
+use tokio::sync::oneshot;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::error::Result;
+use crate::proto::audio_format::{Channels, SampleFormat};
+use crate::proto::emulator_controller_server::{EmulatorController, EmulatorControllerServer};
+use crate::proto::image_format::ImgFormat;
+use crate::proto::{
+    AudioFormat, AudioPacket, BatteryState, BrightnessValue, ClipData, DisplayConfiguration,
+    DisplayConfigurations, DisplayMode, EmulatorStatus, Fingerprint, GpsState, Image, ImageFormat,
+    InputEvent, KeyboardEvent, LogMessage, MouseEvent, Notification, PhoneCall, PhoneNumber,
+    PhoneResponse, PhysicalModelValue, Posture, RotationRadian, SensorValue, SmsMessage,
+    TouchEvent, Velocity, VmRunState, WheelEvent, XrOptions,
+};
+
+/// Size of the single fake display.
+const DISPLAY_WIDTH: u32 = 320;
+const DISPLAY_HEIGHT: u32 = 640;
+/// Edge length of a checkerboard square in the generated screenshots.
+const CHECKER_SIZE: u32 = 32;
+/// Interval between frames of `streamScreenshot`.
+const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+/// Duration of one packet of `streamAudio`.
+const AUDIO_PACKET: Duration = Duration::from_millis(20);
+/// Frequency of the generated audio tone.
+const TONE_HZ: f32 = 440.0;
+
+type ServerStream<T> = Pin<Box<dyn Stream<Item = std::result::Result<T, Status>> + Send>>;
+
+/// Start a fake emulator on an ephemeral localhost port.
+///
+/// Returns the bound address and a sender that shuts the server down when a
+/// value is sent or when it is dropped. Connect with
+/// `DeviceGrpcClient::connect(format!("http://{}", addr))`.
+pub async fn spawn_fake_emulator() -> Result<(SocketAddr, oneshot::Sender<()>)> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let incoming = futures::stream::unfold(listener, |listener| async move {
+        let conn = listener.accept().await.map(|(stream, _)| stream);
+        Some((conn, listener))
+    });
+    Ok((addr, serve(incoming)))
+}
+
+/// Start a fake emulator listening on a Unix domain socket at `path` (Unix only).
+///
+/// Connect with `DeviceGrpcClient::connect(format!("unix://{}", path.display()))`.
+/// The socket file is not removed on shutdown.
+#[cfg(unix)]
+pub async fn spawn_fake_emulator_unix(
+    path: impl AsRef<std::path::Path>,
+) -> Result<oneshot::Sender<()>> {
+    let listener = tokio::net::UnixListener::bind(path)?;
+    let incoming = futures::stream::unfold(listener, |listener| async move {
+        let conn = listener.accept().await.map(|(stream, _)| stream);
+        Some((conn, listener))
+    });
+    Ok(serve(incoming))
+}
+
+/// Serve a `FakeEmulator` on `incoming` connections until the returned sender fires or drops.
+fn serve<S, IO>(incoming: S) -> oneshot::Sender<()>
+where
+    S: Stream<Item = std::io::Result<IO>> + Send + 'static,
+    IO: tokio::io::AsyncRead
+        + tokio::io::AsyncWrite
+        + tonic::transport::server::Connected
+        + Unpin
+        + Send
+        + 'static,
+    IO::ConnectInfo: Clone + Send + Sync + 'static,
+{
+    let (tx, rx) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        let _ = tonic::transport::Server::builder()
+            .add_service(EmulatorControllerServer::new(FakeEmulator::default()))
+            .serve_with_incoming_shutdown(incoming, async {
+                let _ = rx.await;
+            })
+            .await;
+    });
+    tx
+}
+
+/// Canned `EmulatorController` implementation.
+///
+/// Setters are remembered and returned by the matching getters, screenshots
+/// are a black and white checkerboard and audio is a 440 Hz tone.
+pub struct FakeEmulator {
+    clipboard: Mutex<String>,
+    battery: Mutex<BatteryState>,
+    gps: Mutex<GpsState>,
+    vm_state: Arc<Mutex<VmRunState>>,
+    displays: Mutex<DisplayConfigurations>,
+    brightness: Mutex<u32>,
+}
+
+impl Default for FakeEmulator {
+    fn default() -> Self {
+        Self {
+            clipboard: Mutex::new(String::new()),
+            battery: Mutex::new(BatteryState {
+                has_battery: true,
+                is_present: true,
+                charge_level: 100,
+                ..Default::default()
+            }),
+            gps: Mutex::new(GpsState::default()),
+            vm_state: Arc::new(Mutex::new(VmRunState {
+                state: crate::proto::vm_run_state::RunState::Running.into(),
+            })),
+            displays: Mutex::new(DisplayConfigurations {
+                displays: vec![DisplayConfiguration {
+                    width: DISPLAY_WIDTH,
+                    height: DISPLAY_HEIGHT,
+                    dpi: 160,
+                    flags: 0,
+                    display: 0,
+                }],
+                user_configurable: 0,
+                max_displays: 1,
+            }),
+            brightness: Mutex::new(255),
+        }
+    }
+}
+
+/// Render a checkerboard in the requested format and size (0 means native).
+fn checkerboard(fmt: &ImageFormat, seq: u32) -> Image {
+    let width = if fmt.width == 0 {
+        DISPLAY_WIDTH
+    } else {
+        fmt.width
+    };
+    let height = if fmt.height == 0 {
+        DISPLAY_HEIGHT
+    } else {
+        fmt.height
+    };
+    let format = ImgFormat::try_from(fmt.format).unwrap_or(ImgFormat::Png);
+    let mut rgb = image::RgbImage::new(width, height);
+    for (x, y, px) in rgb.enumerate_pixels_mut() {
+        let white = ((x / CHECKER_SIZE) + (y / CHECKER_SIZE)).is_multiple_of(2);
+        *px = image::Rgb(if white { [255; 3] } else { [0; 3] });
+    }
+    let data = match format {
+        ImgFormat::Rgb888 => rgb.into_raw(),
+        ImgFormat::Rgba8888 => image::DynamicImage::ImageRgb8(rgb).to_rgba8().into_raw(),
+        ImgFormat::Png => {
+            let mut out = std::io::Cursor::new(Vec::new());
+            rgb.write_to(&mut out, image::ImageFormat::Png)
+                .expect("PNG encoding to memory");
+            out.into_inner()
+        }
+    };
+    Image {
+        format: Some(ImageFormat {
+            format: format.into(),
+            width,
+            height,
+            display: fmt.display,
+            ..Default::default()
+        }),
+        image: data,
+        seq,
+        timestamp_us: now_us(),
+        ..Default::default()
+    }
+}
+
+/// One packet of a sine tone, `index` counts packets since the stream started.
+fn tone_packet(format: &AudioFormat, index: u64) -> AudioPacket {
+    let rate = if format.sampling_rate == 0 {
+        44100
+    } else {
+        format.sampling_rate
+    };
+    let channels = match Channels::try_from(format.channels) {
+        Ok(Channels::Mono) => 1,
+        _ => 2,
+    };
+    let frames = rate * AUDIO_PACKET.as_millis() as u64 / 1000;
+    let first = index * frames;
+    let mut audio = Vec::with_capacity((frames * channels * 2) as usize);
+    for n in first..first + frames {
+        let t = n as f32 / rate as f32;
+        let sample = ((t * TONE_HZ * std::f32::consts::TAU).sin() * i16::MAX as f32 * 0.5) as i16;
+        for _ in 0..channels {
+            audio.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+    AudioPacket {
+        format: Some(AudioFormat {
+            sampling_rate: rate,
+            channels: format.channels,
+            format: SampleFormat::AudFmtS16.into(),
+            mode: format.mode,
+        }),
+        timestamp: now_us(),
+        audio,
+    }
+}
+
+fn now_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+/// Yield `next(i)` for i = 0, 1, 2... every `period`.
+/// With the MMAP transport and a `file://` handle, write the pixels into that (client
+/// created) file and send the frame without them, like the emulator does.
+fn mmap_frame(fmt: &ImageFormat, mut frame: Image) -> Image {
+    use crate::proto::image_transport::TransportChannel;
+    let Some(transport) = &fmt.transport else {
+        return frame;
+    };
+    if transport.channel != TransportChannel::Mmap as i32 {
+        return frame;
+    }
+    if let Some(path) = transport.handle.strip_prefix("file://") {
+        if std::fs::write(path, &frame.image).is_ok() {
+            frame.image.clear();
+        }
+    }
+    frame
+}
+
+fn periodic<T, F>(period: Duration, next: F) -> ServerStream<T>
+where
+    T: Send + 'static,
+    F: FnMut(u64) -> T + Send + 'static,
+{
+    Box::pin(futures::stream::unfold(
+        (0u64, next),
+        move |(i, mut next)| async move {
+            if i > 0 {
+                tokio::time::sleep(period).await;
+            }
+            let item = next(i);
+            Some((Ok(item), (i + 1, next)))
+        },
+    ))
+}
+
+/// Yield `item` once, then keep the stream open without further messages.
+fn once_then_idle<T: Send + 'static>(item: T) -> ServerStream<T> {
+    Box::pin(futures::stream::once(async move { Ok(item) }).chain(futures::stream::pending()))
+}
+
+#[tonic::async_trait]
+impl EmulatorController for FakeEmulator {
+    type streamSensorStream = ServerStream<SensorValue>;
+    type streamPhysicalModelStream = ServerStream<PhysicalModelValue>;
+    type streamClipboardStream = ServerStream<ClipData>;
+    type streamScreenshotStream = ServerStream<Image>;
+    type streamAudioStream = ServerStream<AudioPacket>;
+    type streamLogcatStream = ServerStream<LogMessage>;
+    type streamNotificationStream = ServerStream<Notification>;
+
+    async fn stream_sensor(
+        &self,
+        request: Request<SensorValue>,
+    ) -> std::result::Result<Response<Self::streamSensorStream>, Status> {
+        Ok(Response::new(once_then_idle(request.into_inner())))
+    }
+
+    async fn get_sensor(
+        &self,
+        request: Request<SensorValue>,
+    ) -> std::result::Result<Response<SensorValue>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+
+    async fn set_sensor(
+        &self,
+        _request: Request<SensorValue>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn set_physical_model(
+        &self,
+        _request: Request<PhysicalModelValue>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn get_physical_model(
+        &self,
+        request: Request<PhysicalModelValue>,
+    ) -> std::result::Result<Response<PhysicalModelValue>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+
+    async fn stream_physical_model(
+        &self,
+        request: Request<PhysicalModelValue>,
+    ) -> std::result::Result<Response<Self::streamPhysicalModelStream>, Status> {
+        Ok(Response::new(once_then_idle(request.into_inner())))
+    }
+
+    async fn set_clipboard(
+        &self,
+        request: Request<ClipData>,
+    ) -> std::result::Result<Response<()>, Status> {
+        *self.clipboard.lock().unwrap() = request.into_inner().text;
+        Ok(Response::new(()))
+    }
+
+    async fn get_clipboard(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<ClipData>, Status> {
+        let text = self.clipboard.lock().unwrap().clone();
+        Ok(Response::new(ClipData { text }))
+    }
+
+    async fn stream_clipboard(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<Self::streamClipboardStream>, Status> {
+        let text = self.clipboard.lock().unwrap().clone();
+        Ok(Response::new(once_then_idle(ClipData { text })))
+    }
+
+    async fn set_battery(
+        &self,
+        request: Request<BatteryState>,
+    ) -> std::result::Result<Response<()>, Status> {
+        *self.battery.lock().unwrap() = request.into_inner();
+        Ok(Response::new(()))
+    }
+
+    async fn get_battery(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<BatteryState>, Status> {
+        Ok(Response::new(self.battery.lock().unwrap().clone()))
+    }
+
+    async fn set_gps(
+        &self,
+        request: Request<GpsState>,
+    ) -> std::result::Result<Response<()>, Status> {
+        *self.gps.lock().unwrap() = request.into_inner();
+        Ok(Response::new(()))
+    }
+
+    async fn get_gps(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<GpsState>, Status> {
+        Ok(Response::new(self.gps.lock().unwrap().clone()))
+    }
+
+    async fn send_fingerprint(
+        &self,
+        _request: Request<Fingerprint>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn send_key(
+        &self,
+        _request: Request<KeyboardEvent>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn send_touch(
+        &self,
+        _request: Request<TouchEvent>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn send_mouse(
+        &self,
+        _request: Request<MouseEvent>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn inject_wheel(
+        &self,
+        request: Request<Streaming<WheelEvent>>,
+    ) -> std::result::Result<Response<()>, Status> {
+        let mut events = request.into_inner();
+        while events.message().await?.is_some() {}
+        Ok(Response::new(()))
+    }
+
+    async fn stream_input_event(
+        &self,
+        request: Request<Streaming<InputEvent>>,
+    ) -> std::result::Result<Response<()>, Status> {
+        let mut events = request.into_inner();
+        while events.message().await?.is_some() {}
+        Ok(Response::new(()))
+    }
+
+    async fn send_phone(
+        &self,
+        _request: Request<PhoneCall>,
+    ) -> std::result::Result<Response<PhoneResponse>, Status> {
+        Ok(Response::new(PhoneResponse::default()))
+    }
+
+    async fn send_sms(
+        &self,
+        _request: Request<SmsMessage>,
+    ) -> std::result::Result<Response<PhoneResponse>, Status> {
+        Ok(Response::new(PhoneResponse::default()))
+    }
+
+    async fn set_phone_number(
+        &self,
+        _request: Request<PhoneNumber>,
+    ) -> std::result::Result<Response<PhoneResponse>, Status> {
+        Ok(Response::new(PhoneResponse::default()))
+    }
+
+    async fn get_status(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<EmulatorStatus>, Status> {
+        Ok(Response::new(EmulatorStatus {
+            version: "fake".to_string(),
+            booted: true,
+            ..Default::default()
+        }))
+    }
+
+    async fn get_screenshot(
+        &self,
+        request: Request<ImageFormat>,
+    ) -> std::result::Result<Response<Image>, Status> {
+        Ok(Response::new(checkerboard(&request.into_inner(), 0)))
+    }
+
+    async fn stream_screenshot(
+        &self,
+        request: Request<ImageFormat>,
+    ) -> std::result::Result<Response<Self::streamScreenshotStream>, Status> {
+        let fmt = request.into_inner();
+        let known = self
+            .displays
+            .lock()
+            .unwrap()
+            .displays
+            .iter()
+            .any(|d| d.display == fmt.display);
+        if !known {
+            return Err(Status::invalid_argument(format!(
+                "no display with id {}",
+                fmt.display
+            )));
+        }
+        let frames = periodic(FRAME_INTERVAL, move |i| {
+            mmap_frame(&fmt, checkerboard(&fmt, i as u32))
+        });
+        // like an exiting emulator, break the stream once the VM is shut down
+        let vm_state = self.vm_state.clone();
+        let running = move |_: &_| {
+            use crate::proto::vm_run_state::RunState;
+            let state = vm_state.lock().unwrap().state;
+            futures::future::ready(state != RunState::Shutdown as i32)
+        };
+        let broken =
+            futures::stream::once(async { Err(Status::unavailable("emulator shut down")) });
+        Ok(Response::new(Box::pin(
+            frames.take_while(running).chain(broken),
+        )))
+    }
+
+    async fn stream_audio(
+        &self,
+        request: Request<AudioFormat>,
+    ) -> std::result::Result<Response<Self::streamAudioStream>, Status> {
+        let fmt = request.into_inner();
+        Ok(Response::new(periodic(AUDIO_PACKET, move |i| {
+            tone_packet(&fmt, i)
+        })))
+    }
+
+    async fn inject_audio(
+        &self,
+        request: Request<Streaming<AudioPacket>>,
+    ) -> std::result::Result<Response<()>, Status> {
+        let mut packets = request.into_inner();
+        while packets.message().await?.is_some() {}
+        Ok(Response::new(()))
+    }
+
+    async fn get_logcat(
+        &self,
+        _request: Request<LogMessage>,
+    ) -> std::result::Result<Response<LogMessage>, Status> {
+        Ok(Response::new(LogMessage {
+            contents: "I/fake: emulator started\n".to_string(),
+            ..Default::default()
+        }))
+    }
+
+    async fn stream_logcat(
+        &self,
+        _request: Request<LogMessage>,
+    ) -> std::result::Result<Response<Self::streamLogcatStream>, Status> {
+        Ok(Response::new(once_then_idle(LogMessage {
+            contents: "I/fake: emulator started\n".to_string(),
+            ..Default::default()
+        })))
+    }
+
+    async fn set_vm_state(
+        &self,
+        request: Request<VmRunState>,
+    ) -> std::result::Result<Response<()>, Status> {
+        use crate::proto::vm_run_state::RunState;
+        // Requested transitions settle immediately into the state the emulator would report
+        let requested = RunState::try_from(request.into_inner().state).unwrap_or_default();
+        let state = match requested {
+            RunState::Start | RunState::Reset | RunState::Restart => RunState::Running,
+            RunState::Stop => RunState::Paused,
+            RunState::Terminate => RunState::Shutdown,
+            other => other,
+        };
+        self.vm_state.lock().unwrap().state = state.into();
+        Ok(Response::new(()))
+    }
+
+    async fn get_vm_state(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<VmRunState>, Status> {
+        Ok(Response::new(self.vm_state.lock().unwrap().clone()))
+    }
+
+    async fn set_display_configurations(
+        &self,
+        request: Request<DisplayConfigurations>,
+    ) -> std::result::Result<Response<DisplayConfigurations>, Status> {
+        let configs = request.into_inner();
+        *self.displays.lock().unwrap() = configs.clone();
+        Ok(Response::new(configs))
+    }
+
+    async fn get_display_configurations(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<DisplayConfigurations>, Status> {
+        Ok(Response::new(self.displays.lock().unwrap().clone()))
+    }
+
+    async fn stream_notification(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<Self::streamNotificationStream>, Status> {
+        Ok(Response::new(Box::pin(futures::stream::pending())))
+    }
+
+    async fn rotate_virtual_scene_camera(
+        &self,
+        _request: Request<RotationRadian>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn set_virtual_scene_camera_velocity(
+        &self,
+        _request: Request<Velocity>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn set_posture(
+        &self,
+        _request: Request<Posture>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn get_brightness(
+        &self,
+        request: Request<BrightnessValue>,
+    ) -> std::result::Result<Response<BrightnessValue>, Status> {
+        let mut value = request.into_inner();
+        value.value = *self.brightness.lock().unwrap();
+        Ok(Response::new(value))
+    }
+
+    async fn set_brightness(
+        &self,
+        request: Request<BrightnessValue>,
+    ) -> std::result::Result<Response<()>, Status> {
+        *self.brightness.lock().unwrap() = request.into_inner().value;
+        Ok(Response::new(()))
+    }
+
+    async fn get_display_mode(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<DisplayMode>, Status> {
+        Ok(Response::new(DisplayMode::default()))
+    }
+
+    async fn set_display_mode(
+        &self,
+        _request: Request<DisplayMode>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn set_xr_options(
+        &self,
+        _request: Request<XrOptions>,
+    ) -> std::result::Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn get_xr_options(
+        &self,
+        _request: Request<()>,
+    ) -> std::result::Result<Response<XrOptions>, Status> {
+        Ok(Response::new(XrOptions::default()))
+    }
+}
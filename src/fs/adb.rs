@@ -1,10 +1,14 @@
-use crate::fs::FileInfo;
-use anyhow::{anyhow, Context, Result};
-use std::collections::HashMap;
+use crate::error::{Error, Result};
+use crate::fs::backend::{AdbBackend, AdbCommand, ProcessBackend};
+use crate::fs::{is_probably_binary, FileInfo, FileType};
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader};
 use std::path::Path;
-use std::process::{Command, Stdio};
+use std::process::{Child, ChildStdout, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
 
 /// Unix file permissions
 
@@ -41,12 +45,75 @@ use std::process::{Command, Stdio};
 //     }
 // }
 
+/// Largest file `read_file` pulls, see `read_file_with_limits`.
+pub const DEFAULT_PULL_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Longest `read_file` waits for a pull to finish, see `read_file_with_limits`.
+pub const DEFAULT_PULL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// Subtree `refresh` and `load_all` leave out by default.
+pub const DEFAULT_SCAN_EXCLUDE: &str = "/proc";
+
+/// Bytes fetched per `dd` round trip by `pull_resumable`.
+const RESUME_CHUNK_BYTES: u64 = 8 * 1024 * 1024;
+
+/// How `AdbHelper` gains root for shell commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RootMethod {
+    /// `su root sh -c '<cmd>'` (AOSP emulator images).
+    SuRoot,
+    /// `su 0 sh -c '<cmd>'`.
+    Su0,
+    /// `su -c '<cmd>'` (Magisk, SuperSU).
+    SuDashC,
+    /// Restart adbd as root once with `adb root`, then run commands unwrapped.
+    AdbRoot,
+    /// Run commands as the shell user.
+    #[default]
+    None,
+}
+
+impl RootMethod {
+    /// Methods tried by `AdbHelper::detect_root_method`, in order.
+    /// `AdbRoot` comes last because trying it restarts adbd.
+    const CANDIDATES: [RootMethod; 4] = [
+        RootMethod::SuRoot,
+        RootMethod::Su0,
+        RootMethod::SuDashC,
+        RootMethod::AdbRoot,
+    ];
+
+    /// Whether commands run under `su`, so only the shell (not adbd) has root.
+    fn wraps_shell(self) -> bool {
+        matches!(
+            self,
+            RootMethod::SuRoot | RootMethod::Su0 | RootMethod::SuDashC
+        )
+    }
+
+    /// Wrap `command` so the whole line (pipes included) runs with this method.
+    fn wrap(self, command: &str) -> String {
+        match self {
+            RootMethod::SuRoot => format!("su root sh -c {}", shell_quote(command)),
+            RootMethod::Su0 => format!("su 0 sh -c {}", shell_quote(command)),
+            RootMethod::SuDashC => format!("su -c {}", shell_quote(command)),
+            RootMethod::AdbRoot | RootMethod::None => command.to_string(),
+        }
+    }
+}
+
 /// ADB-based filesystem client for Android emulator
 #[derive(Clone)]
 pub struct AdbHelper {
     device_serial: Option<String>,
     adb_path: String,
-    root: bool,
+    // extra variables for every adb process, e.g. `ADB_SERVER_SOCKET`
+    env: Vec<(String, String)>,
+    // runs the adb invocations, see `with_backend`
+    backend: Arc<dyn AdbBackend>,
+    root: RootMethod,
+    // set once `adb root` succeeded, shared between clones
+    adb_root_ready: Arc<AtomicBool>,
 }
 
 impl AdbHelper {
@@ -58,56 +125,195 @@ impl AdbHelper {
         Self {
             device_serial,
             adb_path: "adb".to_string(), // Assumes adb is in PATH
-            root: false,
+            env: Vec::new(),
+            backend: Arc::new(ProcessBackend),
+            root: RootMethod::None,
+            adb_root_ready: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    /// Set whether to use root (su) for shell commands
-    pub fn with_root(mut self) -> Self {
-        self.root = true;
+    /// A copy of this helper (adb path, environment, root method) targeting `serial`.
+    ///
+    /// Nothing is run on the device; an `adb root` done by this helper is not assumed
+    /// for the other device.
+    pub fn for_device(&self, serial: impl Into<String>) -> Self {
+        Self {
+            device_serial: Some(serial.into()),
+            adb_root_ready: Arc::new(AtomicBool::new(false)),
+            ..self.clone()
+        }
+    }
+
+    /// Serial of the targeted device, `None` for adb's default device
+    pub fn device_serial(&self) -> Option<&str> {
+        self.device_serial.as_deref()
+    }
+
+    /// Run shell commands as root through `su root` (see `with_root_method` for other forms)
+    pub fn with_root(self) -> Self {
+        self.with_root_method(RootMethod::SuRoot)
+    }
+
+    /// Select how root is obtained for shell commands
+    pub fn with_root_method(mut self, method: RootMethod) -> Self {
+        self.root = method;
         self
     }
 
+    /// Probe the device for a working root method and keep it (falls back to `RootMethod::None`)
+    pub fn with_detected_root(self) -> Self {
+        let method = self.detect_root_method().unwrap_or(RootMethod::None);
+        self.with_root_method(method)
+    }
+
+    /// The root method used for shell commands
+    pub fn root_method(&self) -> RootMethod {
+        self.root
+    }
+
+    /// Find the first root method under which `id -u` reports 0.
+    /// Note that probing `RootMethod::AdbRoot` restarts adbd as root.
+    pub fn detect_root_method(&self) -> Option<RootMethod> {
+        RootMethod::CANDIDATES.into_iter().find(|&method| {
+            let probe = self.clone().with_root_method(method);
+            probe.exec_shell("id -u").is_ok_and(|out| out.trim() == "0")
+        })
+    }
+
+    /// Restart adbd as root the first time a command needs it (`RootMethod::AdbRoot` only)
+    fn ensure_adb_root(&self) -> Result<()> {
+        if self.root != RootMethod::AdbRoot || self.adb_root_ready.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        for action in ["root", "wait-for-device"] {
+            let output = self.backend.run(&self.command(&[action]))?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            // production builds answer "adbd cannot run as root" with a success status
+            if !output.status.success() || stdout.contains("cannot run as root") {
+                return Err(Error::Adb(format!(
+                    "adb {} failed: {}{}",
+                    action,
+                    stdout.trim(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+        }
+        self.adb_root_ready.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
     /// Set custom ADB executable path
     pub fn with_adb_path(mut self, path: String) -> Self {
         self.adb_path = path;
         self
     }
 
-    pub fn exec_pty(&self, command: &str) -> Result<Vec<String>> {
-        // Execute multiple commands in interactive shell with root access
-        let mut child = Command::new(&self.adb_path)
-            .args(&["shell"])
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-        let mut stdin = child.stdin.take().unwrap();
-        let stdout = child.stdout.take().unwrap();
-        let mut reader = BufReader::new(stdout);
+    /// Set `key=value` in the environment of every adb invocation, e.g. `ADB_SERVER_SOCKET`
+    /// to talk to a private adb server. Setting the same key again replaces its value.
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        let value = value.into();
+        match self.env.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.env.push((key, value)),
+        }
+        self
+    }
 
-        // Send commands
-        writeln!(stdin, "su root")?; // TODO: change the SU command when needed
-        writeln!(stdin, "{}", command)?;
-        //writeln!(stdin, "find / -path /proc -prune -o -print0 | xargs -0 stat -c \"%i|%A|%Z|%Y|%X|%U|%G|%s|%N\"")?;
-        writeln!(stdin, "echo ___DF_LV_RO___")?; //TODO: change to unique random token
-        stdin.flush()?;
+    /// Run adb through `backend` instead of spawning the executable, e.g. a `MockBackend`
+    /// feeding canned output to tests. Clones and `for_device` copies share it.
+    /// `tail_follow` always spawns `adb`, it keeps the process running.
+    pub fn with_backend(mut self, backend: Arc<dyn AdbBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
 
-        let mut output: Vec<String> = Vec::new();
-        // Read output
-        let mut line = String::new();
-        while reader.read_line(&mut line)? > 0 {
-            line.pop(); // Remove newline
-            if line.starts_with("___DF_LV_RO___") {
-                break;
-            }
-            output.push(line.clone());
-            line.clear();
+    /// `adb [-s <serial>] <args...>` with the configured path and environment.
+    fn command(&self, args: &[&str]) -> AdbCommand {
+        AdbCommand {
+            program: self.adb_path.clone(),
+            env: self.env.clone(),
+            serial: self.device_serial.clone(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    /// Run `adb [-s <serial>] <args...>` directly, without going through `shell`.
+    ///
+    /// Escape hatch for subcommands this crate does not wrap (`emu`, `install`, ...).
+    /// A non-zero exit is not an error here; check `status` and `stderr` of the result.
+    ///
+    /// ```ignore
+    /// let out = adb.adb(&["emu", "avd", "name"])?;
+    /// println!("{}", String::from_utf8_lossy(&out.stdout));
+    /// ```
+    pub fn adb(&self, args: &[&str]) -> Result<Output> {
+        self.backend.run(&self.command(args))
+    }
+
+    /// `adb` that fails with `Error::Adb` on a non-zero exit, returning stdout.
+    pub(super) fn adb_checked(&self, args: &[&str]) -> Result<String> {
+        let output = self.adb(args)?;
+        if !output.status.success() {
+            return Err(Error::Adb(format!(
+                "adb {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
         }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
 
+    /// Send `command` to the emulator console through `adb emu`, e.g. `geo fix 2.35 48.85`
+    /// or `power capacity 50`. Returns the console response without its final `OK`;
+    /// a `KO: ...` answer becomes `Error::Adb`.
+    pub fn emu(&self, command: &str) -> Result<String> {
+        let mut args = vec!["emu"];
+        args.extend(command.split_whitespace());
+        parse_emu_response(command, &self.adb_checked(&args)?)
+    }
+
+    /// Run `command` in an `adb [-s <serial>] shell` session (as root when configured)
+    /// and collect its output lines.
+    pub fn exec_pty(&self, command: &str) -> Result<Vec<String>> {
+        let mut output: Vec<String> = Vec::new();
+        self.exec_pty_lines(command, |line| output.push(line.to_string()))?;
         Ok(output)
     }
 
+    /// Like `exec_pty` but hands every output line to `on_line` as soon as it is read,
+    /// so huge outputs (a full `find /`) never have to be held in memory.
+    /// Returns the number of lines delivered.
+    pub fn exec_pty_lines(&self, command: &str, on_line: impl FnMut(&str)) -> Result<usize> {
+        self.exec_pty_until(command, None, on_line)
+    }
+
+    /// `exec_pty_lines` that checks `cancel` before every line and, once it is set,
+    /// kills the adb process and returns `Error::Cancelled`.
+    pub fn exec_pty_cancellable(
+        &self,
+        command: &str,
+        cancel: &AtomicBool,
+        on_line: impl FnMut(&str),
+    ) -> Result<usize> {
+        self.exec_pty_until(command, Some(cancel), on_line)
+    }
+
+    fn exec_pty_until(
+        &self,
+        command: &str,
+        cancel: Option<&AtomicBool>,
+        mut on_line: impl FnMut(&str),
+    ) -> Result<usize> {
+        self.ensure_adb_root()?;
+        self.backend.run_lines(
+            &self.command(&["shell"]),
+            &self.root.wrap(command),
+            cancel,
+            &mut on_line,
+        )
+    }
+
     /// Example usage:
     /// ```ignore
     /// let adb = AdbHelper::new(None);
@@ -121,36 +327,40 @@ impl AdbHelper {
     /// ```
     /// Execute an ADB shell command and return stdout
     pub fn exec_shell(&self, command: &str) -> Result<String> {
-        let mut cmd = Command::new(&self.adb_path);
-
-        if let Some(serial) = &self.device_serial {
-            cmd.arg("-s").arg(serial);
-        }
-
-        if self.root {
-            cmd.arg("shell").arg(format!("su root {}", command));
-        } else {
-            cmd.arg("shell").arg(command);
-        }
+        let stdout = self.exec_shell_bytes(command)?;
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    }
 
-        let output = cmd.output().context("Failed to execute adb command")?;
+    /// `exec_shell` returning raw stdout, for output that may not be text.
+    fn exec_shell_bytes(&self, command: &str) -> Result<Vec<u8>> {
+        self.ensure_adb_root()?;
+        let wrapped = self.root.wrap(command);
+        let output = self.backend.run(&self.command(&["shell", &wrapped]))?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!(
+            return Err(Error::Adb(format!(
                 "ADB command failed: {},{}",
                 output.stdout.len(),
                 stderr
-            ));
+            )));
         }
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        Ok(output.stdout)
     }
 
-    /// Execute an ADB pull command to get file content
-    fn exec_pull(&self, remote_path: &str) -> Result<Vec<u8>> {
+    /// Execute an ADB pull command to get file content.
+    /// Aborts once the local copy grows past `max_bytes` or `timeout` elapses.
+    fn exec_pull(
+        &self,
+        remote_path: &str,
+        max_bytes: u64,
+        timeout: std::time::Duration,
+    ) -> Result<Vec<u8>> {
         use std::fs;
 
+        self.check_pullable(remote_path, max_bytes)?;
+
         // Create a temporary file for the pull operation
         let temp_dir = std::env::temp_dir();
         let temp_file = temp_dir.join(format!(
@@ -162,66 +372,229 @@ impl AdbHelper {
                 .as_nanos()
         ));
 
-        let mut cmd = Command::new(&self.adb_path);
+        // Pull to temporary file
+        let pulled = self.backend.pull(
+            &self.command(&["pull", remote_path]),
+            &temp_file,
+            max_bytes,
+            Some(timeout),
+            &mut |_| {},
+        );
+        let data = pulled.and_then(|_| Ok(fs::read(&temp_file)?));
+
+        // Clean up, the pull may have left a partial copy
+        let _ = fs::remove_file(&temp_file);
 
-        if let Some(serial) = &self.device_serial {
-            cmd.arg("-s").arg(serial);
+        data
+    }
+
+    /// Refuse to pull anything but a regular file (after following symlinks) of at most
+    /// `max_bytes`: device nodes, pipes and sockets can stream forever or block.
+    /// Returns the file size.
+    fn check_pullable(&self, remote_path: &str, max_bytes: u64) -> Result<u64> {
+        let output =
+            self.exec_shell(&format!("stat -L -c '%A %s' {}", shell_quote(remote_path)))?;
+        let mut fields = output.split_whitespace();
+        let (Some(mode), Some(size)) = (fields.next(), fields.next()) else {
+            return Err(Error::Adb(format!(
+                "unexpected stat output for {}: {:?}",
+                remote_path, output
+            )));
+        };
+        let file_type = FileType::from(&mode.chars().next().unwrap_or('?'));
+        if file_type != FileType::File {
+            return Err(Error::Adb(format!(
+                "refusing to pull {} ({})",
+                remote_path,
+                file_type.as_str()
+            )));
+        }
+        let size: u64 = size.parse().map_err(|_| {
+            Error::Adb(format!(
+                "unexpected stat output for {}: {:?}",
+                remote_path, output
+            ))
+        })?;
+        if size > max_bytes {
+            return Err(Error::Adb(format!(
+                "{} is {} bytes, above the {} byte pull limit",
+                remote_path, size, max_bytes
+            )));
         }
+        Ok(size)
+    }
 
-        // Pull to temporary file
-        cmd.arg("pull").arg(remote_path).arg(&temp_file);
+    /// Stat every entry on the device (except `/proc`). Usually needs root to be useful.
+    /// Collects the whole listing; prefer `load_all_with` on large devices.
+    pub fn load_all(&self) -> Result<Vec<(OsString, FileInfo)>> {
+        self.load_path(Path::new("/"))
+    }
 
-        let output = cmd.output().context("Failed to execute adb pull")?;
+    /// Streaming form of `load_all`: entries go to `on_entry` as they are parsed, so the
+    /// full listing is never held in memory. Returns the number of entries delivered.
+    pub fn load_all_with(&self, on_entry: impl FnMut(OsString, FileInfo)) -> Result<usize> {
+        self.load_all_excluding(&[DEFAULT_SCAN_EXCLUDE], None, on_entry)
+    }
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let _ = fs::remove_file(&temp_file); // Clean up if it exists
-            return Err(anyhow!("ADB pull failed: {}", stderr));
-        }
+    /// `load_all_with` skipping the subtrees in `excludes` (absolute paths such as `/sys`)
+    /// instead of just `/proc`; an empty list scans everything. Stops with
+    /// `Error::Cancelled` once `cancel` is set.
+    pub fn load_all_excluding(
+        &self,
+        excludes: &[&str],
+        cancel: Option<&AtomicBool>,
+        on_entry: impl FnMut(OsString, FileInfo),
+    ) -> Result<usize> {
+        self.load_all_scoped(excludes, None, cancel, on_entry)
+    }
 
-        // Read the temporary file
-        let data = fs::read(&temp_file).context("Failed to read temporary file")?;
+    /// `load_all_excluding` that stops descending `max_depth` levels below `/`
+    /// (`find / -maxdepth N`): `Some(1)` lists `/system`, `/data`... but not their contents.
+    /// Entries within the depth are stat-ed as usual, timestamps included.
+    pub fn load_all_scoped(
+        &self,
+        excludes: &[&str],
+        max_depth: Option<usize>,
+        cancel: Option<&AtomicBool>,
+        on_entry: impl FnMut(OsString, FileInfo),
+    ) -> Result<usize> {
+        let excludes = scan_excludes(excludes)?;
+        self.load_path_until(Path::new("/"), &excludes, max_depth, cancel, on_entry)
+    }
 
-        // Clean up
-        let _ = fs::remove_file(&temp_file);
+    /// `load_all` split by top-level directory, scanned by up to `jobs` adb shells at once.
+    /// Top-level entries that fail to scan (e.g. unreadable without root) are skipped with a
+    /// warning; an entry reported by more than one scan is kept once.
+    ///
+    /// Each worker buffers its entries until every scan is done; `/proc` is left out.
+    pub fn load_all_parallel(&self, jobs: usize) -> Result<Vec<(OsString, FileInfo)>> {
+        let tops: Vec<String> = self
+            .exec_pty("find / -mindepth 1 -maxdepth 1 ! -path /proc")?
+            .into_iter()
+            .filter(|line| line.starts_with('/'))
+            .collect();
+        let queue = Mutex::new(tops.into_iter());
 
-        Ok(data)
+        let scans: Vec<Result<Vec<(OsString, FileInfo)>>> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..jobs.max(1))
+                .map(|_| {
+                    scope.spawn(|| -> Result<Vec<(OsString, FileInfo)>> {
+                        let mut entries = Vec::new();
+                        loop {
+                            let next = queue.lock().unwrap().next();
+                            let Some(top) = next else { break };
+                            let scanned =
+                                self.load_path_with(&top, |path, info| entries.push((path, info)));
+                            match scanned {
+                                Ok(_) => {}
+                                Err(Error::Adb(msg)) => warn!("skipping {}: {}", top, msg),
+                                Err(e) => return Err(e),
+                            }
+                        }
+                        Ok(entries)
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|_| Err(Error::Other("scan worker panicked".to_string())))
+                })
+                .collect()
+        });
+
+        let mut results = self.stat_paths(&["/".to_string()])?;
+        let mut seen: HashSet<OsString> = results.iter().map(|(path, _)| path.clone()).collect();
+        for scan in scans {
+            for (path, info) in scan? {
+                if seen.insert(path.clone()) {
+                    results.push((path, info));
+                }
+            }
+        }
+        debug!("Loaded {} file entries from ADB", results.len());
+        Ok(results)
     }
 
-    pub fn load_all(&self) -> Result<Vec<(OsString, FileInfo)>> {
-        // find / -print0 | xargs -0 stat -c "%i|%A|%Z_%Y_%X|%U|%G|%s|%N"
-        // find / -path /proc -prune -o -exec stat -c \"%i|%A|%Z|%Y|%X|%U|%G|%s|%N\" {} +
-        let output = self.exec_pty(
-            "find / -path /proc -prune -o -print0 | xargs -0 stat -c \"%i|%A|%Z|%Y|%X|%U|%G|%s|%N\"",
-        )?;
+    /// Stat every entry below `root` (inclusive), e.g. `/sdcard`.
+    /// Works without root as long as the shell user can read the subtree.
+    pub fn load_path(&self, root: impl AsRef<Path>) -> Result<Vec<(OsString, FileInfo)>> {
         let mut results: Vec<(OsString, FileInfo)> = Vec::new();
-        for line in output {
-            let parts: Vec<&str> = line.splitn(9, '|').collect();
-            if parts.len() < 9 {
-                continue;
+        self.load_path_with(root.as_ref(), |path, info| results.push((path, info)))?;
+        debug!("Loaded {} file entries from ADB", results.len());
+        Ok(results)
+    }
+
+    /// Streaming form of `load_path`: `on_entry` sees each entry as soon as its stat line
+    /// arrives. Returns the number of entries delivered.
+    pub fn load_path_with(
+        &self,
+        root: impl AsRef<Path>,
+        on_entry: impl FnMut(OsString, FileInfo),
+    ) -> Result<usize> {
+        self.load_path_until(root.as_ref(), &[], None, None, on_entry)
+    }
+
+    /// `load_path_with` that stops with `Error::Cancelled` as soon as `cancel` is set.
+    pub fn load_path_cancellable(
+        &self,
+        root: impl AsRef<Path>,
+        cancel: &AtomicBool,
+        on_entry: impl FnMut(OsString, FileInfo),
+    ) -> Result<usize> {
+        self.load_path_until(root.as_ref(), &[], None, Some(cancel), on_entry)
+    }
+
+    /// Shared scan behind the `load_*` methods, `excludes` as returned by `scan_excludes`.
+    pub(super) fn load_path_until(
+        &self,
+        root: &Path,
+        excludes: &[String],
+        max_depth: Option<usize>,
+        cancel: Option<&AtomicBool>,
+        mut on_entry: impl FnMut(OsString, FileInfo),
+    ) -> Result<usize> {
+        let command = stat_tree_command(root, excludes, max_depth);
+        let mut entries = 0;
+        self.exec_pty_until(&command, cancel, |line| {
+            if let Some((path, info)) = parse_stat_line(line) {
+                entries += 1;
+                on_entry(path, info);
             }
-            let path_part = parts[8];
-            let path = path_part
-                .split("->")
-                .next()
-                .unwrap_or("")
-                .trim_matches('\'')
-                .to_string();
-
-            let file_info = FileInfo {
-                inode: parts[0].parse().unwrap_or(0),
-                permissions: parts[1].to_string(),
-                modified_time: parts[3].parse().unwrap_or(0),
-                accessed_time: parts[4].parse().unwrap_or(0),
-                created_time: parts[2].parse().unwrap_or(0),
-                user: parts[5].to_string(),
-                group: parts[6].to_string(),
-                size: parts[7].parse().unwrap_or(0),
-            };
+        })?;
+        Ok(entries)
+    }
 
-            results.push((path.into(), file_info));
+    /// Number of entries `load_path(root)` would return, counted without stat-ing them.
+    pub fn count_path(&self, root: impl AsRef<Path>) -> Result<usize> {
+        let root = root.as_ref().to_string_lossy();
+        let command = if root == "/" {
+            "find / -path /proc -prune -o -print | wc -l".to_string()
+        } else {
+            format!("find {} 2>/dev/null | wc -l", shell_quote(&root))
+        };
+        let output = self.exec_pty(&command)?;
+        output
+            .iter()
+            .find_map(|line| line.trim().parse().ok())
+            .ok_or_else(|| Error::Adb(format!("unexpected `{}` output: {:?}", command, output)))
+    }
+
+    /// Stat a specific set of paths, in batches to stay below the shell's argument limit.
+    /// Paths that no longer exist are silently missing from the result.
+    pub fn stat_paths(&self, paths: &[String]) -> Result<Vec<(OsString, FileInfo)>> {
+        const BATCH: usize = 200;
+        let mut results = Vec::with_capacity(paths.len());
+        for batch in paths.chunks(BATCH) {
+            let quoted: Vec<String> = batch.iter().map(|p| shell_quote(p)).collect();
+            let output = self.exec_pty(&format!(
+                "stat -c \"%i|%A|%Z|%Y|%X|%U|%G|%s|%N\" {} 2>/dev/null",
+                quoted.join(" ")
+            ))?;
+            results.extend(parse_stat_lines(&output));
         }
-        println!("Loaded {} file entries from ADB", results.len());
         Ok(results)
     }
 
@@ -231,23 +604,29 @@ impl AdbHelper {
     /// # Returns
     /// Vector of (path, modified_timestamp) tuples
     pub fn list_all(&self) -> Result<Vec<(String, usize)>> {
-        let output = self.exec_shell("find / -d -printf \"%T@|%p\\n\"")?;
         let mut results = Vec::new();
-        for line in output.lines() {
-            let parts: Vec<&str> = line.splitn(2, '|').collect();
-            if parts.len() != 2 {
-                continue;
-            }
-            let path = parts[1].to_string();
-            let timestamp = parts[0]
+        self.list_all_with(|path, mtime| results.push((path, mtime)))?;
+        Ok(results)
+    }
+
+    /// Streaming form of `list_all`: `on_entry` gets each `(path, mtime)` as its line
+    /// arrives. Returns the number of entries delivered.
+    pub fn list_all_with(&self, mut on_entry: impl FnMut(String, usize)) -> Result<usize> {
+        let mut entries = 0;
+        self.exec_pty_lines("find / -d -printf \"%T@|%p\\n\"", |line| {
+            let Some((timestamp, path)) = line.split_once('|') else {
+                return;
+            };
+            let mtime = timestamp
                 .split('.')
                 .next()
                 .unwrap_or("0")
                 .parse()
                 .unwrap_or(0);
-            results.push((path, timestamp as usize));
-        }
-        Ok(results)
+            entries += 1;
+            on_entry(path.to_string(), mtime);
+        })?;
+        Ok(entries)
     }
 
     pub fn list_active_apps_users(&self) -> Result<HashMap<String, String>> {
@@ -279,7 +658,7 @@ impl AdbHelper {
     /// Vector of file/directory names (not full paths)
     pub fn list_files(&self, path: impl AsRef<Path>) -> Result<Vec<String>> {
         let path = path.as_ref().to_string_lossy();
-        let output = self.exec_shell(&format!("ls '{}'", path))?;
+        let output = self.exec_shell(&format!("ls {}", shell_quote(&path)))?;
 
         let files: Vec<String> = output
             .lines()
@@ -293,7 +672,7 @@ impl AdbHelper {
     /// List all folders recursively in a directory
     pub fn list_folders_tree(&self, path: impl AsRef<Path>) -> Result<Vec<String>> {
         let path = path.as_ref().to_string_lossy();
-        let output = self.exec_shell(&format!("find '{}' -type d -print", path))?;
+        let output = self.exec_shell(&format!("find {} -type d -print", shell_quote(&path)))?;
 
         let folders: Vec<String> = output
             .lines()
@@ -387,9 +766,245 @@ impl AdbHelper {
     ///
     /// # Returns
     /// Raw bytes of the file content
+    ///
+    /// Only regular files are pulled, within `DEFAULT_PULL_MAX_BYTES` and
+    /// `DEFAULT_PULL_TIMEOUT`; see `read_file_with_limits` for other bounds.
     pub fn read_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        self.read_file_with_limits(path, DEFAULT_PULL_MAX_BYTES, DEFAULT_PULL_TIMEOUT)
+    }
+
+    /// `read_file` that gives up with an error once the file turns out larger than
+    /// `max_bytes` or the pull runs longer than `timeout`. Device nodes, pipes and sockets
+    /// are refused up front.
+    pub fn read_file_with_limits(
+        &self,
+        path: impl AsRef<Path>,
+        max_bytes: u64,
+        timeout: std::time::Duration,
+    ) -> Result<Vec<u8>> {
         let path_str = path.as_ref().to_string_lossy();
-        self.exec_pull(&path_str)
+        self.exec_pull(&path_str, max_bytes, timeout)
+    }
+
+    /// Pull `remote` straight into the local file `local`, calling `on_progress` with the
+    /// bytes written so far. Returns the final size. Device nodes, pipes and sockets are
+    /// refused up front.
+    ///
+    /// Goes through `adb pull` (progress as the local copy grows), except under a `su` root
+    /// method: adbd would read as the shell user, so the file is then read through the
+    /// root shell instead, a few MiB per call.
+    pub fn pull_to(
+        &self,
+        remote: impl AsRef<Path>,
+        local: impl AsRef<Path>,
+        on_progress: impl FnMut(u64),
+    ) -> Result<u64> {
+        let path_str = remote.as_ref().to_string_lossy();
+        let total = self.check_pullable(&path_str, u64::MAX)?;
+        if self.root.wraps_shell() {
+            self.shell_pull(&path_str, local.as_ref(), total, on_progress)
+        } else {
+            self.adb_pull(&path_str, local.as_ref(), on_progress)
+        }
+    }
+
+    /// `adb pull` of `remote` into `local` through the backend.
+    fn adb_pull(
+        &self,
+        remote: &str,
+        local: &Path,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<u64> {
+        self.backend.pull(
+            &self.command(&["pull", remote]),
+            local,
+            u64::MAX,
+            None,
+            &mut on_progress,
+        )
+    }
+
+    /// Pull `remote` into `local`, continuing a partial copy left by an earlier interrupted
+    /// run instead of starting over. Returns the final size.
+    ///
+    /// The missing tail is read with `dd` in chunks of a few MiB, each appended to `local`
+    /// as it arrives, so a dropped connection loses at most one chunk. A local file larger
+    /// than the remote one is discarded. Falls back to a plain `pull_to` when the device's
+    /// `dd` lacks `iflag=skip_bytes,count_bytes`. The chunks are read through the shell,
+    /// so the root method applies.
+    pub fn pull_resumable(&self, remote: impl AsRef<Path>, local: impl AsRef<Path>) -> Result<u64> {
+        let path_str = remote.as_ref().to_string_lossy();
+        let local = local.as_ref();
+        let total = self.check_pullable(&path_str, u64::MAX)?;
+        let mut offset = std::fs::metadata(local).map_or(0, |meta| meta.len());
+        if offset > total {
+            debug!(
+                "{} is larger than {}, starting over",
+                local.display(),
+                path_str
+            );
+            offset = 0;
+        }
+        if offset == total && total > 0 {
+            return Ok(total);
+        }
+        if !self.dd_supports_byte_offsets() {
+            debug!("dd cannot seek by bytes, pulling {} from scratch", path_str);
+            return self.pull_to(remote, local, |_| {});
+        }
+
+        self.append_chunks(&path_str, local, offset, total, |_| {})
+    }
+
+    /// Read all of `path_str` (`total` bytes) through the shell, so the root method
+    /// applies, replacing `local`.
+    fn shell_pull(
+        &self,
+        path_str: &str,
+        local: &Path,
+        total: u64,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<u64> {
+        if self.dd_supports_byte_offsets() {
+            return self.append_chunks(path_str, local, 0, total, on_progress);
+        }
+        // dd cannot seek by bytes, read it in one go
+        let data = self.exec_shell_bytes(&format!("cat {}", shell_quote(path_str)))?;
+        std::fs::write(local, &data)?;
+        on_progress(data.len() as u64);
+        Ok(data.len() as u64)
+    }
+
+    /// Append bytes `offset..total` of `path_str` to `local` with `dd`, one chunk per
+    /// shell call, reporting the local size to `on_progress` after each chunk.
+    fn append_chunks(
+        &self,
+        path_str: &str,
+        local: &Path,
+        mut offset: u64,
+        total: u64,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<u64> {
+        use std::io::Write;
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(local)?;
+        file.set_len(offset)?;
+        while offset < total {
+            let len = RESUME_CHUNK_BYTES.min(total - offset);
+            let chunk = self.exec_shell_bytes(&format!(
+                "dd if={} bs=65536 skip={} count={} iflag=skip_bytes,count_bytes 2>/dev/null",
+                shell_quote(path_str),
+                offset,
+                len
+            ))?;
+            if chunk.is_empty() {
+                return Err(Error::Adb(format!(
+                    "{} ended at {} bytes, expected {}",
+                    path_str, offset, total
+                )));
+            }
+            file.write_all(&chunk)?;
+            file.flush()?;
+            offset += chunk.len() as u64;
+            on_progress(offset);
+        }
+
+        let size = std::fs::metadata(local)?.len();
+        if size != total {
+            return Err(Error::Adb(format!(
+                "pulled {} bytes of {} into {}, expected {}",
+                size,
+                path_str,
+                local.display(),
+                total
+            )));
+        }
+        Ok(size)
+    }
+
+    /// `pull_resumable` that also compares the SHA-256 of the local copy with `sha256`
+    /// of the remote file. A mismatching copy is deleted so the next attempt starts over.
+    pub fn pull_resumable_verified(
+        &self,
+        remote: impl AsRef<Path>,
+        local: impl AsRef<Path>,
+    ) -> Result<u64> {
+        use sha2::{Digest, Sha256};
+
+        let local = local.as_ref();
+        let size = self.pull_resumable(remote.as_ref(), local)?;
+        let expected = self.sha256(remote.as_ref())?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut std::fs::File::open(local)?, &mut hasher)?;
+        let actual = format!("{:x}", hasher.finalize());
+        if actual != expected {
+            let _ = std::fs::remove_file(local);
+            return Err(Error::Adb(format!(
+                "{} does not match {}: sha256 {} != {}",
+                local.display(),
+                remote.as_ref().display(),
+                actual,
+                expected
+            )));
+        }
+        Ok(size)
+    }
+
+    /// Whether the device's `dd` understands `iflag=skip_bytes,count_bytes`.
+    fn dd_supports_byte_offsets(&self) -> bool {
+        self.exec_shell_bytes(
+            "dd if=/dev/zero bs=4 skip=1 count=1 iflag=skip_bytes,count_bytes 2>/dev/null",
+        )
+        .is_ok_and(|out| out.len() == 1)
+    }
+
+    /// SHA-256 of a file on the device, as lowercase hex.
+    ///
+    /// Hashes on-device with `sha256sum` (or `toybox sha256sum`) to avoid transferring the
+    /// file; when neither is available the file is pulled and hashed locally.
+    pub fn sha256(&self, remote: impl AsRef<Path>) -> Result<String> {
+        let path_str = remote.as_ref().to_string_lossy();
+        for tool in ["sha256sum", "toybox sha256sum"] {
+            if let Ok(output) = self.exec_shell(&format!("{} {}", tool, shell_quote(&path_str))) {
+                // Output format: "<hash>  <path>"
+                if let Some(hash) = output.split_whitespace().next() {
+                    if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Ok(hash.to_ascii_lowercase());
+                    }
+                }
+            }
+        }
+
+        use sha2::{Digest, Sha256};
+        let data = self.exec_pull(&path_str, DEFAULT_PULL_MAX_BYTES, DEFAULT_PULL_TIMEOUT)?;
+        Ok(format!("{:x}", Sha256::digest(&data)))
+    }
+
+    /// Follow a file on the device like `tail -F`, yielding lines as they are written.
+    ///
+    /// The returned iterator blocks until the next line arrives and ends when the device
+    /// side closes. Dropping it kills and reaps the underlying `adb` process.
+    pub fn tail_follow(&self, remote: impl AsRef<Path>) -> Result<TailFollow> {
+        self.ensure_adb_root()?;
+        let path_str = remote.as_ref().to_string_lossy();
+        let tail = format!("tail -n 0 -F {}", shell_quote(&path_str));
+        let mut child = self
+            .command(&["shell", &self.root.wrap(&tail)])
+            .to_process()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::Adb(format!("Failed to execute adb tail: {}", e)))?;
+        let stdout = child.stdout.take().unwrap();
+
+        Ok(TailFollow {
+            child,
+            reader: BufReader::new(stdout),
+        })
     }
 
     /// Read a text file as UTF-8 string
@@ -401,7 +1016,47 @@ impl AdbHelper {
     /// File content as string
     pub fn read_text_file(&self, path: impl AsRef<Path>) -> Result<String> {
         let bytes = self.read_file(path)?;
-        String::from_utf8(bytes).context("File content is not valid UTF-8")
+        String::from_utf8(bytes)
+            .map_err(|e| Error::Other(format!("File content is not valid UTF-8: {}", e)))
+    }
+
+    /// Read a file as text, replacing invalid UTF-8 with U+FFFD instead of failing.
+    /// Check `is_probably_binary` on `read_file` output first to decide on a hex view.
+    pub fn read_text_lossy(&self, path: impl AsRef<Path>) -> Result<String> {
+        let bytes = self.read_file(path)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// First `max_lines` lines of a text file, read on-device with `head` so large
+    /// logs aren't pulled just to show their beginning. Fails on binary content
+    /// (see `is_probably_binary`); invalid UTF-8 is replaced.
+    pub fn read_lines(&self, remote: impl AsRef<Path>, max_lines: usize) -> Result<Vec<String>> {
+        let path_str = remote.as_ref().to_string_lossy();
+        let bytes =
+            self.exec_shell_bytes(&format!("head -n {} {}", max_lines, shell_quote(&path_str)))?;
+        if is_probably_binary(&bytes) {
+            return Err(Error::Other(format!(
+                "{} looks like a binary file",
+                path_str
+            )));
+        }
+        Ok(String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// `len` bytes starting at byte offset `start`, cut on-device with `tail -c | head -c`.
+    /// Shorter than `len` when the file ends first.
+    pub fn read_range(&self, remote: impl AsRef<Path>, start: u64, len: usize) -> Result<Vec<u8>> {
+        let path_str = remote.as_ref().to_string_lossy();
+        // tail -c +N is 1-based
+        self.exec_shell_bytes(&format!(
+            "tail -c +{} {} | head -c {}",
+            start + 1,
+            shell_quote(&path_str),
+            len
+        ))
     }
 
     // pub fn list_files_detailed(&self, path: impl AsRef<Path>) -> Result<Vec<FileInfo>> {
@@ -428,3 +1083,163 @@ impl AdbHelper {
 
     // #endregion
 }
+
+/// Quote `s` as a single POSIX shell word, safe to splice into any command line.
+/// Embedded single quotes are closed, escaped and reopened (`'` -> `'\''`).
+pub(super) fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Console response of `adb emu`: the lines before the closing `OK`, or the `KO` reason.
+pub(super) fn parse_emu_response(command: &str, stdout: &str) -> Result<String> {
+    let mut lines = Vec::new();
+    for line in stdout.lines().map(str::trim_end) {
+        if line == "OK" {
+            break;
+        }
+        if let Some(reason) = line.strip_prefix("KO") {
+            let reason = reason.trim_start_matches(':').trim();
+            return Err(Error::Adb(format!("emu {} failed: {}", command, reason)));
+        }
+        lines.push(line);
+    }
+    Ok(lines.join("\n"))
+}
+
+/// `find | stat` pipeline listing every entry below `root` in the format `parse_stat_line` reads,
+/// pruning each of the (already validated) `excludes` subtrees and, with `max_depth`,
+/// everything deeper than that many levels below `root`.
+fn stat_tree_command(root: &Path, excludes: &[String], max_depth: Option<usize>) -> String {
+    // find / -print0 | xargs -0 stat -c "%i|%A|%Z_%Y_%X|%U|%G|%s|%N"
+    // find / -path /proc -prune -o -exec stat -c \"%i|%A|%Z|%Y|%X|%U|%G|%s|%N\" {} +
+    let root = root.to_string_lossy();
+    // one `-path X -prune -o` clause per excluded subtree, the final `-print0` gets the rest
+    // -maxdepth is a global option and has to come before the tests
+    let mut prune = max_depth
+        .map(|depth| format!("-maxdepth {} ", depth))
+        .unwrap_or_default();
+    prune.extend(
+        excludes
+            .iter()
+            .map(|path| format!("-path {} -prune -o ", shell_quote(path))),
+    );
+    if root == "/" {
+        format!(
+            "find / {}-print0 | xargs -0 stat -c \"%i|%A|%Z|%Y|%X|%U|%G|%s|%N\"",
+            prune
+        )
+    } else {
+        format!(
+            "find {} {}-print0 2>/dev/null | xargs -0 stat -c \"%i|%A|%Z|%Y|%X|%U|%G|%s|%N\"",
+            shell_quote(&root),
+            prune
+        )
+    }
+}
+
+/// Check scan excludes and normalize them the way `find -path` matches: absolute,
+/// without `.`/`..` segments or a trailing slash, and not the device root.
+pub(super) fn scan_excludes(excludes: &[&str]) -> Result<Vec<String>> {
+    excludes
+        .iter()
+        .map(|path| {
+            if !path.starts_with('/') {
+                return Err(Error::Other(format!(
+                    "scan exclude {:?} is not an absolute path",
+                    path
+                )));
+            }
+            let canonical = crate::fs::FileSystem::canonical_path(Path::new(path));
+            if canonical == Path::new("/") {
+                return Err(Error::Other(format!(
+                    "scan exclude {:?} would skip the whole device",
+                    path
+                )));
+            }
+            Ok(canonical.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// Parse `stat -c "%i|%A|%Z|%Y|%X|%U|%G|%s|%N"` output lines into `(path, FileInfo)` pairs.
+/// Lines that don't have all nine fields are skipped.
+fn parse_stat_lines(lines: &[String]) -> Vec<(OsString, FileInfo)> {
+    lines
+        .iter()
+        .filter_map(|line| parse_stat_line(line))
+        .collect()
+}
+
+/// Parse a single stat line, `None` if it doesn't have all nine fields, the inode is not a
+/// number (not a stat line) or the name is empty.
+///
+/// The name is everything after the eighth `|`, so names containing `|` survive. `%N`
+/// quotes it and appends ` -> 'target'` for symlinks only, so a ` -> ` inside any other
+/// name is kept.
+pub(super) fn parse_stat_line(line: &str) -> Option<(OsString, FileInfo)> {
+    let parts: Vec<&str> = line.splitn(9, '|').collect();
+    if parts.len() < 9 {
+        return None;
+    }
+    let inode = parts[0].trim().parse().ok()?;
+    let name = parts[8].trim();
+    let is_link = parts[1].starts_with('l');
+    // GNU stat quotes the names, toybox doesn't
+    let path = match name.strip_prefix('\'') {
+        Some(quoted) if is_link => quoted
+            .split_once("' -> ")
+            .map_or(quoted.trim_end_matches('\''), |(link, _)| link),
+        Some(quoted) => quoted.strip_suffix('\'').unwrap_or(quoted),
+        None if is_link => name.split_once(" -> ").map_or(name, |(link, _)| link),
+        None => name,
+    };
+    if path.is_empty() {
+        return None;
+    }
+
+    let file_info = FileInfo {
+        inode,
+        permissions: parts[1].to_string(),
+        modified_time: parts[3].parse().unwrap_or(0),
+        accessed_time: parts[4].parse().unwrap_or(0),
+        created_time: parts[2].parse().unwrap_or(0),
+        user: parts[5].to_string(),
+        group: parts[6].to_string(),
+        size: parts[7].parse().unwrap_or(0),
+    };
+
+    Some((path.into(), file_info))
+}
+
+/// Line iterator returned by `AdbHelper::tail_follow`.
+/// Owns the `adb shell tail -F` process and kills it when dropped.
+pub struct TailFollow {
+    child: Child,
+    reader: BufReader<ChildStdout>,
+}
+
+impl Iterator for TailFollow {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                // Strip "\n" and the "\r" some adb versions add
+                let trimmed = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(trimmed);
+                Some(Ok(line))
+            }
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+impl Drop for TailFollow {
+    fn drop(&mut self) {
+        // Kill first (tail -F never exits on its own), then wait to avoid a zombie
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,164 @@
+use crate::error::{Error, Result};
+use crate::fs::filesystem::visit_sorted;
+use crate::fs::{FSNode, FileSystem, FileType};
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Kind of a permission problem reported by `FileSystem::security_findings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// Anyone can write to the entry (`o+w`). Directories with the sticky bit are not flagged.
+    WorldWritable,
+    /// The set-user-ID bit is set on a file.
+    Setuid,
+    /// The set-group-ID bit is set on a file.
+    Setgid,
+    /// Owned by root but writable by a non-root group or by everyone.
+    RootOwnedWritable,
+}
+
+/// A single security finding for one path in the tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub path: PathBuf,
+    pub kind: FindingKind,
+    pub detail: String,
+}
+
+impl FileSystem {
+    /// Scan the cached tree for risky permissions: world-writable entries, setuid/setgid
+    /// files and root-owned entries writable by others. Symlinks are skipped since their
+    /// own mode is always `rwxrwxrwx`. Run `refresh` (or `refresh_path`) first.
+    ///
+    /// Filter the result on `Finding::kind` to look at one class of problem.
+    pub fn security_findings(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let _ = visit_sorted(Path::new(""), &self.root, &mut |path, node| {
+            check_node(path, node, &mut findings);
+            Ok(())
+        });
+        findings
+    }
+
+    /// Every entry in the cached tree owned by the app `app_name`, in path order.
+    ///
+    /// The app is looked up in the device's process list (`list_active_apps_users`) by
+    /// package or process name, so it has to be running; its `package:service`
+    /// sub-processes count too. Apps sharing a uid (`android:sharedUserId`) all map to the
+    /// files of that uid. Both the `u0_a123` user name and the numeric uid (`10123`, as
+    /// shown where the name can't be resolved) are matched. Fails with `Error::Other`
+    /// when no process of the app is found.
+    ///
+    /// Uses the owner index when enabled (`with_owner_index`), a walk of the tree otherwise.
+    pub fn files_by_app(&self, app_name: &str) -> Result<Vec<PathBuf>> {
+        let users = self.adb().list_active_apps_users()?;
+        let owners = app_users(&users, app_name);
+        if owners.is_empty() {
+            return Err(Error::Other(format!(
+                "app {} not found in the process list",
+                app_name
+            )));
+        }
+        let mut files: Vec<PathBuf> = owners
+            .iter()
+            .flat_map(|user| uid_aliases(user))
+            .flat_map(|owner| self.files_owned_by(&owner))
+            .collect();
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+}
+
+/// Users running `app_name`, from a `list_active_apps_users` map (user to comma-separated
+/// process names). A process matches when it is named `app_name` or `app_name:<service>`.
+pub(super) fn app_users(users: &HashMap<String, String>, app_name: &str) -> Vec<String> {
+    let mut matching: Vec<String> = users
+        .iter()
+        .filter(|(_, apps)| {
+            apps.split(',').map(str::trim).any(|process| {
+                process == app_name
+                    || process
+                        .strip_prefix(app_name)
+                        .is_some_and(|rest| rest.starts_with(':'))
+            })
+        })
+        .map(|(user, _)| user.clone())
+        .collect();
+    matching.sort();
+    matching
+}
+
+/// Owner names a file of `user` can carry: the name itself and, for app users
+/// (`u<user>_a<app>`), the numeric uid `user * 100000 + 10000 + app`.
+pub(super) fn uid_aliases(user: &str) -> Vec<String> {
+    let mut aliases = vec![user.to_string()];
+    let numeric = user
+        .strip_prefix('u')
+        .and_then(|rest| rest.split_once("_a"))
+        .and_then(|(user_id, app_id)| {
+            Some((user_id.parse::<u32>().ok()?, app_id.parse::<u32>().ok()?))
+        });
+    if let Some((user_id, app_id)) = numeric {
+        aliases.push((user_id * 100_000 + 10_000 + app_id).to_string());
+    }
+    aliases
+}
+
+fn check_node(path: &Path, node: &FSNode, findings: &mut Vec<Finding>) {
+    if *node.file_type() == FileType::Symlink {
+        return;
+    }
+    let info = node.metadata();
+    // ls-style mode string, e.g. "-rwsr-xr-x"
+    let mode: Vec<char> = info.permissions.chars().collect();
+    if mode.len() < 10 {
+        return;
+    }
+
+    let mut report = |kind: FindingKind, detail: String| {
+        findings.push(Finding {
+            path: path.to_path_buf(),
+            kind,
+            detail,
+        });
+    };
+    let owner = format!("{}:{}", info.user, info.group);
+
+    let group_write = mode[5] == 'w';
+    let sticky = matches!(mode[9], 't' | 'T');
+    let is_dir = *node.file_type() == FileType::Directory;
+    // sticky directories such as /data/local/tmp are shared on purpose
+    let other_write = mode[8] == 'w' && !(is_dir && sticky);
+
+    if other_write {
+        report(
+            FindingKind::WorldWritable,
+            format!("{} is writable by everyone ({})", info.permissions, owner),
+        );
+    }
+    if !is_dir && matches!(mode[3], 's' | 'S') {
+        report(
+            FindingKind::Setuid,
+            format!("{} runs as {}", info.permissions, info.user),
+        );
+    }
+    if !is_dir && matches!(mode[6], 's' | 'S') {
+        report(
+            FindingKind::Setgid,
+            format!("{} runs with group {}", info.permissions, info.group),
+        );
+    }
+    if info.user == "root" && (other_write || (group_write && info.group != "root")) {
+        report(
+            FindingKind::RootOwnedWritable,
+            format!(
+                "{} owned by {} is writable by others",
+                info.permissions, owner
+            ),
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::error::Result;
+use crate::fs::{AdbHelper, FileSystem, RefreshProgress};
+
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// `FileSystem` for async code: scans run on the blocking pool while the previous tree
+/// stays readable, so a GUI or a service never stalls for the length of a full scan.
+///
+/// Queries and serializers are those of `FileSystem`, reached through `read`:
+///
+/// ```ignore
+/// let fs = AsyncFileSystem::new(None);
+/// fs.refresh().await?;
+/// let tree = fs.read().await.to_json(false, false);
+/// ```
+///
+/// Clones share the same tree.
+#[derive(Clone)]
+pub struct AsyncFileSystem {
+    fs: Arc<RwLock<FileSystem>>,
+}
+
+impl AsyncFileSystem {
+    /// Async counterpart of `FileSystem::new`.
+    pub fn new(device_serial: Option<String>) -> Self {
+        Self::from(FileSystem::new(device_serial))
+    }
+
+    /// Async counterpart of `FileSystem::from_adb`.
+    pub fn from_adb(adb: AdbHelper) -> Self {
+        Self::from(FileSystem::from_adb(adb))
+    }
+
+    /// Shared access to the cached tree. Held guards delay the end of a refresh, not its scan.
+    pub async fn read(&self) -> RwLockReadGuard<'_, FileSystem> {
+        self.fs.read().await
+    }
+
+    /// Exclusive access, e.g. for the path-scoped `FileSystem::refresh_path`.
+    /// Blocking calls made through it should go through `spawn_blocking`.
+    pub async fn write(&self) -> RwLockWriteGuard<'_, FileSystem> {
+        self.fs.write().await
+    }
+
+    /// `FileSystem::refresh` without blocking the runtime.
+    pub async fn refresh(&self) -> Result<()> {
+        self.scan(|fs| fs.refresh()).await
+    }
+
+    /// `FileSystem::refresh_with_progress`; `progress` runs on the scanning thread.
+    pub async fn refresh_with_progress(
+        &self,
+        progress: impl FnMut(&RefreshProgress) + Send + 'static,
+    ) -> Result<()> {
+        self.scan(|fs| fs.refresh_with_progress(progress)).await
+    }
+
+    /// `FileSystem::refresh_cancellable`: setting `cancel` ends the scan with
+    /// `Error::Cancelled`.
+    pub async fn refresh_cancellable(
+        &self,
+        cancel: Arc<AtomicBool>,
+        progress: impl FnMut(&RefreshProgress) + Send + 'static,
+    ) -> Result<()> {
+        self.scan(move |fs| fs.refresh_cancellable(&cancel, progress))
+            .await
+    }
+
+    /// Run `refresh` on a fresh tree in the blocking pool and swap it in once it succeeds.
+    /// Unlike the sync refreshes, a failed or cancelled scan leaves the previous tree.
+    async fn scan(
+        &self,
+        refresh: impl FnOnce(&mut FileSystem) -> Result<()> + Send + 'static,
+    ) -> Result<()> {
+        let adb = self.fs.read().await.adb().clone();
+        let scanned = tokio::task::spawn_blocking(move || {
+            let mut fs = FileSystem::from_adb(adb);
+            refresh(&mut fs).map(|_| fs)
+        })
+        .await??;
+        self.fs.write().await.replace_tree(scanned);
+        Ok(())
+    }
+}
+
+impl From<FileSystem> for AsyncFileSystem {
+    fn from(fs: FileSystem) -> Self {
+        Self {
+            fs: Arc::new(RwLock::new(fs)),
+        }
+    }
+}
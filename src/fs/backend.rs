@@ -0,0 +1,448 @@
+use crate::error::{Error, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Marker echoed after every streamed shell command, followed by its exit status.
+const PTY_SENTINEL: &str = "___DF_LV_RO___";
+
+/// How often a running pull is checked against its size and time limits.
+const PULL_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// One adb invocation as `AdbHelper` builds it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdbCommand {
+    /// adb executable, see `AdbHelper::with_adb_path`
+    pub program: String,
+    /// Extra environment, see `AdbHelper::with_env`
+    pub env: Vec<(String, String)>,
+    /// Device passed with `-s`, `None` for adb's default device
+    pub serial: Option<String>,
+    /// Subcommand and its arguments (`["shell", "whoami"]`)
+    pub args: Vec<String>,
+}
+
+impl AdbCommand {
+    /// The subcommand and its arguments joined by spaces, without the serial
+    /// (`shell whoami`, `emu avd name`).
+    pub fn command_line(&self) -> String {
+        self.args.join(" ")
+    }
+
+    /// A `Command` running this invocation.
+    pub fn to_process(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+        if let Some(serial) = &self.serial {
+            cmd.arg("-s").arg(serial);
+        }
+        cmd.args(&self.args);
+        cmd
+    }
+}
+
+/// Executes adb invocations for `AdbHelper`.
+///
+/// `ProcessBackend` spawns the real `adb`; `MockBackend` answers from canned output so
+/// code driving `AdbHelper` can be tested without a device.
+pub trait AdbBackend: Send + Sync {
+    /// Run `cmd` to completion. A non-zero exit is not an error here.
+    fn run(&self, cmd: &AdbCommand) -> Result<Output>;
+
+    /// Run `command` in the shell opened by `cmd` (`adb shell`), handing every output line
+    /// to `on_line` as it arrives. Returns the number of lines delivered.
+    ///
+    /// Fails with `Error::Adb` if the command exits non-zero without output, and with
+    /// `Error::Cancelled` once `cancel` is set.
+    fn run_lines(
+        &self,
+        cmd: &AdbCommand,
+        command: &str,
+        cancel: Option<&AtomicBool>,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<usize>;
+
+    /// Run `cmd` (`adb pull <remote>`) with `local` as the destination, calling
+    /// `on_progress` with the local size as it grows. Returns the final size.
+    ///
+    /// Stops the transfer with `Error::Adb` once `local` grows past `max_bytes` and with
+    /// `Error::Timeout` after `timeout`; `local` may then hold a partial copy.
+    fn pull(
+        &self,
+        cmd: &AdbCommand,
+        local: &Path,
+        max_bytes: u64,
+        timeout: Option<Duration>,
+        on_progress: &mut dyn FnMut(u64),
+    ) -> Result<u64>;
+}
+
+/// Spawns the `adb` executable, the default backend.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessBackend;
+
+impl AdbBackend for ProcessBackend {
+    fn run(&self, cmd: &AdbCommand) -> Result<Output> {
+        cmd.to_process().stdin(Stdio::null()).output().map_err(|e| {
+            Error::Adb(format!(
+                "Failed to execute adb {}: {}",
+                cmd.command_line(),
+                e
+            ))
+        })
+    }
+
+    fn run_lines(
+        &self,
+        cmd: &AdbCommand,
+        command: &str,
+        cancel: Option<&AtomicBool>,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<usize> {
+        // Execute multiple commands in interactive shell with root access
+        let mut child = cmd
+            .to_process()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let mut stderr = child.stderr.take().unwrap();
+        let mut reader = BufReader::new(stdout);
+
+        // Drain stderr concurrently so a chatty command can't block on a full pipe
+        let stderr_reader = std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr.read_to_string(&mut buf);
+            buf
+        });
+
+        // Send commands
+        writeln!(stdin, "{}", command)?;
+        // The sentinel line carries the command's exit status; the leading newline
+        // terminates output that doesn't end with one
+        writeln!(stdin, "printf '\\n{} %d\\n' $?", PTY_SENTINEL)?; //TODO: change to unique random token
+        stdin.flush()?;
+
+        let mut delivered = 0;
+        let mut status: Option<i32> = None;
+        // Empty lines are held back until we know whether the last one is the
+        // sentinel's leading newline, which is dropped
+        let mut pending_empty: usize = 0;
+        // Read output
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            if cancel.is_some_and(|c| c.load(Ordering::SeqCst)) {
+                // Killing adb hangs up the device shell, which stops the remote command too
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = stderr_reader.join();
+                return Err(Error::Cancelled);
+            }
+            let trimmed = line.trim_end_matches(['\n', '\r']);
+            if let Some(code) = trimmed.strip_prefix(PTY_SENTINEL) {
+                status = code.trim().parse().ok();
+                pending_empty = pending_empty.saturating_sub(1);
+                break;
+            }
+            if trimmed.is_empty() {
+                pending_empty += 1;
+            } else {
+                delivered += pending_empty + 1;
+                for _ in 0..std::mem::take(&mut pending_empty) {
+                    on_line("");
+                }
+                on_line(trimmed);
+            }
+            line.clear();
+        }
+        for _ in 0..pending_empty {
+            on_line("");
+        }
+        delivered += pending_empty;
+
+        // Closing stdin ends the (root) shell, which lets the stderr reader finish
+        drop(stdin);
+        drop(reader);
+        let _ = child.wait();
+        let stderr = stderr_reader.join().unwrap_or_default();
+        check_shell_status(command, status, delivered, stderr.trim())?;
+        Ok(delivered)
+    }
+
+    fn pull(
+        &self,
+        cmd: &AdbCommand,
+        local: &Path,
+        max_bytes: u64,
+        timeout: Option<Duration>,
+        on_progress: &mut dyn FnMut(u64),
+    ) -> Result<u64> {
+        let mut child = cmd
+            .to_process()
+            .arg(local)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::Adb(format!("Failed to execute adb pull: {}", e)))?;
+
+        let started = Instant::now();
+        let mut reported = None;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            let size = std::fs::metadata(local).map_or(0, |meta| meta.len());
+            let exceeded = if size > max_bytes {
+                Some(pull_limit_error(cmd, max_bytes))
+            } else {
+                timeout
+                    .filter(|timeout| started.elapsed() >= *timeout)
+                    .map(|timeout| {
+                        Error::Timeout(format!(
+                            "pulling {} took longer than {:?}",
+                            pull_remote(cmd),
+                            timeout
+                        ))
+                    })
+            };
+            if let Some(err) = exceeded {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err);
+            }
+            if reported != Some(size) {
+                on_progress(size);
+                reported = Some(size);
+            }
+            std::thread::sleep(PULL_POLL_INTERVAL);
+        };
+
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            return Err(Error::Adb(format!("ADB pull failed: {}", stderr.trim())));
+        }
+        let size = std::fs::metadata(local)?.len();
+        if size > max_bytes {
+            return Err(pull_limit_error(cmd, max_bytes));
+        }
+        on_progress(size);
+        Ok(size)
+    }
+}
+
+/// Remote path of an `adb pull <remote>` invocation.
+fn pull_remote(cmd: &AdbCommand) -> &str {
+    cmd.args.get(1).map_or("", String::as_str)
+}
+
+/// Error for a pull that grew past `max_bytes`.
+fn pull_limit_error(cmd: &AdbCommand, max_bytes: u64) -> Error {
+    Error::Adb(format!(
+        "{} exceeds the {} byte pull limit",
+        pull_remote(cmd),
+        max_bytes
+    ))
+}
+
+/// Error for a streamed shell command that exited with `status` after `delivered` lines.
+fn check_shell_status(
+    command: &str,
+    status: Option<i32>,
+    delivered: usize,
+    stderr: &str,
+) -> Result<()> {
+    match status {
+        Some(0) => Ok(()),
+        // Partial results (e.g. find hitting unreadable entries) are still useful
+        Some(code) if delivered > 0 => {
+            if !stderr.is_empty() {
+                warn!("adb shell `{}` exited with {}: {}", command, code, stderr);
+            }
+            Ok(())
+        }
+        Some(code) => Err(Error::Adb(format!(
+            "`{}` exited with {}: {}",
+            command, code, stderr
+        ))),
+        None => Err(Error::Adb(format!(
+            "shell ended before `{}` completed: {}",
+            command, stderr
+        ))),
+    }
+}
+
+/// Canned answer of a `MockBackend`.
+#[derive(Debug, Clone, Default)]
+struct MockResponse {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    code: i32,
+    /// Fail the invocation itself instead of answering
+    io_error: Option<std::io::ErrorKind>,
+}
+
+/// Backend answering from canned output and recording every invocation, for tests.
+///
+/// Responses are matched on the command line, `shell <command>` for shell commands
+/// (`shell whoami`, `shell find '/sdcard' -print0 ...`): the first registered prefix
+/// that matches wins. Unmatched invocations exit with status 1. A pull writes the
+/// canned stdout to the local file.
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    responses: Mutex<Vec<(String, MockResponse)>>,
+    calls: Mutex<Vec<AdbCommand>>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer command lines starting with `prefix` with `stdout` and a zero exit status.
+    pub fn with_response(self, prefix: impl Into<String>, stdout: impl Into<Vec<u8>>) -> Self {
+        self.with_exit(prefix, stdout, "", 0)
+    }
+
+    /// Answer command lines starting with `prefix` with the given output and exit status.
+    pub fn with_exit(
+        self,
+        prefix: impl Into<String>,
+        stdout: impl Into<Vec<u8>>,
+        stderr: impl Into<Vec<u8>>,
+        code: i32,
+    ) -> Self {
+        self.responses.lock().unwrap().push((
+            prefix.into(),
+            MockResponse {
+                stdout: stdout.into(),
+                stderr: stderr.into(),
+                code,
+                io_error: None,
+            },
+        ));
+        self
+    }
+
+    /// Fail command lines starting with `prefix` with an I/O error of `kind`, as when
+    /// the pipe to adb breaks.
+    pub fn with_io_error(self, prefix: impl Into<String>, kind: std::io::ErrorKind) -> Self {
+        self.responses.lock().unwrap().push((
+            prefix.into(),
+            MockResponse {
+                io_error: Some(kind),
+                ..Default::default()
+            },
+        ));
+        self
+    }
+
+    /// Every invocation so far, in order. Shell commands appear with the command
+    /// appended to `args`, as `adb shell <command>` would run it, and pulls with their
+    /// local destination.
+    pub fn calls(&self) -> Vec<AdbCommand> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn answer(&self, cmd: AdbCommand) -> Result<MockResponse> {
+        let line = cmd.command_line();
+        self.calls.lock().unwrap().push(cmd);
+        let response = self
+            .responses
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(prefix, _)| line.starts_with(prefix.as_str()))
+            .map(|(_, response)| response.clone())
+            .unwrap_or_else(|| MockResponse {
+                stderr: format!("no canned response for `{}`", line).into_bytes(),
+                code: 1,
+                ..Default::default()
+            });
+        match response.io_error {
+            Some(kind) => Err(Error::Io(kind.into())),
+            None => Ok(response),
+        }
+    }
+}
+
+impl AdbBackend for MockBackend {
+    fn run(&self, cmd: &AdbCommand) -> Result<Output> {
+        let response = self.answer(cmd.clone())?;
+        Ok(Output {
+            status: exit_status(response.code),
+            stdout: response.stdout,
+            stderr: response.stderr,
+        })
+    }
+
+    fn run_lines(
+        &self,
+        cmd: &AdbCommand,
+        command: &str,
+        cancel: Option<&AtomicBool>,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<usize> {
+        let mut shell = cmd.clone();
+        shell.args.push(command.to_string());
+        let response = self.answer(shell)?;
+        let stdout = String::from_utf8_lossy(&response.stdout);
+        let mut delivered = 0;
+        for line in stdout.lines() {
+            if cancel.is_some_and(|c| c.load(Ordering::SeqCst)) {
+                return Err(Error::Cancelled);
+            }
+            on_line(line.trim_end_matches('\r'));
+            delivered += 1;
+        }
+        let stderr = String::from_utf8_lossy(&response.stderr);
+        check_shell_status(command, Some(response.code), delivered, stderr.trim())?;
+        Ok(delivered)
+    }
+
+    fn pull(
+        &self,
+        cmd: &AdbCommand,
+        local: &Path,
+        max_bytes: u64,
+        _timeout: Option<Duration>,
+        on_progress: &mut dyn FnMut(u64),
+    ) -> Result<u64> {
+        let mut pull = cmd.clone();
+        pull.args.push(local.to_string_lossy().into_owned());
+        let response = self.answer(pull)?;
+        if response.code != 0 {
+            let stderr = String::from_utf8_lossy(&response.stderr);
+            return Err(Error::Adb(format!("ADB pull failed: {}", stderr.trim())));
+        }
+        if response.stdout.len() as u64 > max_bytes {
+            return Err(pull_limit_error(cmd, max_bytes));
+        }
+        std::fs::write(local, &response.stdout)?;
+        let size = response.stdout.len() as u64;
+        on_progress(size);
+        Ok(size)
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    // wait status layout: the exit code lives in the second byte
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
@@ -0,0 +1,176 @@
+use crate::error::{Error, Result};
+use crate::fs::adb::shell_quote;
+use crate::fs::AdbHelper;
+
+use std::collections::HashMap;
+
+/// Parsed `dumpsys battery`.
+///
+/// Every `key: value` line is kept in `fields`; the common ones are also typed.
+/// `status` and `health` are the raw `BatteryManager` constants.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatteryDump {
+    pub ac_powered: bool,
+    pub usb_powered: bool,
+    pub wireless_powered: bool,
+    pub present: bool,
+    pub status: Option<i32>,
+    pub health: Option<i32>,
+    pub level: Option<i32>,
+    pub scale: Option<i32>,
+    pub voltage_mv: Option<i32>,
+    /// Degrees Celsius (dumpsys reports tenths of a degree)
+    pub temperature_c: Option<f32>,
+    pub technology: Option<String>,
+    pub fields: HashMap<String, String>,
+}
+
+impl BatteryDump {
+    /// Charge in percent, from `level` and `scale`.
+    pub fn percent(&self) -> Option<f32> {
+        match (self.level, self.scale) {
+            (Some(level), Some(scale)) if scale > 0 => Some(level as f32 * 100.0 / scale as f32),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed `dumpsys meminfo <package>`, all sizes in KB.
+///
+/// `summary` holds the PSS column of the "App Summary" section keyed by label
+/// (e.g. "Java Heap", "Graphics"); the common rows are also typed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemInfo {
+    pub pid: Option<u32>,
+    pub process: Option<String>,
+    pub total_pss_kb: Option<u64>,
+    pub total_rss_kb: Option<u64>,
+    pub total_swap_pss_kb: Option<u64>,
+    pub java_heap_kb: Option<u64>,
+    pub native_heap_kb: Option<u64>,
+    pub code_kb: Option<u64>,
+    pub stack_kb: Option<u64>,
+    pub graphics_kb: Option<u64>,
+    pub summary: HashMap<String, u64>,
+}
+
+impl AdbHelper {
+    /// Raw `dumpsys <service>` output.
+    pub fn dumpsys(&self, service: &str) -> Result<String> {
+        self.exec_shell(&format!("dumpsys {}", shell_quote(service)))
+    }
+
+    /// Battery state as seen by the framework (`dumpsys battery`).
+    pub fn dumpsys_battery(&self) -> Result<BatteryDump> {
+        Ok(parse_battery(&self.dumpsys("battery")?))
+    }
+
+    /// Memory usage of the running `package` (`dumpsys meminfo <package>`).
+    /// Fails with `Error::Adb` when no such process is running.
+    pub fn dumpsys_meminfo(&self, package: &str) -> Result<MemInfo> {
+        let output = self.exec_shell(&format!("dumpsys meminfo {}", shell_quote(package)))?;
+        parse_meminfo(&output)
+            .ok_or_else(|| Error::Adb(format!("no meminfo for {}: {}", package, output.trim())))
+    }
+}
+
+pub(super) fn parse_battery(output: &str) -> BatteryDump {
+    let fields: HashMap<String, String> = output
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect();
+    let flag = |key: &str| fields.get(key).is_some_and(|v| v == "true");
+    let int = |key: &str| fields.get(key).and_then(|v| v.parse::<i32>().ok());
+
+    BatteryDump {
+        ac_powered: flag("AC powered"),
+        usb_powered: flag("USB powered"),
+        wireless_powered: flag("Wireless powered"),
+        present: flag("present"),
+        status: int("status"),
+        health: int("health"),
+        level: int("level"),
+        scale: int("scale"),
+        voltage_mv: int("voltage"),
+        temperature_c: int("temperature").map(|t| t as f32 / 10.0),
+        technology: fields.get("technology").filter(|v| !v.is_empty()).cloned(),
+        fields,
+    }
+}
+
+/// `None` when the output has neither a process header nor totals (process not found).
+pub(super) fn parse_meminfo(output: &str) -> Option<MemInfo> {
+    let mut info = MemInfo::default();
+    let mut in_summary = false;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        // ** MEMINFO in pid 1234 [com.example.app] **
+        if let Some(rest) = trimmed.strip_prefix("** MEMINFO in pid ") {
+            let rest = rest.trim_end_matches('*').trim();
+            let (pid, process) = rest.split_once(' ').unwrap_or((rest, ""));
+            info.pid = pid.parse().ok();
+            info.process = process
+                .trim()
+                .strip_prefix('[')
+                .and_then(|p| p.strip_suffix(']'))
+                .map(str::to_string);
+            continue;
+        }
+        if trimmed == "App Summary" {
+            in_summary = true;
+            continue;
+        }
+
+        for (label, value) in label_values(trimmed) {
+            match label.as_str() {
+                // "TOTAL PSS:" on current releases, a bare "TOTAL:" on older ones
+                "TOTAL PSS" | "TOTAL" => info.total_pss_kb = Some(value),
+                "TOTAL RSS" => info.total_rss_kb = Some(value),
+                "TOTAL SWAP PSS" => info.total_swap_pss_kb = Some(value),
+                _ if in_summary => {
+                    info.summary.insert(label, value);
+                }
+                _ => {}
+            }
+        }
+    }
+    if info.pid.is_none() && info.total_pss_kb.is_none() {
+        return None;
+    }
+
+    let summary = |label: &str| info.summary.get(label).copied();
+    info.java_heap_kb = summary("Java Heap");
+    info.native_heap_kb = summary("Native Heap");
+    info.code_kb = summary("Code");
+    info.stack_kb = summary("Stack");
+    info.graphics_kb = summary("Graphics");
+    Some(info)
+}
+
+/// `label: number` pairs of a line such as `TOTAL PSS:  1234   TOTAL RSS:  5678`.
+/// Numbers without a preceding label (extra columns) are ignored.
+fn label_values(line: &str) -> Vec<(String, u64)> {
+    let mut pairs = Vec::new();
+    let mut label: Vec<&str> = Vec::new();
+    let mut pending: Option<String> = None;
+    for token in line.split_whitespace() {
+        if let Some(name) = pending.take() {
+            if let Ok(value) = token.parse() {
+                pairs.push((name, value));
+                continue;
+            }
+        }
+        match token.strip_suffix(':') {
+            Some(last) => {
+                label.push(last);
+                pending = Some(label.join(" "));
+                label.clear();
+            }
+            None if token.parse::<u64>().is_ok() => label.clear(),
+            None => label.push(token),
+        }
+    }
+    pairs
+}
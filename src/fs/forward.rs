@@ -0,0 +1,72 @@
+use crate::error::Result;
+use crate::fs::AdbHelper;
+
+/// One line of `adb forward --list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardRule {
+    pub serial: String,
+    /// Host side, e.g. `tcp:8080`
+    pub local: String,
+    /// Device side, e.g. `tcp:8080` or `localabstract:chrome_devtools_remote`
+    pub remote: String,
+}
+
+impl AdbHelper {
+    /// Forward host socket `local` to device socket `remote` (`adb forward <local> <remote>`).
+    ///
+    /// Returns the host TCP port: the one adb allocated when `local` is `tcp:0`, otherwise
+    /// the port given in `local`. `None` for non-TCP specs such as `localabstract:`.
+    pub fn forward(&self, local: &str, remote: &str) -> Result<Option<u16>> {
+        let stdout = self.adb_checked(&["forward", local, remote])?;
+        Ok(bound_port(local, &stdout))
+    }
+
+    /// Forward device socket `remote` to host socket `local` (`adb reverse <remote> <local>`).
+    ///
+    /// Returns the device TCP port, allocated by adb when `remote` is `tcp:0`.
+    pub fn reverse(&self, remote: &str, local: &str) -> Result<Option<u16>> {
+        let stdout = self.adb_checked(&["reverse", remote, local])?;
+        Ok(bound_port(remote, &stdout))
+    }
+
+    /// Active forwards of this device (all devices when no serial is set).
+    pub fn forward_list(&self) -> Result<Vec<ForwardRule>> {
+        let stdout = self.adb_checked(&["forward", "--list"])?;
+        Ok(parse_forward_list(&stdout)
+            .into_iter()
+            .filter(|rule| {
+                self.device_serial()
+                    .is_none_or(|serial| rule.serial == serial)
+            })
+            .collect())
+    }
+
+    /// Remove the forward listening on host socket `local`, e.g. `tcp:8080`.
+    pub fn remove_forward(&self, local: &str) -> Result<()> {
+        self.adb_checked(&["forward", "--remove", local])
+            .map(|_| ())
+    }
+}
+
+/// TCP port bound for `spec`: the port adb printed for `tcp:0`, else the one in the spec.
+fn bound_port(spec: &str, stdout: &str) -> Option<u16> {
+    let port: u16 = spec.strip_prefix("tcp:")?.parse().ok()?;
+    if port != 0 {
+        return Some(port);
+    }
+    stdout.trim().parse().ok()
+}
+
+pub(super) fn parse_forward_list(output: &str) -> Vec<ForwardRule> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            Some(ForwardRule {
+                serial: parts.next()?.to_string(),
+                local: parts.next()?.to_string(),
+                remote: parts.next()?.to_string(),
+            })
+        })
+        .collect()
+}
@@ -1,8 +1,18 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FileType {
     File,
     Directory,
     Symlink,
+    /// Block device node (`b`), e.g. `/dev/block/sda`
+    BlockDevice,
+    /// Character device node (`c`), e.g. `/dev/null`
+    CharDevice,
+    /// Named pipe (`p`)
+    Fifo,
+    /// Unix domain socket (`s`)
+    Socket,
     Other,
 }
 impl Default for FileType {
@@ -11,18 +21,47 @@ impl Default for FileType {
     }
 }
 
+impl FileType {
+    /// Lowercase name used in exports ("file", "directory", "symlink", "block_device",
+    /// "char_device", "fifo", "socket", "other").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileType::File => "file",
+            FileType::Directory => "directory",
+            FileType::Symlink => "symlink",
+            FileType::BlockDevice => "block_device",
+            FileType::CharDevice => "char_device",
+            FileType::Fifo => "fifo",
+            FileType::Socket => "socket",
+            FileType::Other => "other",
+        }
+    }
+
+    /// Device nodes, pipes and sockets: reading them can block or never end.
+    pub fn is_special(&self) -> bool {
+        matches!(
+            self,
+            FileType::BlockDevice | FileType::CharDevice | FileType::Fifo | FileType::Socket
+        )
+    }
+}
+
 impl From<&char> for FileType {
     fn from(s: &char) -> Self {
         match s {
             '-' => FileType::File,
             'd' => FileType::Directory,
             'l' => FileType::Symlink,
+            'b' => FileType::BlockDevice,
+            'c' => FileType::CharDevice,
+            'p' => FileType::Fifo,
+            's' => FileType::Socket,
             _ => FileType::Other,
         }
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct FileInfo {
     pub inode: usize,
     pub permissions: String,
@@ -33,3 +72,122 @@ pub struct FileInfo {
     pub group: String,
     pub size: u64,
 }
+
+impl FileInfo {
+    /// `created_time` (the status change time, `stat %Z`) as a date, `None` when unknown.
+    pub fn created_datetime(&self) -> Option<DateTime<Utc>> {
+        unix_datetime(self.created_time)
+    }
+
+    /// `modified_time` as a date, `None` when unknown.
+    pub fn modified_datetime(&self) -> Option<DateTime<Utc>> {
+        unix_datetime(self.modified_time)
+    }
+
+    /// `accessed_time` as a date, `None` when unknown.
+    pub fn accessed_datetime(&self) -> Option<DateTime<Utc>> {
+        unix_datetime(self.accessed_time)
+    }
+
+    /// `created_time` in ISO 8601, see `format_timestamp`.
+    pub fn created_iso(&self) -> Option<String> {
+        format_timestamp(self.created_time)
+    }
+
+    /// `modified_time` in ISO 8601, see `format_timestamp`.
+    pub fn modified_iso(&self) -> Option<String> {
+        format_timestamp(self.modified_time)
+    }
+
+    /// `accessed_time` in ISO 8601, see `format_timestamp`.
+    pub fn accessed_iso(&self) -> Option<String> {
+        format_timestamp(self.accessed_time)
+    }
+}
+
+/// Unix seconds as read from `stat` to a UTC date. 0 is what placeholder entries and
+/// unparsable stat fields hold, so it means unknown and gives `None`, as do values
+/// out of chrono's range.
+pub fn unix_datetime(secs: usize) -> Option<DateTime<Utc>> {
+    if secs == 0 {
+        return None;
+    }
+    DateTime::from_timestamp(i64::try_from(secs).ok()?, 0)
+}
+
+/// Unix seconds as an ISO 8601 / RFC 3339 UTC timestamp (`2023-11-14T22:13:20Z`),
+/// `None` when unknown (see `unix_datetime`).
+pub fn format_timestamp(secs: usize) -> Option<String> {
+    unix_datetime(secs).map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Guess whether `bytes` is binary rather than text, from the first 8 KiB:
+/// any NUL byte, or more than 10% control characters other than whitespace and ESC.
+/// Invalid UTF-8 alone doesn't count, so Latin-1 logs still read as text.
+pub fn is_probably_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(8192)];
+    if sample.is_empty() {
+        return false;
+    }
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| (b < 0x20 && !b"\t\n\r\x0c\x1b".contains(&b)) || b == 0x7f)
+        .count();
+    control * 10 > sample.len()
+}
+
+/// `xxd`-style dump of `len` bytes of `bytes` starting at `offset`, 16 bytes per line:
+/// `00000010: 01ff 6162 63                             ..abc`
+pub fn hexdump(bytes: &[u8], offset: usize, len: usize) -> String {
+    hexdump_with_width(bytes, offset, len, 16)
+}
+
+/// `hexdump` with `bytes_per_line` bytes per line (at least 1).
+/// The range is clamped to `bytes`; offsets in the output are relative to the start of `bytes`.
+pub fn hexdump_with_width(
+    bytes: &[u8],
+    offset: usize,
+    len: usize,
+    bytes_per_line: usize,
+) -> String {
+    use std::fmt::Write;
+
+    let bytes_per_line = bytes_per_line.max(1);
+    let start = offset.min(bytes.len());
+    let end = start.saturating_add(len).min(bytes.len());
+    // two hex digits per byte plus a space between 2-byte groups
+    let hex_width = bytes_per_line * 2 + bytes_per_line.div_ceil(2) - 1;
+
+    let mut out = String::new();
+    for (i, chunk) in bytes[start..end].chunks(bytes_per_line).enumerate() {
+        let mut hex = String::with_capacity(hex_width);
+        for (j, b) in chunk.iter().enumerate() {
+            if j > 0 && j % 2 == 0 {
+                hex.push(' ');
+            }
+            let _ = write!(hex, "{:02x}", b);
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let _ = writeln!(
+            out,
+            "{:08x}: {:<width$}  {}",
+            start + i * bytes_per_line,
+            hex,
+            ascii,
+            width = hex_width
+        );
+    }
+    out
+}
@@ -1,10 +1,30 @@
 mod adb;
+mod analysis;
+mod async_fs;
+mod backend;
+mod dumpsys;
 mod filesystem;
+mod forward;
 mod helpers;
+mod report;
+mod snapshot;
 
-use adb::AdbHelper;
-pub use filesystem::{FSNode, FileSystem};
-pub use helpers::{FileInfo, FileType};
+pub use adb::{
+    AdbHelper, RootMethod, TailFollow, DEFAULT_PULL_MAX_BYTES, DEFAULT_PULL_TIMEOUT,
+    DEFAULT_SCAN_EXCLUDE,
+};
+pub use analysis::{Finding, FindingKind};
+pub use async_fs::AsyncFileSystem;
+pub use backend::{AdbBackend, AdbCommand, MockBackend, ProcessBackend};
+pub use dumpsys::{BatteryDump, MemInfo};
+pub use filesystem::{FSNode, FileSystem, FsStats, RefreshProgress};
+pub use forward::ForwardRule;
+pub use helpers::{
+    format_timestamp, hexdump, hexdump_with_width, is_probably_binary, unix_datetime, FileInfo,
+    FileType,
+};
+pub use report::{Report, ReportEntry, ReportFormat, ReportSections};
+pub use snapshot::{SnapshotError, SNAPSHOT_FORMAT_VERSION, SNAPSHOT_SCHEMA};
 
 #[cfg(test)]
 mod tests {
@@ -14,17 +34,17 @@ mod tests {
         use std::path::Path;
         use FileSystem;
         println!("Testing ADB Filesystem creation...");
-        let mut fs = FileSystem::new(None);
+        let mut fs = FileSystem::new(None).with_root();
         fs.refresh().expect("Failed to refresh filesystem");
 
-        let jdata = fs.subtree_as_json(Path::new("/storage/emulated/0"));
+        let jdata = fs.subtree_to_json(Path::new("/storage/emulated/0"), false, false);
         println!("{}", jdata);
         println!("DOne");
     }
     #[test]
     fn test_adb() {
         use super::AdbHelper;
-        let adb = AdbHelper::new(None);
+        let adb = AdbHelper::new(None).with_root();
 
         let output = adb
             .exec_pty("find / -path /proc -prune -o -print0 | xargs -0 stat -c \"%i|%A|%Z|%Y|%X|%U|%G|%s|%N\"")
@@ -35,4 +55,1107 @@ mod tests {
         //let output = adb.exec_tty(&["su root","find / -path /proc -prune -o -print0 | xargs -0 stat -c \"%i|%A|%Z|%Y|%X|%U|%G|%s|%N\""]).expect("Failed to exec shell");
         //println!("ADB whoami output: {}", output);
     }
+
+    #[test]
+    fn test_add_child_explicit_directory() {
+        use std::path::Path;
+        let dir_info = FileInfo {
+            inode: 42,
+            permissions: "drwxrwx--x".to_string(),
+            ..Default::default()
+        };
+
+        // Child first: /data/app is created implicitly, then its own stat line arrives
+        let mut root = FSNode::new(FileInfo::default());
+        root.add_child(
+            Path::new("/data/app/base.apk"),
+            FileType::File,
+            FileInfo::default(),
+        );
+        let created = root.add_child(
+            Path::new("/data/app"),
+            FileType::Directory,
+            dir_info.clone(),
+        );
+        assert_eq!(created, 0);
+        let app = root.get_child(Path::new("/data/app")).unwrap();
+        assert_eq!(app.metadata().inode, 42);
+        assert_eq!(app.metadata().permissions, "drwxrwx--x");
+        assert!(app.get_child(Path::new("base.apk")).is_some());
+
+        // Directory first: adding a child must not reset the directory's metadata
+        let mut root = FSNode::new(FileInfo::default());
+        root.add_child(Path::new("/data/app"), FileType::Directory, dir_info);
+        root.add_child(
+            Path::new("/data/app/base.apk"),
+            FileType::File,
+            FileInfo::default(),
+        );
+        let app = root.get_child(Path::new("/data/app")).unwrap();
+        assert_eq!(app.metadata().inode, 42);
+        assert_eq!(app.file_type(), &FileType::Directory);
+    }
+
+    #[test]
+    fn test_canonical_paths() {
+        use std::path::{Path, PathBuf};
+        let canonical = |p: &str| FileSystem::canonical_path(Path::new(p));
+        assert_eq!(canonical("/a/b"), PathBuf::from("/a/b"));
+        assert_eq!(canonical("a/b"), PathBuf::from("/a/b"));
+        assert_eq!(canonical("/a/../b"), PathBuf::from("/b"));
+        assert_eq!(canonical("/a/./b/"), PathBuf::from("/a/b"));
+        assert_eq!(canonical("/.."), PathBuf::from("/"));
+        assert_eq!(canonical(""), PathBuf::from("/"));
+
+        let mut root = FSNode::new(FileInfo::default());
+        root.add_child(Path::new("/a/b"), FileType::File, FileInfo::default());
+        root.add_child(Path::new("/a/../c"), FileType::File, FileInfo::default());
+        assert!(root.get_child(Path::new("/a/./b")).is_some());
+        assert!(root.get_child(Path::new("/c")).is_some());
+        // `..` never becomes a node of its own
+        assert_eq!(root.get_child(Path::new("/a")).unwrap().children.len(), 1);
+        assert_eq!(root.get_child(Path::new("/")).unwrap().children.len(), 2);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        use super::adb::shell_quote;
+        let nasty = [
+            "/sdcard/My Documents",
+            "/sdcard/it's here",
+            "/sdcard/$HOME",
+            "/sdcard/a; rm -rf /",
+            "/sdcard/`id`\"'\\",
+        ];
+        assert_eq!(shell_quote("/sdcard/it's"), r"'/sdcard/it'\''s'");
+        // A local POSIX shell must hand every quoted path back verbatim
+        for path in nasty {
+            let out = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf %s {}", shell_quote(path)))
+                .output()
+                .expect("Failed to run sh");
+            assert_eq!(String::from_utf8_lossy(&out.stdout), path);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_for_device_keeps_path_and_env() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("adb");
+        std::fs::write(&script, "#!/bin/sh\necho \"$ADB_SERVER_SOCKET $*\"\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let adb = AdbHelper::new(Some("emulator-5554".into()))
+            .with_adb_path(script.display().to_string())
+            .with_env("ADB_SERVER_SOCKET", "tcp:localhost:5038")
+            .with_env("ADB_SERVER_SOCKET", "tcp:localhost:5037");
+        let other = adb.for_device("emulator-5556");
+        assert_eq!(adb.device_serial(), Some("emulator-5554"));
+        let out = other.adb(&["get-state"]).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out.stdout),
+            "tcp:localhost:5037 -s emulator-5556 get-state\n"
+        );
+    }
+
+    #[test]
+    fn test_mock_backend_feeds_refresh_path() {
+        use std::path::Path;
+        use std::sync::Arc;
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_response(
+                    "shell find '/sdcard'",
+                    "10|drwxrwx--x|1700000000|1700000000|1700000000|root|sdcard_rw|4096|'/sdcard'\n\
+                     11|drwxrwx--x|1700000000|1700000000|1700000000|root|sdcard_rw|4096|'/sdcard/Download'\n\
+                     12|-rw-rw----|1700000000|1700000100|1700000000|root|sdcard_rw|5|'/sdcard/Download/a.txt'\n",
+                )
+                .with_response("emu avd name", "Pixel_7_API_34\r\nOK\r\n"),
+        );
+        let adb = AdbHelper::new(Some("emulator-5554".into())).with_backend(mock.clone());
+        assert_eq!(adb.emu("avd name").unwrap(), "Pixel_7_API_34");
+        // unmatched commands fail like a non-zero adb exit
+        assert!(matches!(
+            adb.exec_shell("whoami"),
+            Err(crate::Error::Adb(_))
+        ));
+
+        let mut fs = FileSystem::from_adb(adb);
+        fs.refresh_path(Path::new("/sdcard")).unwrap();
+        assert!(fs
+            .root
+            .get_child(Path::new("/sdcard/Download/a.txt"))
+            .is_some());
+
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 3);
+        assert!(calls
+            .iter()
+            .all(|call| call.serial.as_deref() == Some("emulator-5554")));
+        assert_eq!(calls[1].args[..2], ["shell", "whoami"]);
+        assert!(calls[2].command_line().starts_with("shell find '/sdcard'"));
+    }
+
+    #[test]
+    fn test_exec_pty_targets_serial() {
+        use std::sync::Arc;
+        let mock = Arc::new(MockBackend::new().with_response("shell ls /", "data\nsystem\n"));
+        let adb = AdbHelper::new(Some("emulator-5556".into())).with_backend(mock.clone());
+        assert_eq!(adb.exec_pty("ls /").unwrap(), ["data", "system"]);
+
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 1);
+        let args: Vec<_> = calls[0]
+            .to_process()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(args[..3], ["-s", "emulator-5556", "shell"]);
+    }
+
+    #[test]
+    fn test_pull_resumable_appends_missing_tail() {
+        use std::sync::Arc;
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_response(
+                    "shell stat -L -c '%A %s' '/sdcard/big.bin'",
+                    "-rw-rw---- 10\n",
+                )
+                .with_response("shell dd if=/dev/zero", "\0")
+                .with_response("shell dd if='/sdcard/big.bin'", "56789"),
+        );
+        let adb = AdbHelper::new(None).with_backend(mock.clone());
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("big.bin");
+        std::fs::write(&local, "01234").unwrap();
+
+        assert_eq!(adb.pull_resumable("/sdcard/big.bin", &local).unwrap(), 10);
+        assert_eq!(std::fs::read(&local).unwrap(), b"0123456789");
+        let dd = mock.calls().last().unwrap().command_line();
+        assert!(dd.contains("skip=5 count=5"), "{}", dd);
+
+        // nothing left to fetch
+        let calls = mock.calls().len();
+        assert_eq!(adb.pull_resumable("/sdcard/big.bin", &local).unwrap(), 10);
+        assert_eq!(mock.calls().len(), calls + 1);
+    }
+
+    #[test]
+    fn test_read_file_pulls_through_backend() {
+        use std::sync::Arc;
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_response("shell stat -L -c '%A %s' '/sdcard/a.txt'", "-rw-rw---- 5\n")
+                .with_response("pull /sdcard/a.txt", "hello")
+                .with_response("shell stat", "-rw-rw---- 2048\n")
+                .with_exit(
+                    "pull /sdcard/gone.txt",
+                    "",
+                    "remote object does not exist",
+                    1,
+                ),
+        );
+        let adb = AdbHelper::new(None).with_backend(mock.clone());
+        assert_eq!(adb.read_file("/sdcard/a.txt").unwrap(), b"hello");
+        let pull = mock.calls().last().unwrap().clone();
+        assert_eq!(pull.args[..2], ["pull", "/sdcard/a.txt"]);
+        // the temporary copy is removed after reading
+        assert!(!std::path::Path::new(&pull.args[2]).exists());
+
+        assert!(matches!(
+            adb.read_file("/sdcard/gone.txt"),
+            Err(crate::Error::Adb(_))
+        ));
+        // over the limit
+        assert!(matches!(
+            adb.read_file_with_limits("/sdcard/big.txt", 1024, std::time::Duration::from_secs(1)),
+            Err(crate::Error::Adb(_))
+        ));
+    }
+
+    #[test]
+    fn test_pull_to_reports_progress() {
+        use std::sync::Arc;
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_response("shell stat", "-rw-rw---- 4\n")
+                .with_response("pull /sdcard/b.bin", "data"),
+        );
+        let adb = AdbHelper::new(None).with_backend(mock);
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("b.bin");
+        let mut progress = Vec::new();
+        let size = adb
+            .pull_to("/sdcard/b.bin", &local, |bytes| progress.push(bytes))
+            .unwrap();
+        assert_eq!(size, 4);
+        assert_eq!(std::fs::read(&local).unwrap(), b"data");
+        assert_eq!(progress, [4]);
+    }
+
+    #[test]
+    fn test_pull_to_reads_through_root_shell() {
+        use std::sync::Arc;
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_response("shell su root sh -c 'stat", "-rw------- 6\n")
+                .with_response("shell su root sh -c 'dd if=/dev/zero", "\0")
+                .with_response("shell su root sh -c 'dd if=", "secret"),
+        );
+        let adb = AdbHelper::new(None)
+            .with_backend(mock.clone())
+            .with_root_method(RootMethod::SuRoot);
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("secret.bin");
+        let mut progress = Vec::new();
+
+        let size = adb
+            .pull_to("/data/secret.bin", &local, |bytes| progress.push(bytes))
+            .unwrap();
+        assert_eq!(size, 6);
+        assert_eq!(std::fs::read(&local).unwrap(), b"secret");
+        assert_eq!(progress, [6]);
+        assert!(mock.calls().iter().all(|call| call.args[0] != "pull"));
+
+        // a FIFO never ends, so it is refused before anything is read
+        let fifo = Arc::new(MockBackend::new().with_response("shell stat", "prw-rw---- 0\n"));
+        let adb = AdbHelper::new(None).with_backend(fifo.clone());
+        assert!(matches!(
+            adb.pull_to("/data/local/tmp/pipe", &local, |_| {}),
+            Err(crate::Error::Adb(_))
+        ));
+        assert_eq!(fifo.calls().len(), 1);
+    }
+
+    #[test]
+    fn test_list_all_with_streams_mtimes() {
+        use std::sync::Arc;
+        let mock = Arc::new(MockBackend::new().with_response(
+            "shell find / -d -printf",
+            "1700000100.5|/sdcard/a.txt\nnot a listing line\n1700000000.0|/sdcard\n",
+        ));
+        let adb = AdbHelper::new(None).with_backend(mock);
+        let mut seen = Vec::new();
+        let delivered = adb
+            .list_all_with(|path, mtime| seen.push((path, mtime)))
+            .unwrap();
+        assert_eq!(delivered, 2);
+        assert_eq!(
+            seen,
+            [
+                ("/sdcard/a.txt".to_string(), 1700000100),
+                ("/sdcard".to_string(), 1700000000)
+            ]
+        );
+        assert_eq!(adb.list_all().unwrap(), seen);
+    }
+
+    #[test]
+    fn test_load_all_parallel() {
+        use std::io::ErrorKind;
+        use std::path::Path;
+        use std::sync::Arc;
+        let stat = |inode: usize, mode: &str, path: &str| {
+            format!(
+                "{}|{}|1700000000|1700000100|1700000200|root|root|0|'{}'\n",
+                inode, mode, path
+            )
+        };
+        let scans = || {
+            MockBackend::new()
+                .with_response(
+                    "shell find / -mindepth 1 -maxdepth 1",
+                    "/sdcard\n/mnt\n/data\n",
+                )
+                .with_response("shell stat -c", stat(1, "drwxr-xr-x", "/"))
+                .with_response(
+                    "shell find '/sdcard'",
+                    stat(2, "drwxrwx---", "/sdcard") + &stat(4, "-rw-rw----", "/sdcard/a.txt"),
+                )
+                // a second path to the same file, e.g. through a bind mount
+                .with_response(
+                    "shell find '/mnt'",
+                    stat(3, "drwxr-xr-x", "/mnt") + &stat(4, "-rw-rw----", "/sdcard/a.txt"),
+                )
+        };
+
+        // /data is unreadable without root: skipped
+        let mock = Arc::new(scans().with_exit("shell find '/data'", "", "Permission denied", 1));
+        let adb = AdbHelper::new(None).with_backend(mock);
+        let mut paths: Vec<_> = adb
+            .load_all_parallel(2)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        paths.sort();
+        assert_eq!(paths, ["/", "/mnt", "/sdcard", "/sdcard/a.txt"]);
+
+        let mut fs = FileSystem::from_adb(adb);
+        fs.refresh_parallel(2).unwrap();
+        let mut tree: Vec<_> = fs.walk(Path::new("/")).map(|(path, _, _)| path).collect();
+        tree.sort();
+        assert_eq!(
+            tree,
+            [
+                Path::new("/"),
+                Path::new("/mnt"),
+                Path::new("/sdcard"),
+                Path::new("/sdcard/a.txt")
+            ]
+        );
+
+        // anything but an adb failure aborts the scan
+        let mock = Arc::new(scans().with_io_error("shell find '/data'", ErrorKind::BrokenPipe));
+        let adb = AdbHelper::new(None).with_backend(mock);
+        assert!(matches!(
+            adb.load_all_parallel(2),
+            Err(crate::Error::Io(e)) if e.kind() == ErrorKind::BrokenPipe
+        ));
+    }
+
+    #[test]
+    fn test_security_findings_skip_sticky_dirs() {
+        use std::sync::Arc;
+        let mock = Arc::new(MockBackend::new().with_response(
+            "shell find / ",
+            "1|drwxr-xr-x|0|0|0|root|root|4096|'/'\n\
+             2|drwxrwxrwt|0|0|0|root|root|4096|'/tmp'\n\
+             3|drwxrwxrwx|0|0|0|root|root|4096|'/open'\n",
+        ));
+        let mut fs = FileSystem::from_adb(AdbHelper::new(None).with_backend(mock));
+        fs.refresh().unwrap();
+
+        let mut found: Vec<_> = fs
+            .security_findings()
+            .into_iter()
+            .map(|finding| (finding.path.display().to_string(), finding.kind))
+            .collect();
+        found.sort_by_key(|(path, kind)| (path.clone(), format!("{:?}", kind)));
+        assert_eq!(
+            found,
+            [
+                ("/open".to_string(), FindingKind::RootOwnedWritable),
+                ("/open".to_string(), FindingKind::WorldWritable),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_stat_lines() {
+        fn info(inode: usize, permissions: &str, size: u64) -> FileInfo {
+            FileInfo {
+                inode,
+                permissions: permissions.to_string(),
+                created_time: 1700000000,
+                modified_time: 1700000100,
+                accessed_time: 1700000200,
+                user: "u0_a12".to_string(),
+                group: "media_rw".to_string(),
+                size,
+            }
+        }
+        let times = "1700000000|1700000100|1700000200|u0_a12|media_rw";
+        let cases: Vec<(String, Option<(&str, FileInfo)>)> = vec![
+            (
+                format!("10|-rw-rw----|{}|5|'/sdcard/a.txt'", times),
+                Some(("/sdcard/a.txt", info(10, "-rw-rw----", 5))),
+            ),
+            // zero-size file
+            (
+                format!("11|-rw-rw----|{}|0|'/sdcard/empty'", times),
+                Some(("/sdcard/empty", info(11, "-rw-rw----", 0))),
+            ),
+            // GNU and toybox symlink forms
+            (
+                format!(
+                    "12|lrwxrwxrwx|{}|21|'/sdcard' -> '/storage/self/primary'",
+                    times
+                ),
+                Some(("/sdcard", info(12, "lrwxrwxrwx", 21))),
+            ),
+            (
+                format!("13|lrwxrwxrwx|{}|8|/etc -> /system/etc", times),
+                Some(("/etc", info(13, "lrwxrwxrwx", 8))),
+            ),
+            // ` -> ` is only a link marker for symlinks
+            (
+                format!("14|-rw-rw----|{}|1|'/sdcard/a -> b.txt'", times),
+                Some(("/sdcard/a -> b.txt", info(14, "-rw-rw----", 1))),
+            ),
+            (
+                format!("15|-rw-rw----|{}|7|'/sdcard/été/日本語.txt'", times),
+                Some(("/sdcard/été/日本語.txt", info(15, "-rw-rw----", 7))),
+            ),
+            (
+                format!("16|-rw-rw----|{}|3|'/sdcard/a|b|c'", times),
+                Some(("/sdcard/a|b|c", info(16, "-rw-rw----", 3))),
+            ),
+            // unparsable numbers fall back to 0 rather than dropping the entry
+            (
+                "17|drwxrwx--x|x|y|z|root|root|big|'/data'".to_string(),
+                Some((
+                    "/data",
+                    FileInfo {
+                        inode: 17,
+                        permissions: "drwxrwx--x".to_string(),
+                        user: "root".to_string(),
+                        group: "root".to_string(),
+                        ..Default::default()
+                    },
+                )),
+            ),
+            // truncated, noise and empty names
+            (format!("18|-rw-rw----|{}", times), None),
+            ("12|-rw".to_string(), None),
+            ("".to_string(), None),
+            (format!("stat: '/x': No such file|-|{}|0|'/x'", times), None),
+            (format!("19|-rw-rw----|{}|0|''", times), None),
+        ];
+        for (line, expected) in cases {
+            let parsed = adb::parse_stat_line(&line);
+            let expected = expected.map(|(path, info)| (std::ffi::OsString::from(path), info));
+            assert_eq!(parsed, expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_read_file_refuses_special_and_large_files() {
+        use std::sync::Arc;
+        use std::time::Duration;
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_response("shell stat -L -c '%A %s' '/dev/zero'", "crw-rw-rw- 0\n")
+                .with_response(
+                    "shell stat -L -c '%A %s' '/sdcard/big.bin'",
+                    "-rw-rw---- 4096\n",
+                ),
+        );
+        let adb = AdbHelper::new(None).with_backend(mock.clone());
+
+        let err = adb.read_file("/dev/zero").unwrap_err();
+        assert!(err.to_string().contains("refusing to pull"), "{}", err);
+        let err = adb
+            .read_file_with_limits("/sdcard/big.bin", 1024, Duration::from_secs(1))
+            .unwrap_err();
+        assert!(err.to_string().contains("pull limit"), "{}", err);
+        // nothing was pulled
+        assert!(mock.calls().iter().all(|call| call.args[0] == "shell"));
+    }
+
+    #[test]
+    fn test_special_files_keep_their_type() {
+        use std::path::Path;
+        use std::sync::Arc;
+        let stat = |inode: u32, mode: &str, path: &str| {
+            format!("{}|{}|0|0|0|root|root|0|'{}'\n", inode, mode, path)
+        };
+        let output = [
+            stat(1, "drwxr-xr-x", "/dev"),
+            stat(2, "brw-------", "/dev/sda"),
+            stat(3, "crw-rw-rw-", "/dev/null"),
+            stat(4, "prw-------", "/dev/pipe"),
+            stat(5, "srw-rw----", "/dev/socket"),
+        ]
+        .concat();
+        let mock = Arc::new(MockBackend::new().with_response("shell find '/dev'", output));
+        let mut fs = FileSystem::from_adb(AdbHelper::new(None).with_backend(mock));
+        fs.refresh_path(Path::new("/dev")).unwrap();
+
+        let dev = fs.subtree_to_json(Path::new("/dev"), true, false);
+        let types: Vec<(&str, &str)> = dev["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| (row["name"].as_str().unwrap(), row["type"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("null", "char_device"),
+                ("pipe", "fifo"),
+                ("sda", "block_device"),
+                ("socket", "socket"),
+            ]
+        );
+        let null = fs.root.get_child(Path::new("/dev/null")).unwrap();
+        assert!(null.file_type().is_special());
+    }
+
+    #[test]
+    fn test_refresh_with_excludes() {
+        use std::sync::Arc;
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_response("shell find / ", "1|drwxr-xr-x|0|0|0|root|root|0|'/data'\n"),
+        );
+        let mut fs = FileSystem::from_adb(AdbHelper::new(None).with_backend(mock.clone()));
+        fs.refresh().unwrap();
+        fs.refresh_with_excludes(&["/proc", "/sys/", "/mnt/../dev"])
+            .unwrap();
+        fs.refresh_with_excludes(&[]).unwrap();
+        let finds: Vec<String> = mock
+            .calls()
+            .iter()
+            .map(|call| call.args[1].split(" | ").next().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            finds,
+            [
+                "find / -path '/proc' -prune -o -print0",
+                "find / -path '/proc' -prune -o -path '/sys' -prune -o -path '/dev' -prune -o -print0",
+                "find / -print0",
+            ]
+        );
+        let count = fs.count;
+        assert!(count > 0);
+
+        for bad in ["proc", "/", "/proc/.."] {
+            assert!(fs.refresh_with_excludes(&[bad]).is_err(), "{}", bad);
+        }
+        // an invalid list leaves the tree alone
+        assert_eq!(fs.count, count);
+        assert_eq!(mock.calls().len(), 3);
+    }
+
+    #[test]
+    fn test_refresh_scoped_max_depth() {
+        use std::path::{Path, PathBuf};
+        use std::sync::Arc;
+        let mock = Arc::new(MockBackend::new().with_response(
+            "shell find / ",
+            "1|drwxr-xr-x|0|0|0|root|root|0|'/data'\n\
+             2|drwxrwx--x|0|0|0|system|system|0|'/data/data'\n\
+             3|drwx------|0|0|0|u0_a12|u0_a12|0|'/data/data/com.app'\n\
+             4|-rw-------|5|6|7|u0_a12|u0_a12|9|'/data/data/com.app/db'\n",
+        ));
+        let mut fs = FileSystem::from_adb(AdbHelper::new(None).with_backend(mock.clone()));
+        fs.refresh_scoped(&["/proc"], Some(3)).unwrap();
+        let find = mock.calls()[0].args[1].clone();
+        assert!(
+            find.starts_with("find / -maxdepth 3 -path '/proc' -prune -o -print0 | "),
+            "{}",
+            find
+        );
+
+        let names = |listing: Vec<(PathBuf, FileType, usize)>| -> Vec<PathBuf> {
+            listing.into_iter().map(|(path, _, _)| path).collect()
+        };
+        let root = Path::new("/");
+        assert_eq!(
+            names(fs.root.list_folders_tree_depth(root, Some(1))),
+            [PathBuf::from("/data")]
+        );
+        assert_eq!(fs.root.list_folders_tree_depth(root, Some(2)).len(), 2);
+        assert_eq!(
+            fs.root.list_folders_tree_depth(root, None),
+            fs.root.list_folders_tree(root)
+        );
+        assert_eq!(fs.root.list_folders_tree(root).len(), 4);
+    }
+
+    #[test]
+    fn test_stats() {
+        use std::path::PathBuf;
+        use std::sync::Arc;
+        let mock = Arc::new(MockBackend::new().with_response(
+            "shell find / ",
+            "1|drwxr-xr-x|0|0|0|root|root|3452|'/'\n\
+             2|drwxrwx--x|0|0|0|system|system|3452|'/data'\n\
+             3|-rw-------|0|0|0|u0_a12|u0_a12|100|'/data/a.db'\n\
+             4|-rw-------|0|0|0|u0_a12|u0_a12|250|'/data/b.db'\n\
+             5|lrwxrwxrwx|0|0|0|root|root|11|'/sdcard' -> '/storage/self/primary'\n\
+             6|crw-rw-rw-|0|0|0|root|root|0|'/data/null'\n",
+        ));
+        let mut fs = FileSystem::from_adb(AdbHelper::new(None).with_backend(mock));
+        assert_eq!(fs.stats(), FsStats::default());
+        fs.refresh().unwrap();
+        assert_eq!(
+            fs.stats(),
+            FsStats {
+                total_files: 2,
+                total_dirs: 2,
+                total_symlinks: 1,
+                total_bytes: 350,
+                largest_file: Some((PathBuf::from("/data/b.db"), 250)),
+                deepest_path: Some(PathBuf::from("/data/a.db")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_owner_index() {
+        use std::path::{Path, PathBuf};
+        use std::sync::Arc;
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_response(
+                    "shell find / ",
+                    "1|drwxrwx--x|0|0|0|system|system|0|'/data/data'\n\
+                     2|drwx------|0|0|0|u0_a12|u0_a12|0|'/data/data/com.app'\n\
+                     3|-rw-rw----|0|0|0|u0_a12|u0_a12_cache|9|'/data/data/com.app/db'\n\
+                     4|-rw-------|0|0|0|system|system|9|'/data/system.xml'\n",
+                )
+                .with_response(
+                    "shell find '/data/data/com.app' ",
+                    "2|drwx------|0|0|0|u0_a12|u0_a12|0|'/data/data/com.app'\n",
+                ),
+        );
+        let adb = AdbHelper::new(None).with_backend(mock);
+        let mut plain = FileSystem::from_adb(adb.clone());
+        let mut indexed = FileSystem::from_adb(adb).with_owner_index();
+        plain.refresh().unwrap();
+        indexed.refresh().unwrap();
+
+        let app = [
+            PathBuf::from("/data/data/com.app"),
+            PathBuf::from("/data/data/com.app/db"),
+        ];
+        for fs in [&plain, &indexed] {
+            assert_eq!(fs.files_owned_by("u0_a12"), app);
+            assert_eq!(fs.files_in_group("u0_a12"), app[..1]);
+            assert_eq!(
+                fs.files_in_group("u0_a12_cache"),
+                [PathBuf::from("/data/data/com.app/db")]
+            );
+            assert!(fs.files_owned_by("u0_a99").is_empty());
+        }
+
+        // the index follows partial rescans: the app's db is gone
+        indexed
+            .refresh_path(Path::new("/data/data/com.app"))
+            .unwrap();
+        assert_eq!(indexed.files_owned_by("u0_a12"), app[..1]);
+        assert!(indexed.files_in_group("u0_a12_cache").is_empty());
+        assert_eq!(indexed.files_owned_by("system").len(), 2);
+    }
+
+    #[test]
+    fn test_files_by_app() {
+        use std::collections::HashMap;
+        use std::path::PathBuf;
+        use std::sync::Arc;
+
+        assert_eq!(analysis::uid_aliases("u0_a123"), ["u0_a123", "10123"]);
+        assert_eq!(analysis::uid_aliases("u10_a5"), ["u10_a5", "1010005"]);
+        assert_eq!(analysis::uid_aliases("u0_i3"), ["u0_i3"]);
+        assert_eq!(analysis::uid_aliases("system"), ["system"]);
+
+        let users = HashMap::from([
+            ("u0_a12".to_string(), "com.app,com.app:sync".to_string()),
+            (
+                "u0_a40".to_string(),
+                "com.shared.one,com.shared.two".to_string(),
+            ),
+        ]);
+        assert_eq!(analysis::app_users(&users, "com.app"), ["u0_a12"]);
+        assert_eq!(analysis::app_users(&users, "com.shared.two"), ["u0_a40"]);
+        assert!(analysis::app_users(&users, "com.ap").is_empty());
+        assert!(analysis::app_users(&users, "com.app:sync:x").is_empty());
+
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_response(
+                    "shell ps ",
+                    "u0_a12        com.app\nu0_a12        com.app:sync\nu0_a40 com.other\n",
+                )
+                .with_response(
+                    "shell find / ",
+                    "1|drwx------|0|0|0|u0_a12|u0_a12|0|'/data/data/com.app'\n\
+                     2|-rw-rw----|0|0|0|10012|1015|9|'/sdcard/com.app.log'\n\
+                     3|drwx------|0|0|0|u0_a40|u0_a40|0|'/data/data/com.other'\n",
+                ),
+        );
+        let mut fs = FileSystem::from_adb(AdbHelper::new(None).with_backend(mock));
+        fs.refresh().unwrap();
+        assert_eq!(
+            fs.files_by_app("com.app").unwrap(),
+            [
+                PathBuf::from("/data/data/com.app"),
+                PathBuf::from("/sdcard/com.app.log")
+            ]
+        );
+        assert!(fs.files_by_app("com.missing").is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        use std::sync::Arc;
+        let mock = Arc::new(MockBackend::new().with_response(
+            "shell find / ",
+            "1|drwxrwxrwx|0|0|0|root|root|0|'/data'\n\
+             2|-rw-r--r--|0|300|0|system|system|10|'/data/old.xml'\n\
+             3|-rw-r--r--|0|900|0|u0_a12|u0_a12|50|'/data/<new>.db'\n\
+             4|-rw-r--r--|0|600|0|u0_a12|u0_a12|70|'/data/big.bin'\n",
+        ));
+        let mut fs = FileSystem::from_adb(AdbHelper::new(None).with_backend(mock));
+        fs.refresh().unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&fs.report(ReportFormat::Json)).unwrap();
+        assert_eq!(json["stats"]["total_files"], 3);
+        assert_eq!(json["findings"][0]["path"], "/data");
+        assert_eq!(json["findings"][0]["kind"], "world_writable");
+        assert_eq!(json["largest_files"][0]["path"], "/data/big.bin");
+        assert_eq!(json["recent_files"][0]["path"], "/data/<new>.db");
+
+        let sections = ReportSections {
+            stats: false,
+            findings: false,
+            limit: 1,
+            ..Default::default()
+        };
+        let report = fs.build_report(&sections);
+        assert!(report.stats.is_none() && report.findings.is_none());
+        assert_eq!(report.largest_files.unwrap().len(), 1);
+        let json: serde_json::Value =
+            serde_json::from_str(&fs.report_with(ReportFormat::Json, &sections)).unwrap();
+        assert!(json.get("stats").is_none());
+
+        let html = fs.report_with(ReportFormat::Html, &sections);
+        assert!(html.contains("<h2>Largest files</h2>"));
+        assert!(!html.contains("Security findings"));
+        assert!(html.contains("/data/&lt;new&gt;.db"));
+        assert!(!html.contains("<new>"));
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let info = FileInfo {
+            created_time: 1700000000,
+            modified_time: 1700000100,
+            ..Default::default()
+        };
+        assert_eq!(info.created_iso().as_deref(), Some("2023-11-14T22:13:20Z"));
+        assert_eq!(
+            info.modified_datetime().map(|date| date.timestamp()),
+            Some(1700000100)
+        );
+        // 0 is the unknown / placeholder value
+        assert_eq!(info.accessed_datetime(), None);
+        assert_eq!(info.accessed_iso(), None);
+        assert_eq!(format_timestamp(usize::MAX), None);
+    }
+
+    #[test]
+    fn test_snapshot_versions() {
+        use std::path::Path;
+        use std::sync::Arc;
+        let mock = Arc::new(MockBackend::new().with_response(
+            "shell find / ",
+            "1|drwxr-xr-x|0|0|0|root|root|3452|'/'\n\
+             2|drwxrwx--x|0|0|0|system|system|3452|'/data'\n\
+             3|-rw-------|1700000000|1700000100|1700000200|u0_a12|u0_a12|100|'/data/a.db'\n\
+             5|lrwxrwxrwx|0|0|0|root|root|11|'/sdcard' -> '/storage/self/primary'\n",
+        ));
+        let mut fs = FileSystem::from_adb(AdbHelper::new(None).with_backend(mock));
+        fs.refresh().unwrap();
+        let mut snapshot = Vec::new();
+        fs.save_snapshot(&mut snapshot).unwrap();
+        let header = String::from_utf8_lossy(&snapshot)
+            .lines()
+            .next()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            header,
+            format!(
+                r#"{{"schema":"{}","format_version":{}}}"#,
+                SNAPSHOT_SCHEMA, SNAPSHOT_FORMAT_VERSION
+            )
+        );
+
+        let mut loaded = FileSystem::new(None);
+        loaded.load_snapshot(snapshot.as_slice()).unwrap();
+        assert_eq!(loaded.stats(), fs.stats());
+        assert_eq!(loaded.to_json(true, true), fs.to_json(true, true));
+
+        // version 1: a plain `write_jsonl` dump
+        let mut legacy = Vec::new();
+        fs.write_jsonl(&mut legacy, Path::new("/")).unwrap();
+        let mut migrated = FileSystem::new(None);
+        migrated.load_snapshot(legacy.as_slice()).unwrap();
+        assert_eq!(migrated.to_json(true, true), fs.to_json(true, true));
+
+        let newer = r#"{"schema":"ro_analyzer.fs_snapshot","format_version":99}"#;
+        assert!(matches!(
+            loaded.load_snapshot(newer.as_bytes()),
+            Err(SnapshotError::UnsupportedVersion {
+                found: 99,
+                supported: SNAPSHOT_FORMAT_VERSION
+            })
+        ));
+        let other = r#"{"schema":"something_else","format_version":1}"#;
+        assert!(matches!(
+            loaded.load_snapshot(other.as_bytes()),
+            Err(SnapshotError::WrongSchema(_))
+        ));
+        let garbled = format!("{}\n{{\"path\":\"/x\"}}\n", header);
+        assert!(matches!(
+            loaded.load_snapshot(garbled.as_bytes()),
+            Err(SnapshotError::Malformed { line: 2, .. })
+        ));
+        // failed loads keep the previous tree
+        assert_eq!(loaded.to_json(true, true), fs.to_json(true, true));
+    }
+
+    #[tokio::test]
+    async fn test_async_refresh() {
+        use std::path::Path;
+        use std::sync::Arc;
+        let mock = Arc::new(MockBackend::new().with_response(
+            "shell find / ",
+            "1|drwxr-xr-x|0|0|0|root|root|3452|'/'\n\
+                     2|drwxrwx--x|0|0|0|system|system|3452|'/data'\n\
+                     3|-rw-------|0|0|0|u0_a12|u0_a12|100|'/data/a.db'\n",
+        ));
+        let adb = AdbHelper::new(None).with_backend(mock);
+        let mut sync_fs = FileSystem::from_adb(adb.clone());
+        sync_fs.refresh().unwrap();
+
+        let fs = AsyncFileSystem::from(FileSystem::from_adb(adb).with_owner_index());
+        fs.refresh().await.unwrap();
+        assert_eq!(
+            fs.read().await.to_json(true, true),
+            sync_fs.to_json(true, true)
+        );
+        assert_eq!(
+            fs.read().await.files_owned_by("u0_a12"),
+            [Path::new("/data/a.db")]
+        );
+
+        // a failed scan keeps the previous tree
+        let failing = AdbHelper::new(None).with_backend(Arc::new(MockBackend::new()));
+        *fs.write().await = FileSystem::from_adb(failing);
+        fs.write().await.root = sync_fs.root.clone();
+        assert!(fs.refresh().await.is_err());
+        assert_eq!(
+            fs.read().await.to_json(true, true),
+            sync_fs.to_json(true, true)
+        );
+    }
+
+    #[test]
+    fn test_parse_emu_response() {
+        assert_eq!(
+            adb::parse_emu_response("avd name", "Pixel_7_API_34\r\nOK\r\n").unwrap(),
+            "Pixel_7_API_34"
+        );
+        assert_eq!(
+            adb::parse_emu_response("geo fix 2.35 48.85", "OK\n").unwrap(),
+            ""
+        );
+        let err = adb::parse_emu_response("power bogus", "KO: bad power command\n").unwrap_err();
+        assert!(err.to_string().contains("bad power command"));
+    }
+
+    #[test]
+    fn test_parse_forward_list() {
+        let rules = forward::parse_forward_list(
+            "emulator-5554 tcp:8080 tcp:8080\nemulator-5556 tcp:41234 localabstract:chrome_devtools_remote\n\n",
+        );
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            rules[1],
+            ForwardRule {
+                serial: "emulator-5556".into(),
+                local: "tcp:41234".into(),
+                remote: "localabstract:chrome_devtools_remote".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_dumpsys_battery() {
+        let battery = dumpsys::parse_battery(
+            "Current Battery Service state:\n  (UPDATES STOPPED -- use 'reset' to restart)\n  \
+             AC powered: false\n  USB powered: true\n  Wireless powered: false\n  \
+             Max charging current: 500000\n  status: 2\n  health: 2\n  present: true\n  \
+             level: 42\n  scale: 100\n  voltage: 4123\n  temperature: 253\n  \
+             technology: Li-ion\n",
+        );
+        assert!(battery.usb_powered && !battery.ac_powered && battery.present);
+        assert_eq!(battery.status, Some(2));
+        assert_eq!(battery.percent(), Some(42.0));
+        assert_eq!(battery.voltage_mv, Some(4123));
+        assert_eq!(battery.temperature_c, Some(25.3));
+        assert_eq!(battery.technology.as_deref(), Some("Li-ion"));
+        assert_eq!(battery.fields["Max charging current"], "500000");
+    }
+
+    #[test]
+    fn test_parse_dumpsys_meminfo() {
+        let output = "Applications Memory Usage (in Kilobytes):\n\
+Uptime: 1083 Realtime: 1083\n\
+\n\
+** MEMINFO in pid 4321 [com.example.app] **\n\
+                   Pss  Private  Private  SwapPss      Rss\n\
+                 Total    Dirty    Clean    Dirty    Total\n\
+                ------   ------   ------   ------   ------\n\
+  Native Heap     5120     5000        0       12     6000\n\
+        TOTAL    30123    20000     1000       40    70000\n\
+\n\
+ App Summary\n\
+                       Pss(KB)                        Rss(KB)\n\
+                        ------                         ------\n\
+           Java Heap:     4000                          9000\n\
+         Native Heap:     5000                          6000\n\
+                Code:     8000                         20000\n\
+               Stack:      300                            300\n\
+            Graphics:     2000                           2000\n\
+       Private Other:     1700\n\
+              System:     9123\n\
+\n\
+           TOTAL PSS:    30123            TOTAL RSS:    70000       TOTAL SWAP PSS:       40\n";
+        let info = dumpsys::parse_meminfo(output).unwrap();
+        assert_eq!(info.pid, Some(4321));
+        assert_eq!(info.process.as_deref(), Some("com.example.app"));
+        assert_eq!(info.total_pss_kb, Some(30123));
+        assert_eq!(info.total_rss_kb, Some(70000));
+        assert_eq!(info.total_swap_pss_kb, Some(40));
+        assert_eq!(info.java_heap_kb, Some(4000));
+        assert_eq!(info.native_heap_kb, Some(5000));
+        assert_eq!(info.graphics_kb, Some(2000));
+        assert_eq!(info.summary["Private Other"], 1700);
+
+        // older releases only print "TOTAL:"
+        let old =
+            dumpsys::parse_meminfo(" App Summary\n    TOTAL:    1234       TOTAL SWAP PSS:   5\n")
+                .unwrap();
+        assert_eq!(old.total_pss_kb, Some(1234));
+        assert_eq!(old.total_swap_pss_kb, Some(5));
+
+        assert!(dumpsys::parse_meminfo("No process found for: com.missing\n").is_none());
+    }
+
+    #[test]
+    fn test_refresh_progress_percent() {
+        let mut progress = RefreshProgress::default();
+        assert_eq!(progress.percent(), None);
+        progress.total = Some(200);
+        progress.entries = 50;
+        assert_eq!(progress.percent(), Some(25.0));
+        // entries created while scanning must not push it past 100%
+        progress.entries = 250;
+        assert_eq!(progress.percent(), Some(100.0));
+    }
+
+    #[test]
+    fn test_is_probably_binary() {
+        assert!(!is_probably_binary(b""));
+        assert!(!is_probably_binary(
+            b"line one\r\n\tline two\n\x1b[31mred\x1b[0m\n"
+        ));
+        // Latin-1 text is not valid UTF-8 but still text
+        assert!(!is_probably_binary(b"caf\xe9 cr\xe8me\n"));
+        assert!(is_probably_binary(b"ELF\x7f\x02\x01\x01\x00\x00"));
+        assert!(is_probably_binary(&[0x01, 0x02, 0x03, b'a', b'b', 0x04]));
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let bytes = b"Hello, hexdump!\x00\x01\xffabc";
+        // Same as `xxd`
+        assert_eq!(
+            hexdump(bytes, 0, bytes.len()),
+            "00000000: 4865 6c6c 6f2c 2068 6578 6475 6d70 2100  Hello, hexdump!.\n\
+             00000010: 01ff 6162 63                             ..abc\n"
+        );
+        assert_eq!(
+            hexdump_with_width(bytes, 14, 100, 3),
+            "0000000e: 2100 01  !..\n00000011: ff61 62  .ab\n00000014: 63       c\n"
+        );
+        assert_eq!(hexdump(bytes, 100, 16), "");
+    }
+
+    #[test]
+    fn test_subtree_to_json() {
+        use std::path::Path;
+        let mut fs = FileSystem::new(None);
+        let dir = FileInfo {
+            permissions: "drwxr-xr-x".to_string(),
+            ..Default::default()
+        };
+        let file = FileInfo {
+            size: 7,
+            ..Default::default()
+        };
+        fs.root
+            .add_child(Path::new("/"), FileType::Directory, dir.clone());
+        fs.root
+            .add_child(Path::new("/sdcard"), FileType::Directory, dir);
+        fs.root
+            .add_child(Path::new("/sdcard/b.txt"), FileType::File, file.clone());
+        fs.root
+            .add_child(Path::new("/sdcard/a.txt"), FileType::File, file);
+
+        let tree = fs.to_json(true, false);
+        assert_eq!(tree["name"], "/");
+        assert_eq!(tree["path"], "/");
+        let sdcard = &tree["rows"][0];
+        assert_eq!(sdcard["path"], "/sdcard");
+        assert_eq!(sdcard["type"], "directory");
+        assert!(sdcard.get("meta").is_none());
+        // files are leaves, in name order
+        assert_eq!(sdcard["rows"][0]["path"], "/sdcard/a.txt");
+        assert_eq!(sdcard["rows"][0]["rows"], serde_json::json!([]));
+        assert_eq!(sdcard["rows"][1]["name"], "b.txt");
+
+        let dirs_only = fs.subtree_to_json(Path::new("sdcard"), false, true);
+        assert_eq!(dirs_only["rows"], serde_json::json!([]));
+        assert_eq!(dirs_only["meta"]["permissions"], "drwxr-xr-x");
+        assert!(fs
+            .subtree_to_json(Path::new("/missing"), true, true)
+            .is_null());
+
+        let shallow = fs.subtree_to_json_depth(Path::new("/"), true, false, Some(1));
+        assert_eq!(shallow["rows"][0]["truncated"], true);
+        assert_eq!(shallow["rows"][0]["rows"], serde_json::json!([]));
+        assert!(fs.to_json(true, false)["rows"][0]
+            .get("truncated")
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_by_name() {
+        use std::path::{Path, PathBuf};
+        let mut fs = FileSystem::new(None);
+        for path in [
+            "/data/data/com.app/databases/Main.db",
+            "/data/data/com.app/databases/main.db-journal",
+            "/data/data/com.app/cache/main_cache",
+            "/sdcard/notes.txt",
+        ] {
+            fs.root
+                .add_child(Path::new(path), FileType::File, FileInfo::default());
+        }
+        let found = |query: &str| -> Vec<PathBuf> {
+            fs.find_by_name(query)
+                .into_iter()
+                .map(|(path, _, _)| path)
+                .collect()
+        };
+
+        // substring, case-insensitive, directories included
+        assert_eq!(found("MAIN").len(), 3);
+        assert_eq!(
+            found("databases"),
+            [PathBuf::from("/data/data/com.app/databases")]
+        );
+        // globs match the whole name
+        assert_eq!(
+            found("*.db"),
+            [PathBuf::from("/data/data/com.app/databases/Main.db")]
+        );
+        assert_eq!(found("main*").len(), 3);
+        assert_eq!(
+            found("m*n*e"),
+            [PathBuf::from("/data/data/com.app/cache/main_cache")]
+        );
+        assert_eq!(found("*.txt*"), [PathBuf::from("/sdcard/notes.txt")]);
+        assert!(found("notes").len() == 1 && found("*notes").is_empty());
+    }
 }
@@ -0,0 +1,238 @@
+use crate::fs::{format_timestamp, FileSystem, FileType, Finding, FsStats};
+
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Output format of `FileSystem::report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Pretty-printed JSON of the `Report` data model
+    Json,
+    /// Standalone HTML page, one table per section
+    Html,
+}
+
+/// Sections included in a report, see `FileSystem::report_with`. All on by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportSections {
+    /// `FileSystem::stats` summary
+    pub stats: bool,
+    /// `FileSystem::security_findings`
+    pub findings: bool,
+    /// Biggest regular files
+    pub largest_files: bool,
+    /// Most recently modified regular files
+    pub recent_files: bool,
+    /// Number of entries in the largest/recent lists
+    pub limit: usize,
+}
+
+impl Default for ReportSections {
+    fn default() -> Self {
+        Self {
+            stats: true,
+            findings: true,
+            largest_files: true,
+            recent_files: true,
+            limit: 20,
+        }
+    }
+}
+
+/// One file listed in a report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    pub permissions: String,
+    pub user: String,
+    pub group: String,
+    /// Unix seconds
+    pub modified_time: usize,
+}
+
+/// Data model of a report; sections left out are `None` (and absent from the JSON).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<FsStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub findings: Option<Vec<Finding>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub largest_files: Option<Vec<ReportEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent_files: Option<Vec<ReportEntry>>,
+}
+
+impl FileSystem {
+    /// Analysis report of the cached tree with every section, see `report_with`.
+    pub fn report(&self, format: ReportFormat) -> String {
+        self.report_with(format, &ReportSections::default())
+    }
+
+    /// Bundle the selected analyses of the cached tree (stats, security findings, largest
+    /// and recently modified files) into a single JSON or HTML document.
+    pub fn report_with(&self, format: ReportFormat, sections: &ReportSections) -> String {
+        let report = self.build_report(sections);
+        match format {
+            ReportFormat::Json => {
+                serde_json::to_string_pretty(&report).expect("report is always serializable")
+            }
+            ReportFormat::Html => report.to_html(),
+        }
+    }
+
+    /// The report data model, for callers rendering it themselves.
+    pub fn build_report(&self, sections: &ReportSections) -> Report {
+        let mut files: Vec<ReportEntry> = Vec::new();
+        if sections.largest_files || sections.recent_files {
+            files = self
+                .walk(Path::new("/"))
+                .filter(|(_, _, file_type)| *file_type == FileType::File)
+                .map(|(path, info, _)| ReportEntry {
+                    path,
+                    size: info.size,
+                    permissions: info.permissions.clone(),
+                    user: info.user.clone(),
+                    group: info.group.clone(),
+                    modified_time: info.modified_time,
+                })
+                .collect();
+        }
+        // stable sorts keep `walk` (path) order between equal keys
+        let top = |key: fn(&ReportEntry) -> u64| {
+            let mut sorted = files.clone();
+            sorted.sort_by_key(|entry| std::cmp::Reverse(key(entry)));
+            sorted.truncate(sections.limit);
+            sorted
+        };
+        Report {
+            stats: sections.stats.then(|| self.stats()),
+            findings: sections.findings.then(|| self.security_findings()),
+            largest_files: sections.largest_files.then(|| top(|entry| entry.size)),
+            recent_files: sections
+                .recent_files
+                .then(|| top(|entry| entry.modified_time as u64)),
+        }
+    }
+}
+
+impl Report {
+    /// Render as a standalone HTML page.
+    pub fn to_html(&self) -> String {
+        let mut out = String::from(
+            "<!doctype html>\n<meta charset=\"utf-8\">\n<title>RoAnalyzer report</title>\n\
+             <h1>RoAnalyzer report</h1>\n",
+        );
+        if let Some(stats) = &self.stats {
+            let largest = stats
+                .largest_file
+                .as_ref()
+                .map(|(path, size)| format!("{} ({} bytes)", path.display(), size))
+                .unwrap_or_default();
+            let deepest = stats
+                .deepest_path
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default();
+            table(
+                &mut out,
+                "Summary",
+                &["", ""],
+                vec![
+                    vec!["Files".into(), stats.total_files.to_string()],
+                    vec!["Directories".into(), stats.total_dirs.to_string()],
+                    vec!["Symlinks".into(), stats.total_symlinks.to_string()],
+                    vec!["Total bytes".into(), stats.total_bytes.to_string()],
+                    vec!["Largest file".into(), largest],
+                    vec!["Deepest path".into(), deepest],
+                ],
+            );
+        }
+        if let Some(findings) = &self.findings {
+            table(
+                &mut out,
+                "Security findings",
+                &["Path", "Kind", "Detail"],
+                findings
+                    .iter()
+                    .map(|finding| {
+                        vec![
+                            finding.path.display().to_string(),
+                            format!("{:?}", finding.kind),
+                            finding.detail.clone(),
+                        ]
+                    })
+                    .collect(),
+            );
+        }
+        if let Some(entries) = &self.largest_files {
+            entry_table(&mut out, "Largest files", entries);
+        }
+        if let Some(entries) = &self.recent_files {
+            entry_table(&mut out, "Recently modified files", entries);
+        }
+        out
+    }
+}
+
+fn entry_table(out: &mut String, title: &str, entries: &[ReportEntry]) {
+    table(
+        out,
+        title,
+        &["Path", "Size", "Permissions", "Owner", "Modified"],
+        entries
+            .iter()
+            .map(|entry| {
+                vec![
+                    entry.path.display().to_string(),
+                    entry.size.to_string(),
+                    entry.permissions.clone(),
+                    format!("{}:{}", entry.user, entry.group),
+                    format_timestamp(entry.modified_time).unwrap_or_default(),
+                ]
+            })
+            .collect(),
+    );
+}
+
+fn table(out: &mut String, title: &str, header: &[&str], rows: Vec<Vec<String>>) {
+    let _ = writeln!(out, "<h2>{}</h2>", escape_html(title));
+    if rows.is_empty() {
+        out.push_str("<p>None</p>\n");
+        return;
+    }
+    out.push_str("<table>\n");
+    if header.iter().any(|cell| !cell.is_empty()) {
+        out.push_str("<tr>");
+        for cell in header {
+            let _ = write!(out, "<th>{}</th>", escape_html(cell));
+        }
+        out.push_str("</tr>\n");
+    }
+    for row in rows {
+        out.push_str("<tr>");
+        for cell in row {
+            let _ = write!(out, "<td>{}</td>", escape_html(&cell));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+}
+
+/// Escape text for HTML element content and attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
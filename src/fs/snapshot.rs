@@ -0,0 +1,152 @@
+use crate::fs::{FSNode, FileInfo, FileSystem, FileType};
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// `schema` tag in the header line of every snapshot written by `FileSystem::save_snapshot`.
+pub const SNAPSHOT_SCHEMA: &str = "ro_analyzer.fs_snapshot";
+
+/// Snapshot format written by `FileSystem::save_snapshot`.
+///
+/// * 1: headerless `write_jsonl` dump of `/`, as written before snapshots were versioned
+/// * 2: header line `{"schema", "format_version"}` followed by the version 1 entry lines
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 2;
+
+/// Why `FileSystem::load_snapshot` rejected a file. The tree is left untouched.
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    /// Written by a newer (or unknown) version of the format.
+    #[error("unsupported snapshot format version {found} (supported up to {supported})")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    /// The header names another kind of document.
+    #[error("not a filesystem snapshot: schema {0:?}")]
+    WrongSchema(String),
+    /// A line is not valid JSON or lacks entry fields (`line` is 1-based).
+    #[error("malformed snapshot line {line}: {reason}")]
+    Malformed { line: usize, reason: String },
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotHeader {
+    schema: String,
+    format_version: u32,
+}
+
+/// One entry line, the shape of a `write_jsonl` object.
+#[derive(Deserialize)]
+struct SnapshotEntry {
+    path: String,
+    #[serde(rename = "type")]
+    file_type: String,
+    size: u64,
+    permissions: String,
+    user: String,
+    group: String,
+    inode: usize,
+    created_time: usize,
+    modified_time: usize,
+    accessed_time: usize,
+}
+
+impl FileSystem {
+    /// Save the cached tree for `load_snapshot`: a versioned header line, then one
+    /// `write_jsonl` object per entry.
+    pub fn save_snapshot(&self, mut writer: impl Write) -> crate::error::Result<()> {
+        let header = SnapshotHeader {
+            schema: SNAPSHOT_SCHEMA.to_string(),
+            format_version: SNAPSHOT_FORMAT_VERSION,
+        };
+        serde_json::to_writer(&mut writer, &header).map_err(std::io::Error::from)?;
+        writer.write_all(b"\n")?;
+        self.write_jsonl(writer, Path::new("/"))
+    }
+
+    /// Replace the cached tree with a snapshot from `save_snapshot`. Headerless dumps of
+    /// format version 1 (`write_jsonl` of `/`) are migrated on the fly. A newer version,
+    /// another schema or a malformed line fails without touching the tree.
+    pub fn load_snapshot(&mut self, reader: impl BufRead) -> Result<(), SnapshotError> {
+        let mut root = FSNode::new(FileInfo::default());
+        let mut count = 0;
+        let mut format_version = None;
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let malformed = |e: serde_json::Error| SnapshotError::Malformed {
+                line: index + 1,
+                reason: e.to_string(),
+            };
+            let value: serde_json::Value = serde_json::from_str(&line).map_err(malformed)?;
+            let version = match format_version {
+                Some(version) => version,
+                None if value.get("schema").is_some() => {
+                    let header: SnapshotHeader =
+                        serde_json::from_value(value).map_err(malformed)?;
+                    if header.schema != SNAPSHOT_SCHEMA {
+                        return Err(SnapshotError::WrongSchema(header.schema));
+                    }
+                    if header.format_version == 0 || header.format_version > SNAPSHOT_FORMAT_VERSION
+                    {
+                        return Err(SnapshotError::UnsupportedVersion {
+                            found: header.format_version,
+                            supported: SNAPSHOT_FORMAT_VERSION,
+                        });
+                    }
+                    format_version = Some(header.format_version);
+                    continue;
+                }
+                None => *format_version.insert(1),
+            };
+            let entry = migrate(version, serde_json::from_value(value).map_err(malformed)?);
+            let file_type =
+                file_type_named(&entry.file_type).ok_or_else(|| SnapshotError::Malformed {
+                    line: index + 1,
+                    reason: format!("unknown type {:?}", entry.file_type),
+                })?;
+            let info = FileInfo {
+                inode: entry.inode,
+                permissions: entry.permissions,
+                created_time: entry.created_time,
+                modified_time: entry.modified_time,
+                accessed_time: entry.accessed_time,
+                user: entry.user,
+                group: entry.group,
+                size: entry.size,
+            };
+            count += root.add_child(Path::new(&entry.path), file_type, info);
+        }
+        self.root = root;
+        self.count = count;
+        self.index_owners();
+        Ok(())
+    }
+}
+
+/// Bring an entry of format `version` to the current format. Entry lines have kept
+/// their shape so far; this is where field renames of future versions go.
+fn migrate(version: u32, entry: SnapshotEntry) -> SnapshotEntry {
+    match version {
+        1 | SNAPSHOT_FORMAT_VERSION => entry,
+        _ => unreachable!("version checked against the header"),
+    }
+}
+
+/// Inverse of `FileType::as_str`.
+fn file_type_named(name: &str) -> Option<FileType> {
+    [
+        FileType::File,
+        FileType::Directory,
+        FileType::Symlink,
+        FileType::BlockDevice,
+        FileType::CharDevice,
+        FileType::Fifo,
+        FileType::Socket,
+        FileType::Other,
+    ]
+    .into_iter()
+    .find(|file_type| file_type.as_str() == name)
+}
@@ -1,8 +1,15 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use qmetaobject::QString;
 use qmetaobject::*;
 use ro_grpc::fs::FileSystem;
+use ro_grpc::DeviceGrpcClient;
+
+/// Most results `search` returns, the view can't usefully show more
+const SEARCH_LIMIT: usize = 500;
+/// Quiet time after the last keystroke before `search_debounced` runs
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(250);
 
 #[derive(QObject)]
 struct AndroidFileExplorer {
@@ -15,20 +22,42 @@ struct AndroidFileExplorer {
     pub path_changed: qt_signal!(),
     pub json_data_changed: qt_signal!(),
     pub refresh: qt_method!(fn(&mut self)),
+    /// Children of one node, loaded when the user opens it
+    pub expand: qt_method!(fn(&self, path: QString) -> QString),
+    pub children_loaded: qt_signal!(path: QString, json_data: QString),
     pub print_lol: qt_method!(fn(&self, json_data: QString)),
+    pub search: qt_method!(fn(&self, query: QString) -> QString),
+    /// Like `search` but waits for typing to pause, answers via `search_results`
+    pub search_debounced: qt_method!(fn(&mut self, query: QString)),
+    pub search_results: qt_signal!(query: QString, json_data: QString),
+    search_generation: u64,
+    pub download: qt_method!(fn(&self, remote: QString, local: QString)),
+    /// Percent done, -1 when the file size isn't known
+    pub download_progress: qt_signal!(remote: QString, percent: i32),
+    /// `error` is empty on success
+    pub download_finished: qt_signal!(remote: QString, local: QString, error: QString),
 }
 
 impl Default for AndroidFileExplorer {
     fn default() -> Self {
         Self {
-            fs: FileSystem::new(None),
+            fs: FileSystem::new(None).with_root(),
             base: Default::default(),
             current_path: QString::from("/data/"),
             path_changed: Default::default(),
             json_data: QString::from("[{\"name\": \"lol\", \"rows\": [{\"name\": \"xd\",\"rows\":[{\"name\": \"child1\"}]},{\"name\": \"aaa\"}]}]"),
             json_data_changed: Default::default(),
             refresh: Default::default(),
+            expand: Default::default(),
+            children_loaded: Default::default(),
             print_lol: Default::default(),
+            search: Default::default(),
+            search_debounced: Default::default(),
+            search_results: Default::default(),
+            search_generation: 0,
+            download: Default::default(),
+            download_progress: Default::default(),
+            download_finished: Default::default(),
         }
     }
 }
@@ -40,13 +69,185 @@ impl AndroidFileExplorer {
 
     pub fn refresh(&mut self) {
         self.fs.refresh().unwrap();
-        let json_data = self.fs.subtree_json(PathBuf::from("/").as_path());
+        // Only the top levels, deeper nodes come through `expand`
+        let json_data =
+            self.fs
+                .subtree_to_json_depth(PathBuf::from("/").as_path(), false, false, Some(2));
         //println!("JSON Data: {}", json_data.to_string());
         self.json_data = QString::from(json_data.to_string());
         self.json_data_changed();
         // Build a QJsonArray that QML TreeModel accepts as "array"
         // Build a QJsonArray that QML TreeModel accepts as "array"
     }
+
+    /// JSON array of the child directories of `path`, each with its own children so the
+    /// view can tell which ones are expandable. Also emitted through `children_loaded`.
+    pub fn expand(&self, path: QString) -> QString {
+        let json_data = self.fs.subtree_to_json_depth(
+            PathBuf::from(path.to_string()).as_path(),
+            false,
+            false,
+            Some(2),
+        );
+        let rows = json_data
+            .get("rows")
+            .cloned()
+            .unwrap_or_else(|| serde_json::Value::Array(vec![]));
+        let rows = QString::from(rows.to_string());
+        self.children_loaded(path, rows.clone());
+        rows
+    }
+
+    /// Flat JSON list of `{name, path, type, meta}` for entries whose name matches `query`
+    /// (case-insensitive substring, or a `*` glob), at most `SEARCH_LIMIT` of them.
+    pub fn search(&self, query: QString) -> QString {
+        let query = query.to_string();
+        if query.trim().is_empty() {
+            return QString::from("[]");
+        }
+        let results: Vec<serde_json::Value> = self
+            .fs
+            .find_by_name(query.trim())
+            .into_iter()
+            .take(SEARCH_LIMIT)
+            .map(|(path, info, file_type)| {
+                serde_json::json!({
+                    "name": path.file_name().map(|name| name.to_string_lossy()),
+                    "path": path.to_string_lossy(),
+                    "type": file_type.as_str(),
+                    "meta": {
+                        "size": info.size,
+                        "permissions": info.permissions,
+                        "user": info.user,
+                        "group": info.group,
+                        "modified_time": info.modified_time,
+                        "modified": info.modified_iso(),
+                    },
+                })
+            })
+            .collect();
+        QString::from(serde_json::Value::Array(results).to_string())
+    }
+
+    pub fn search_debounced(&mut self, query: QString) {
+        self.search_generation += 1;
+        let generation = self.search_generation;
+        let this = QPointer::from(&*self);
+        single_shot(SEARCH_DEBOUNCE, move || {
+            if let Some(this) = this.as_pinned() {
+                let this = this.borrow();
+                // Skip queries superseded by a later keystroke
+                if this.search_generation == generation {
+                    let results = this.search(query.clone());
+                    this.search_results(query.clone(), results);
+                }
+            }
+        });
+    }
+
+    /// Pull `remote` to `local` on a worker thread so the UI stays responsive.
+    /// Reports through `download_progress` and always ends with `download_finished`.
+    pub fn download(&self, remote: QString, local: QString) {
+        let (remote, local) = (remote.to_string(), local.to_string());
+        let total = self
+            .fs
+            .root
+            .get_child(Path::new(&remote))
+            .map(|node| node.metadata().size);
+
+        // Signals must be emitted on the Qt thread
+        let this = QPointer::from(self);
+        let progress = queued_callback(move |(remote, percent): (String, i32)| {
+            if let Some(this) = this.as_pinned() {
+                this.borrow().download_progress(remote.into(), percent);
+            }
+        });
+        let this = QPointer::from(self);
+        let finished = queued_callback(move |(remote, local, error): (String, String, String)| {
+            if let Some(this) = this.as_pinned() {
+                this.borrow()
+                    .download_finished(remote.into(), local.into(), error.into());
+            }
+        });
+
+        let adb = self.fs.adb().clone();
+        std::thread::spawn(move || {
+            let result = adb.pull_to(&remote, &local, |bytes| {
+                let percent = match total {
+                    Some(total) if total > 0 => (bytes * 100 / total).min(100) as i32,
+                    _ => -1,
+                };
+                progress((remote.clone(), percent));
+            });
+            let error = result.err().map(|e| e.to_string()).unwrap_or_default();
+            finished((remote, local, error));
+        });
+    }
+}
+
+/// Emulator clipboard for the QML clipboard panel.
+/// Connects to `endpoint` (gRPC, default `DEFAULT_ENDPOINT`) on first use; a failed call
+/// sets `error` and drops the connection so the next call reconnects.
+#[derive(QObject, Default)]
+struct EmulatorClipboard {
+    base: qt_base_class!(trait QObject),
+    pub endpoint: qt_property!(QString),
+    pub error: qt_property!(QString; NOTIFY error_changed),
+    pub error_changed: qt_signal!(),
+    pub get: qt_method!(fn(&mut self) -> QString),
+    pub set: qt_method!(fn(&mut self, text: QString) -> bool),
+    runtime: Option<tokio::runtime::Runtime>,
+    client: Option<DeviceGrpcClient>,
+}
+
+const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:50051";
+
+impl EmulatorClipboard {
+    pub fn get(&mut self) -> QString {
+        let result = self.connect().and_then(|()| {
+            let runtime = self.runtime.as_ref().expect("connected");
+            let client = self.client.as_mut().expect("connected");
+            runtime.block_on(client.get_clipboard())
+        });
+        self.report(result).map(QString::from).unwrap_or_default()
+    }
+
+    pub fn set(&mut self, text: QString) -> bool {
+        let result = self.connect().and_then(|()| {
+            let runtime = self.runtime.as_ref().expect("connected");
+            let client = self.client.as_mut().expect("connected");
+            runtime.block_on(client.set_clipboard(text.to_string()))
+        });
+        self.report(result).is_some()
+    }
+
+    fn connect(&mut self) -> ro_grpc::error::Result<()> {
+        if self.runtime.is_none() {
+            self.runtime = Some(tokio::runtime::Runtime::new()?);
+        }
+        if self.client.is_none() {
+            let endpoint = match self.endpoint.to_string() {
+                endpoint if endpoint.is_empty() => DEFAULT_ENDPOINT.to_string(),
+                endpoint => endpoint,
+            };
+            let runtime = self.runtime.as_ref().expect("created above");
+            self.client = Some(runtime.block_on(DeviceGrpcClient::connect(endpoint))?);
+        }
+        Ok(())
+    }
+
+    /// Publish the outcome through `error` (empty on success).
+    fn report<T>(&mut self, result: ro_grpc::error::Result<T>) -> Option<T> {
+        self.error = match &result {
+            Ok(_) => QString::default(),
+            Err(e) => {
+                self.client = None;
+                QString::from(e.to_string())
+            }
+        };
+        self.error_changed();
+        result.ok()
+    }
 }
 
 fn format_size(size: u64) -> String {
@@ -73,6 +274,13 @@ fn main() {
         cstr::cstr!("AndroidFileExplorer"),
     );
 
+    qml_register_type::<EmulatorClipboard>(
+        cstr::cstr!("AndroidFileExplorer"),
+        1,
+        0,
+        cstr::cstr!("EmulatorClipboard"),
+    );
+
     let mut engine = QmlEngine::new();
 
     // Load QML from file
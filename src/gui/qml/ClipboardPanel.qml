@@ -0,0 +1,44 @@
+import QtQuick
+import QtQuick.Controls
+import QtQuick.Layouts
+import AndroidFileExplorer 1.0
+
+// Read and write the emulator clipboard (text only)
+ColumnLayout {
+    id: clipboardPanel
+    spacing: 6
+
+    EmulatorClipboard {
+        id: clipboard
+    }
+
+    Label {
+        text: "Emulator clipboard"
+        font.bold: true
+    }
+
+    TextArea {
+        id: clipboardText
+        Layout.fillWidth: true
+        Layout.preferredHeight: 120
+        placeholderText: "Clipboard text"
+        wrapMode: TextArea.Wrap
+    }
+
+    RowLayout {
+        Button {
+            text: "Get"
+            onClicked: clipboardText.text = clipboard.get()
+        }
+        Button {
+            text: "Set"
+            onClicked: clipboard.set(clipboardText.text)
+        }
+        Label {
+            Layout.fillWidth: true
+            text: clipboard.error
+            color: "#B00020"
+            elide: Text.ElideRight
+        }
+    }
+}
@@ -151,6 +151,13 @@ ColumnLayout {
                                 current = current.parent;
                             }
                             explorer.print_lol(path.reverse().join("/"));
+                            // Lazy loading: fetch the children the first time a node is opened
+                            if (currentIndex.valid && treeModel.rowCount(currentIndex) === 0) {
+                                var children = JSON.parse(explorer.expand(path.join("/")));
+                                for (var i = 0; i < children.length; i++) {
+                                    treeModel.appendRow(currentIndex, children[i]);
+                                }
+                            }
                         }
                     }                    
                     delegate: TreeViewDelegate {
@@ -66,6 +66,13 @@ ApplicationWindow {
                         Rectangle {
                             color: "white"
                             anchors.fill: parent
+
+                            ClipboardPanel {
+                                anchors.left: parent.left
+                                anchors.right: parent.right
+                                anchors.top: parent.top
+                                anchors.margins: 10
+                            }
                         }
                     }
                     Item {
@@ -1,99 +1,482 @@
 // Library wrapper around the Android EmulatorController proto
 
 pub mod proto {
+    // the server trait names stream types after the camelCase rpc names (`streamAudioStream`)
+    #![allow(non_camel_case_types)]
     // Generated code will be included here by tonic
     tonic::include_proto!("android.emulation.control");
 }
+// Stable names for the commonly used proto types
+pub mod types;
 // Re-export video submodule so external crates (tests/bins) can access it
 pub mod video;
 // File system operations via ADB
 pub mod fs;
+// Typed emulator notifications
+pub mod events;
+pub use events::Event;
+// Crate-wide error type
+pub mod error;
+pub use error::Error;
+// Display model
+pub mod display;
+pub use display::Display;
+pub mod vm;
+pub use vm::VmStatus;
+// Combined device snapshot for reports
+pub mod state;
+pub use state::DeviceState;
+// Stoppable wrapper for server streams
+pub mod stream_handle;
+pub use stream_handle::{StreamHandle, StreamStopper};
+// Channel over a Unix domain socket
+#[cfg(unix)]
+mod uds;
+// Auth token metadata
+pub mod auth;
+use auth::AuthInterceptor;
+// Running emulators from their discovery files
+pub mod discovery;
+pub use discovery::EmulatorEndpoint;
+// Encoder-free capture to raw files
+pub mod raw_capture;
+pub use raw_capture::RawManifest;
+pub mod transport;
+pub use transport::Transport;
+// Opt-in stderr output for the library's tracing events
+#[cfg(feature = "log-stderr")]
+pub mod logging;
+// Capture health counters
+pub mod metrics;
+// Distance/bearing math for GPS simulation
+pub mod geo;
+// Phone number checks and modem responses
+mod phone;
+// Optional OCR over screenshots
+#[cfg(feature = "ocr")]
+pub mod ocr;
+// Browser remote control over HTTP
+#[cfg(feature = "web")]
+pub mod web;
+// Fake emulator server for tests
+#[cfg(feature = "test-server")]
+pub mod fake_emulator;
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Channel;
-use tonic::Status;
 
 /// Configuration for screen recording
 //#[derive(Debug, Clone)]
 // Use the generated types through our proto module
 use proto::emulator_controller_client::EmulatorControllerClient;
+use proto::phone_call::Operation;
 use proto::{
     AudioFormat, AudioPacket, BatteryState, BrightnessValue, ClipData, DisplayConfigurations,
-    GpsState, Image, ImageFormat, LogMessage, PhysicalModelValue, SensorValue, Touch, TouchEvent,
-    VmRunState,
+    GpsState, Image, ImageFormat, LogMessage, Notification, PhoneCall, PhysicalModelValue,
+    SensorValue, SmsMessage, Touch, TouchEvent, VmRunState,
 };
 
+/// Overall timeout used by `DeviceGrpcClient::wait_for_stable`.
+const STABLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Highest backlight value accepted by `BrightnessValue` (the range is 0-255).
+const MAX_BRIGHTNESS: i32 = 255;
+
+/// Default pause between the two taps of `DeviceGrpcClient::double_tap`.
+pub const DOUBLE_TAP_GAP: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// How long a finger stays down during a single tap of a gesture.
+const TAP_HOLD: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Base delay between read retries, multiplied by the attempt number.
+const READ_RETRY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Interval between position updates of `DeviceGrpcClient::drive_route`.
+const GPS_TICK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Interval between `get_vm_state` polls of `DeviceGrpcClient::wait_for_vm_state`.
+const VM_STATE_POLL: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// Generated client with the auth interceptor in front of the channel.
+type ControllerClient = EmulatorControllerClient<InterceptedService<Channel, AuthInterceptor>>;
+
 /// Async wrapper client for the emulator controller gRPC service.
 pub struct DeviceGrpcClient {
-    inner: EmulatorControllerClient<Channel>,
+    inner: ControllerClient,
+    // kept to rebuild `inner` with a new interceptor, see `with_token`
+    channel: Channel,
+    // extra attempts for idempotent getters, see `with_retry_reads`
+    retry_reads: u32,
+    // reported by `metrics`
+    read_retries: std::sync::atomic::AtomicU64,
+    connection_errors: std::sync::atomic::AtomicU64,
+    // tasks of recording sessions and replay buffers started here, aborted on close/drop
+    background: std::sync::Mutex<Vec<tokio::task::AbortHandle>>,
+    #[cfg(feature = "ocr")]
+    ocr: Option<Box<dyn ocr::Ocr>>,
 }
 
 impl DeviceGrpcClient {
-    /// Connect to the gRPC endpoint (e.g., "127.0.0.1:8701").
-    pub async fn connect(endpoint: impl Into<String>) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Connect to the gRPC endpoint (e.g., "http://127.0.0.1:8701").
+    ///
+    /// `unix:///path/to/socket` connects to a controller bound to a Unix domain socket
+    /// instead. That form is only available on Unix platforms; elsewhere it returns an error.
+    pub async fn connect(endpoint: impl Into<String>) -> Result<Self, Error> {
         let ep = endpoint.into();
-        let channel = Channel::from_shared(ep)?.connect().await?;
-        let inner = EmulatorControllerClient::new(channel);
-        Ok(Self { inner })
+        let channel = match ep.strip_prefix("unix://") {
+            #[cfg(unix)]
+            Some(path) => uds::connect(path).await?,
+            #[cfg(not(unix))]
+            Some(_) => {
+                return Err(Error::Other(format!(
+                    "{}: Unix domain sockets are not supported on this platform",
+                    ep
+                )))
+            }
+            None => Channel::from_shared(ep)?.connect().await?,
+        };
+        let inner =
+            EmulatorControllerClient::with_interceptor(channel.clone(), AuthInterceptor::default());
+        Ok(Self {
+            inner,
+            channel,
+            retry_reads: 0,
+            read_retries: Default::default(),
+            connection_errors: Default::default(),
+            background: Default::default(),
+            #[cfg(feature = "ocr")]
+            ocr: None,
+        })
+    }
+
+    /// Connect to `endpoint` (see `connect`) and authenticate every request with `token`.
+    pub async fn connect_with_token(
+        endpoint: impl Into<String>,
+        token: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        Self::connect(endpoint).await?.with_token(token)
+    }
+
+    /// Running emulators advertised in the discovery directory (see `discovery::discovery_dir`),
+    /// ordered by pid. Files of emulators that have exited are ignored.
+    pub fn discover() -> Result<Vec<EmulatorEndpoint>, Error> {
+        discovery::discover()
+    }
+
+    /// Connect to the first running emulator that accepts a connection, authenticating
+    /// with the token from its discovery file.
+    pub async fn connect_discovered() -> Result<Self, Error> {
+        let mut last_err = None;
+        for endpoint in Self::discover()? {
+            match Self::connect_endpoint(&endpoint).await {
+                Ok(client) => return Ok(client),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| Error::Other("no running emulator found".to_string())))
+    }
+
+    /// Connect to the local emulator serving gRPC on `grpc_port`, using the token it
+    /// advertises in its discovery file (`pid_<pid>.ini`).
+    /// Emulators started without a token are connected to without one.
+    pub async fn connect_discovered_port(grpc_port: u16) -> Result<Self, Error> {
+        let client = Self::connect(format!("http://127.0.0.1:{}", grpc_port)).await?;
+        match discovery::discover_token(grpc_port)? {
+            Some(token) => client.with_token(token),
+            None => Ok(client),
+        }
+    }
+
+    /// Connect to a discovered emulator, with its token if it has one.
+    pub async fn connect_endpoint(endpoint: &EmulatorEndpoint) -> Result<Self, Error> {
+        let client = Self::connect(endpoint.uri()).await?;
+        match &endpoint.token {
+            Some(token) => client.with_token(token),
+            None => Ok(client),
+        }
+    }
+
+    /// Send `token` as `authorization: Bearer <token>` metadata on every request.
+    pub fn with_token(mut self, token: impl AsRef<str>) -> Result<Self, Error> {
+        let interceptor = AuthInterceptor::with_token(token.as_ref())?;
+        self.inner = EmulatorControllerClient::with_interceptor(self.channel.clone(), interceptor);
+        Ok(self)
+    }
+
+    /// Retry read-only calls up to `retries` times when the emulator answers
+    /// `Unavailable` or `DeadlineExceeded` (default 0, no retries).
+    ///
+    /// Only these idempotent getters participate: `get_clipboard`, `get_screenshot`,
+    /// `get_screenshot_as`, `get_battery`, `get_gps`, `get_vm_state`,
+    /// `get_display_configurations`, `get_brightness`, `get_sensor` and
+    /// `get_physical_model` (and the helpers built on them such as `displays` or
+    /// `current_brightness`). Setters, input events and streams are never retried,
+    /// so state changes are not applied twice.
+    pub fn with_retry_reads(mut self, retries: u32) -> Self {
+        self.retry_reads = retries;
+        self
+    }
+
+    /// Counters since the client connected, see `StreamPuffer::metrics` for capture health.
+    pub fn metrics(&self) -> metrics::ClientMetrics {
+        metrics::ClientMetrics {
+            read_retries: self.read_retries.load(std::sync::atomic::Ordering::Relaxed),
+            connection_errors: self
+                .connection_errors
+                .load(std::sync::atomic::Ordering::Relaxed),
+        }
+    }
+
+    /// Longest `close` waits for aborted background tasks to end.
+    pub const CLOSE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    /// Shut the client down: abort the background tasks of the recording sessions, replay
+    /// buffers and multi-display recordings it started, wait for them to end, then drop the
+    /// channel. No call can be in flight since this takes the client by value.
+    ///
+    /// Aborted captures are discarded, not flushed: a `RecordingSession` writes no file
+    /// and `record_all_displays` none of its MP4s. Call `RecordingSession::stop` (or let
+    /// `record_all_displays` return) first to keep them.
+    ///
+    /// Streams returned by `stream_*` belong to the caller and keep the connection open
+    /// until dropped; wrap them in a `StreamHandle` to stop them explicitly.
+    /// Fails with `Error::Timeout` when a task does not end within `CLOSE_TIMEOUT`.
+    pub async fn close(self) -> Result<(), Error> {
+        let tasks = std::mem::take(&mut *self.background.lock().unwrap());
+        for task in &tasks {
+            task.abort();
+        }
+        // aborted tasks end at their next await point
+        let waited = tokio::time::timeout(Self::CLOSE_TIMEOUT, async {
+            while tasks.iter().any(|task| !task.is_finished()) {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        })
+        .await;
+        waited.map_err(|_| {
+            Error::Timeout(format!(
+                "background tasks still running {:?} after close",
+                Self::CLOSE_TIMEOUT
+            ))
+        })
+    }
+
+    /// Abort `task` when the client is closed or dropped.
+    #[cfg(feature = "video")]
+    fn track(&self, task: tokio::task::AbortHandle) {
+        let mut background = self.background.lock().unwrap();
+        background.retain(|task| !task.is_finished());
+        background.push(task);
+    }
+
+    /// Run an idempotent unary call, retrying transient failures per `retry_reads`.
+    async fn read<Req, Resp, F, Fut>(&self, req: Req, mut call: F) -> Result<Resp, Error>
+    where
+        Req: Clone,
+        F: FnMut(ControllerClient, tonic::Request<Req>) -> Fut,
+        Fut: std::future::Future<Output = Result<tonic::Response<Resp>, tonic::Status>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = call(self.inner.clone(), tonic::Request::new(req.clone())).await;
+            match result {
+                Ok(resp) => return Ok(resp.into_inner()),
+                Err(status) if attempt < self.retry_reads && is_transient(&status) => {
+                    self.count_unavailable(&status);
+                    attempt += 1;
+                    self.read_retries
+                        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    tokio::time::sleep(READ_RETRY_BACKOFF * attempt).await;
+                }
+                Err(status) => return Err(self.status_error(status)),
+            }
+        }
+    }
+
+    /// Error for a failed call, counted in `connection_errors` if the emulator was
+    /// unreachable.
+    fn status_error(&self, status: tonic::Status) -> Error {
+        self.count_unavailable(&status);
+        status.into()
+    }
+
+    fn count_unavailable(&self, status: &tonic::Status) {
+        if status.code() == tonic::Code::Unavailable {
+            self.connection_errors
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
     }
 
     /// Get clipboard text from the emulator.
-    pub async fn get_clipboard(&mut self) -> Result<String, Status> {
-        let req = tonic::Request::new(());
-        let resp = self.inner.get_clipboard(req).await?;
-        Ok(resp.into_inner().text)
+    pub async fn get_clipboard(&mut self) -> Result<String, Error> {
+        let clip = self
+            .read((), |mut c, req| async move { c.get_clipboard(req).await })
+            .await?;
+        Ok(clip.text)
     }
 
     /// Set clipboard text on the emulator.
-    pub async fn set_clipboard(&mut self, text: impl Into<String>) -> Result<(), Status> {
+    pub async fn set_clipboard(&mut self, text: impl Into<String>) -> Result<(), Error> {
         let data = ClipData { text: text.into() };
         let req = tonic::Request::new(data);
         self.inner
             .set_clipboard(req)
             .await
-            .map(|_| ())
-            .map_err(|e| e)
+            .map_err(|status| self.status_error(status))?;
+        Ok(())
     }
 
     /// Send a single touch event (best-effort). This constructs a TouchEvent with a single touch.
     /// Many emulator input APIs expect sequences; this helper sends one event which often suffices for simple taps.
-    pub async fn send_touch(&mut self, x: i32, y: i32) -> Result<(), Status> {
-        let touch = Touch {
-            x,
-            y,
-            identifier: 0,
-            pressure: 1,
-            touch_major: 0,
-            touch_minor: 0,
-            // expiration and orientation are enums/ints; leave as defaults (0)
-            expiration: 0,
-            orientation: 0,
-        };
-        let event = TouchEvent {
-            touches: vec![touch],
-            display: 0,
-        };
-        let req = tonic::Request::new(event);
-        self.inner.send_touch(req).await.map(|_| ()).map_err(|e| e)
+    pub async fn send_touch(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        let req = tonic::Request::new(touch_event(x, y, 1));
+        self.inner
+            .send_touch(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
+        Ok(())
     }
 
     /// Convenience: perform a simple tap (alias to `send_touch`).
-    pub async fn tap(&mut self, x: i32, y: i32) -> Result<(), Status> {
+    pub async fn tap(&mut self, x: i32, y: i32) -> Result<(), Error> {
         self.send_touch(x, y).await
     }
 
+    /// Send a precomputed gesture: each event is sent in order and followed by its delay.
+    /// Events may target different displays, every event is delivered to its own `display`.
+    /// Returns on the first failed send; the remaining events are not sent.
+    pub async fn send_touch_sequence(
+        &mut self,
+        events: Vec<(TouchEvent, std::time::Duration)>,
+    ) -> Result<(), Error> {
+        for (event, delay) in events {
+            self.inner
+                .send_touch(tonic::Request::new(event))
+                .await
+                .map_err(|status| self.status_error(status))?;
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Press at (x, y), hold for `duration`, then release.
+    /// The down event is sent on its own, so the touch stays active for the whole hold.
+    pub async fn long_press(
+        &mut self,
+        x: i32,
+        y: i32,
+        duration: std::time::Duration,
+    ) -> Result<(), Error> {
+        self.send_touch_sequence(vec![
+            (touch_event(x, y, 1), duration),
+            (touch_event(x, y, 0), std::time::Duration::ZERO),
+        ])
+        .await
+    }
+
+    /// Tap twice at (x, y), `gap` apart (defaults to `DOUBLE_TAP_GAP`, well under
+    /// Android's 300 ms double-tap timeout).
+    pub async fn double_tap(
+        &mut self,
+        x: i32,
+        y: i32,
+        gap: Option<std::time::Duration>,
+    ) -> Result<(), Error> {
+        let gap = gap.unwrap_or(DOUBLE_TAP_GAP);
+        self.send_touch_sequence(vec![
+            (touch_event(x, y, 1), TAP_HOLD),
+            (touch_event(x, y, 0), gap),
+            (touch_event(x, y, 1), TAP_HOLD),
+            (touch_event(x, y, 0), std::time::Duration::ZERO),
+        ])
+        .await
+    }
+
     /// Request a continuous screenshot stream. Returns the tonic streaming of `Image`.
     pub async fn stream_screenshot(
         &mut self,
         fmt: ImageFormat,
-    ) -> Result<tonic::Streaming<Image>, Status> {
+    ) -> Result<tonic::Streaming<Image>, Error> {
         let req = tonic::Request::new(fmt);
-        let resp = self.inner.stream_screenshot(req).await?;
+        let resp = self
+            .inner
+            .stream_screenshot(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
         Ok(resp.into_inner())
     }
 
+    /// Stream `fmt` over `transport` and wait for the first frame, pixels resolved.
+    /// Falls back to `Transport::Grpc` when the side channel cannot be set up or its
+    /// first frame cannot be read; returns the transport actually in use.
+    async fn open_frame_stream(
+        &mut self,
+        fmt: ImageFormat,
+        transport: &Transport,
+    ) -> Result<(tonic::Streaming<Image>, Image, Transport), Error> {
+        if !transport.is_grpc() {
+            match self.try_frame_stream(&fmt, transport).await {
+                Ok(opened) => return Ok(opened),
+                Err(e) => tracing::warn!("{:?} unusable, streaming over gRPC: {}", transport, e),
+            }
+        }
+        let mut stream = self.stream_screenshot(fmt).await?;
+        let (frame, _) = first_frame(&mut stream).await?;
+        Ok((stream, frame, Transport::Grpc))
+    }
+
+    async fn try_frame_stream(
+        &mut self,
+        fmt: &ImageFormat,
+        transport: &Transport,
+    ) -> Result<(tonic::Streaming<Image>, Image, Transport), Error> {
+        let mut stream = self.stream_screenshot(transport.prepare(fmt)?).await?;
+        let (mut frame, _) = first_frame(&mut stream).await?;
+        if !frame.image.is_empty() {
+            // the emulator ignored the side channel
+            return Ok((stream, frame, Transport::Grpc));
+        }
+        transport.resolve(&mut frame).await?;
+        Ok((stream, frame, transport.clone()))
+    }
+
+    /// RGB888 stream format of the display at list position `config.display`, at
+    /// `config.width`x`config.height` or, where either is 0, the display's native size.
+    /// The lookup also rejects an index past the display list with `DisplayOutOfRange`.
+    async fn recording_format(&mut self, config: &RecordingConfig) -> Result<ImageFormat, Error> {
+        let display = self.display(config.display).await?;
+        let (width, height) = if config.width == 0 || config.height == 0 {
+            (display.width, display.height)
+        } else {
+            (config.width, config.height)
+        };
+        Ok(ImageFormat {
+            format: proto::image_format::ImgFormat::Rgb888 as i32,
+            rotation: None,
+            width,
+            height,
+            // the emulator addresses displays by id, not list position
+            display: display.id,
+            transport: None,
+            folded_display: None,
+            display_mode: 0,
+        })
+    }
+
     /// Get a single screenshot from the emulator.
-    pub async fn get_screenshot(&mut self) -> Result<Image, Status> {
+    pub async fn get_screenshot(&mut self) -> Result<Image, Error> {
+        self.get_screenshot_as(proto::image_format::ImgFormat::Png)
+            .await
+    }
+
+    /// Get a single screenshot in the given pixel format (PNG, RGBA8888 or RGB888).
+    pub async fn get_screenshot_as(
+        &mut self,
+        format: proto::image_format::ImgFormat,
+    ) -> Result<Image, Error> {
         let fmt = ImageFormat {
-            format: proto::image_format::ImgFormat::Png.into(),
+            format: format.into(),
             rotation: None,
             width: 0,
             height: 0,
@@ -102,74 +485,417 @@ impl DeviceGrpcClient {
             folded_display: None,
             display_mode: 0,
         };
-        let req = tonic::Request::new(fmt);
-        let resp = self.inner.get_screenshot(req).await?;
-        Ok(resp.into_inner())
+        self.read(fmt, |mut c, req| async move { c.get_screenshot(req).await })
+            .await
+    }
+
+    /// PNG screenshots of every display, as `(index, image)` pairs in display order
+    /// (`index` as in `Display::index`). The captures run concurrently over the shared
+    /// channel and follow `with_retry_reads`.
+    pub async fn screenshot_all(&mut self) -> Result<Vec<(u32, Image)>, Error> {
+        let displays = self.displays().await?;
+        let this = &*self;
+        let captures = displays.into_iter().map(|display| {
+            let fmt = ImageFormat {
+                format: proto::image_format::ImgFormat::Png.into(),
+                display: display.id,
+                ..Default::default()
+            };
+            async move {
+                let image = this
+                    .read(fmt, |mut c, req| async move { c.get_screenshot(req).await })
+                    .await?;
+                Ok::<_, Error>((display.index, image))
+            }
+        });
+        futures::future::try_join_all(captures).await
     }
 
     /// Save a screenshot as PNG file
     pub async fn save_screenshot(
         &mut self,
         path: impl AsRef<std::path::Path>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), Error> {
         let image = self.get_screenshot().await?;
         std::fs::write(path, image.image)?;
         Ok(())
     }
 
+    /// Take a screenshot as an `image` crate image (fetched as RGB888, so nothing is decoded).
+    pub async fn screenshot_image(&mut self) -> Result<image::DynamicImage, Error> {
+        let image = self
+            .get_screenshot_as(proto::image_format::ImgFormat::Rgb888)
+            .await?;
+        video::image_to_dynamic(&image)
+    }
+
+    /// Save the `rect` part of a screenshot, encoded according to the extension of `path`
+    /// (`.png`, `.jpg`, `.webp`...). Errors if `rect` is not inside the screen.
+    pub async fn save_screenshot_region(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        rect: video::Rect,
+    ) -> Result<(), Error> {
+        let image = self.screenshot_image().await?;
+        let path = path.as_ref().to_path_buf();
+        tokio::task::spawn_blocking(move || save_image(&video::crop(&image, rect)?, &path)).await?
+    }
+
+    /// Save a screenshot shrunk so that neither side exceeds `max_dim` (e.g. report
+    /// thumbnails), encoded according to the extension of `path`.
+    pub async fn save_screenshot_scaled(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        max_dim: u32,
+    ) -> Result<(), Error> {
+        let image = self.screenshot_image().await?;
+        let path = path.as_ref().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            save_image(&video::scale_to_fit(&image, max_dim)?, &path)
+        })
+        .await?
+    }
+
+    /// Wait until the screen stops changing, with a default overall timeout of 30 seconds.
+    ///
+    /// A screenshot is taken every `poll`; the screen counts as stable once consecutive
+    /// frames have differed by less than `threshold` (fraction of changed pixels, see
+    /// `video::frame_diff`) for at least `stable_for`.
+    pub async fn wait_for_stable(
+        &mut self,
+        poll: std::time::Duration,
+        stable_for: std::time::Duration,
+        threshold: f32,
+    ) -> Result<(), Error> {
+        self.wait_for_stable_timeout(poll, stable_for, threshold, STABLE_TIMEOUT)
+            .await
+    }
+
+    /// Like `wait_for_stable` but with an explicit overall `timeout`.
+    pub async fn wait_for_stable_timeout(
+        &mut self,
+        poll: std::time::Duration,
+        stable_for: std::time::Duration,
+        threshold: f32,
+        timeout: std::time::Duration,
+    ) -> Result<(), Error> {
+        let wait = async {
+            // RGB888 avoids a PNG encode/decode round trip per poll
+            let mut prev = self
+                .get_screenshot_as(proto::image_format::ImgFormat::Rgb888)
+                .await?;
+            let mut stable_since = tokio::time::Instant::now();
+            loop {
+                tokio::time::sleep(poll).await;
+                let next = self
+                    .get_screenshot_as(proto::image_format::ImgFormat::Rgb888)
+                    .await?;
+                if video::frame_diff(&prev, &next) >= threshold {
+                    stable_since = tokio::time::Instant::now();
+                } else if stable_since.elapsed() >= stable_for {
+                    return Ok::<(), Error>(());
+                }
+                prev = next;
+            }
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| Error::Timeout(format!("screen not stable after {:?}", timeout)))?
+    }
+
+    /// Tap at (x, y), wait for the UI to react per `settle` and return a PNG screenshot,
+    /// e.g. to compare against a golden image. A plain `Duration` sleeps that long;
+    /// `Settle::Stable` waits for the screen to stop changing instead.
+    pub async fn tap_and_capture(
+        &mut self,
+        x: i32,
+        y: i32,
+        settle: impl Into<Settle>,
+    ) -> Result<Image, Error> {
+        self.tap(x, y).await?;
+        match settle.into() {
+            Settle::Delay(delay) => tokio::time::sleep(delay).await,
+            Settle::Stable {
+                poll,
+                stable_for,
+                threshold,
+                timeout,
+            } => {
+                self.wait_for_stable_timeout(poll, stable_for, threshold, timeout)
+                    .await?
+            }
+        }
+        self.get_screenshot().await
+    }
+
     /// Get the battery state from the emulator
-    pub async fn get_battery(&mut self) -> Result<BatteryState, Status> {
-        let req = tonic::Request::new(());
-        let resp = self.inner.get_battery(req).await?;
-        Ok(resp.into_inner())
+    pub async fn get_battery(&mut self) -> Result<BatteryState, Error> {
+        self.read((), |mut c, req| async move { c.get_battery(req).await })
+            .await
     }
 
     /// Set the battery state on the emulator
-    pub async fn set_battery(&mut self, state: BatteryState) -> Result<(), Status> {
+    pub async fn set_battery(&mut self, state: BatteryState) -> Result<(), Error> {
         let req = tonic::Request::new(state);
-        self.inner.set_battery(req).await.map(|_| ())
+        self.inner
+            .set_battery(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
+        Ok(())
     }
 
     /// Get the GPS state from the emulator
-    pub async fn get_gps(&mut self) -> Result<GpsState, Status> {
-        let req = tonic::Request::new(());
-        let resp = self.inner.get_gps(req).await?;
-        Ok(resp.into_inner())
+    pub async fn get_gps(&mut self) -> Result<GpsState, Error> {
+        self.read((), |mut c, req| async move { c.get_gps(req).await })
+            .await
     }
 
     /// Set the GPS state on the emulator
-    pub async fn set_gps(&mut self, state: GpsState) -> Result<(), Status> {
+    pub async fn set_gps(&mut self, state: GpsState) -> Result<(), Error> {
         let req = tonic::Request::new(state);
-        self.inner.set_gps(req).await.map(|_| ())
+        self.inner
+            .set_gps(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
+        Ok(())
+    }
+
+    /// Deliver an incoming SMS from `from` (e.g. "+15551234567" or "(650) 555-1221").
+    pub async fn send_sms(&mut self, from: &str, body: &str) -> Result<(), Error> {
+        let req = tonic::Request::new(SmsMessage {
+            src_address: phone::check_number(from)?,
+            text: body.to_string(),
+        });
+        let response = self
+            .inner
+            .send_sms(req)
+            .await
+            .map_err(|status| self.status_error(status))?
+            .into_inner();
+        phone::check_response("send sms", &response)
+    }
+
+    /// Ring the device with an incoming call from `from`.
+    pub async fn incoming_call(&mut self, from: &str) -> Result<(), Error> {
+        self.send_phone(Operation::InitCall, from).await
+    }
+
+    /// Accept the call from `from` on the emulated modem side, as if the device picked up.
+    pub async fn accept_call(&mut self, from: &str) -> Result<(), Error> {
+        self.send_phone(Operation::AcceptCall, from).await
+    }
+
+    /// Hang up the call with `from`. Fails with `Error::Phone` when no such call is active.
+    pub async fn end_call(&mut self, from: &str) -> Result<(), Error> {
+        self.send_phone(Operation::DisconnectCall, from).await
+    }
+
+    async fn send_phone(&mut self, operation: Operation, number: &str) -> Result<(), Error> {
+        let req = tonic::Request::new(PhoneCall {
+            operation: operation as i32,
+            number: phone::check_number(number)?,
+        });
+        let response = self
+            .inner
+            .send_phone(req)
+            .await
+            .map_err(|status| self.status_error(status))?
+            .into_inner();
+        phone::check_response(operation.as_str_name(), &response)
+    }
+
+    /// Move the device along `waypoints` (latitude, longitude) at `speed_kmh`.
+    ///
+    /// The position is interpolated and sent every second with the speed and a bearing
+    /// derived from the current leg. On the final waypoint the speed drops to 0 and the
+    /// call returns. To cancel mid-route drop the future (e.g. from `tokio::select!` or
+    /// by aborting the task running it); the last sent position stays in effect.
+    pub async fn drive_route(
+        &mut self,
+        waypoints: Vec<(f64, f64)>,
+        speed_kmh: f64,
+    ) -> Result<(), Error> {
+        let Some(&last) = waypoints.last() else {
+            return Err(Error::InvalidArgument(
+                "route needs at least one waypoint".to_string(),
+            ));
+        };
+        if !speed_kmh.is_finite() || speed_kmh <= 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "invalid route speed {} km/h",
+                speed_kmh
+            )));
+        }
+        let speed_ms = speed_kmh / 3.6;
+        let step_m = speed_ms * GPS_TICK.as_secs_f64();
+
+        let mut ticker = tokio::time::interval(GPS_TICK);
+        let mut bearing = 0.0;
+        // distance already covered into the current leg, carried over from the previous one
+        let mut travelled = 0.0;
+        for leg in waypoints.windows(2) {
+            let (from, to) = (leg[0], leg[1]);
+            let length = geo::distance_m(from, to);
+            if length == 0.0 {
+                continue;
+            }
+            bearing = geo::bearing_deg(from, to);
+            while travelled < length {
+                ticker.tick().await;
+                let (latitude, longitude) = geo::interpolate(from, to, travelled / length);
+                self.set_gps(route_fix(latitude, longitude, speed_ms, bearing))
+                    .await?;
+                travelled += step_m;
+            }
+            travelled -= length;
+        }
+
+        // Arrived: park on the final waypoint
+        ticker.tick().await;
+        self.set_gps(route_fix(last.0, last.1, 0.0, bearing)).await
     }
 
     /// Get the VM state from the emulator
-    pub async fn get_vm_state(&mut self) -> Result<VmRunState, Status> {
-        let req = tonic::Request::new(());
-        let resp = self.inner.get_vm_state(req).await?;
-        Ok(resp.into_inner())
+    pub async fn get_vm_state(&mut self) -> Result<VmRunState, Error> {
+        self.read((), |mut c, req| async move { c.get_vm_state(req).await })
+            .await
     }
 
     /// Set the VM state on the emulator
-    pub async fn set_vm_state(&mut self, state: VmRunState) -> Result<(), Status> {
+    pub async fn set_vm_state(&mut self, state: VmRunState) -> Result<(), Error> {
         let req = tonic::Request::new(state);
-        self.inner.set_vm_state(req).await.map(|_| ())
+        self.inner
+            .set_vm_state(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
+        Ok(())
+    }
+
+    /// Current VM state as a `VmStatus`.
+    pub async fn vm_status(&mut self) -> Result<VmStatus, Error> {
+        Ok(VmStatus::from(&self.get_vm_state().await?))
+    }
+
+    /// Pause the guest; it stops consuming CPU until `resume`.
+    pub async fn pause(&mut self) -> Result<(), Error> {
+        self.set_run_state(proto::vm_run_state::RunState::Paused)
+            .await
+    }
+
+    /// Resume a guest paused with `pause`.
+    pub async fn resume(&mut self) -> Result<(), Error> {
+        self.set_run_state(proto::vm_run_state::RunState::Running)
+            .await
+    }
+
+    /// Reset the guest, like pressing a hardware reset button.
+    pub async fn reset(&mut self) -> Result<(), Error> {
+        self.set_run_state(proto::vm_run_state::RunState::Reset)
+            .await
+    }
+
+    /// Request a clean shutdown (same as closing the emulator window). Returns once the
+    /// request is accepted; use `shutdown_and_wait` to block until the VM is gone.
+    pub async fn shutdown(&mut self) -> Result<(), Error> {
+        self.set_run_state(proto::vm_run_state::RunState::Shutdown)
+            .await
+    }
+
+    /// `shutdown`, then wait up to `timeout` until the VM reports `VmStatus::Shutdown`
+    /// or stops answering.
+    pub async fn shutdown_and_wait(&mut self, timeout: std::time::Duration) -> Result<(), Error> {
+        self.shutdown().await?;
+        match self.wait_for_vm_state(VmStatus::Shutdown, timeout).await {
+            // an emulator that has exited can't report its state anymore
+            Err(Error::Transport(_)) => Ok(()),
+            Err(Error::Status(status)) if status.code() == tonic::Code::Unavailable => Ok(()),
+            other => other,
+        }
+    }
+
+    /// Poll the VM state until it is `status`, failing with `Error::Timeout` after `timeout`.
+    pub async fn wait_for_vm_state(
+        &mut self,
+        status: VmStatus,
+        timeout: std::time::Duration,
+    ) -> Result<(), Error> {
+        let wait = async {
+            loop {
+                if self.vm_status().await? == status {
+                    return Ok::<(), Error>(());
+                }
+                tokio::time::sleep(VM_STATE_POLL).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| Error::Timeout(format!("VM not {:?} after {:?}", status, timeout)))?
+    }
+
+    async fn set_run_state(&mut self, state: proto::vm_run_state::RunState) -> Result<(), Error> {
+        self.set_vm_state(VmRunState {
+            state: state.into(),
+        })
+        .await
     }
 
     /// Get the display configurations from the emulator
-    pub async fn get_display_configurations(&mut self) -> Result<DisplayConfigurations, Status> {
-        let req = tonic::Request::new(());
-        let resp = self.inner.get_display_configurations(req).await?;
-        Ok(resp.into_inner())
+    pub async fn get_display_configurations(&mut self) -> Result<DisplayConfigurations, Error> {
+        self.read((), |mut c, req| async move {
+            c.get_display_configurations(req).await
+        })
+        .await
+    }
+
+    /// Capture a PNG screenshot of the main display together with the battery, GPS,
+    /// display and VM state, for a "device right now" report (see `DeviceState::write_report`).
+    /// The read-only RPCs run concurrently and follow `with_retry_reads`.
+    pub async fn capture_state(&mut self) -> Result<DeviceState, Error> {
+        let captured_at = chrono::Utc::now().to_rfc3339();
+        let png = ImageFormat {
+            format: proto::image_format::ImgFormat::Png.into(),
+            ..Default::default()
+        };
+        let (screenshot, battery, gps, displays, vm) = tokio::try_join!(
+            self.read(png, |mut c, req| async move { c.get_screenshot(req).await }),
+            self.read((), |mut c, req| async move { c.get_battery(req).await }),
+            self.read((), |mut c, req| async move { c.get_gps(req).await }),
+            self.read((), |mut c, req| async move {
+                c.get_display_configurations(req).await
+            }),
+            self.read((), |mut c, req| async move { c.get_vm_state(req).await }),
+        )?;
+        DeviceState::new(captured_at, screenshot, &battery, &gps, &displays, &vm)
+    }
+
+    /// List the emulator's displays.
+    pub async fn displays(&mut self) -> Result<Vec<Display>, Error> {
+        let configs = self.get_display_configurations().await?;
+        Ok(Display::list(&configs))
+    }
+
+    /// The display at `index` in the emulator's display list.
+    pub async fn display(&mut self, index: u32) -> Result<Display, Error> {
+        let configs = self.get_display_configurations().await?;
+        Display::select(&configs, index)
+    }
+
+    /// The main (first) display.
+    pub async fn primary_display(&mut self) -> Result<Display, Error> {
+        self.display(0).await
     }
 
     /// Set the display configurations on the emulator
     pub async fn set_display_configurations(
         &mut self,
         configs: DisplayConfigurations,
-    ) -> Result<DisplayConfigurations, Status> {
+    ) -> Result<DisplayConfigurations, Error> {
         let req = tonic::Request::new(configs);
-        let resp = self.inner.set_display_configurations(req).await?;
+        let resp = self
+            .inner
+            .set_display_configurations(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
         Ok(resp.into_inner())
     }
 
@@ -177,38 +903,88 @@ impl DeviceGrpcClient {
     pub async fn get_brightness(
         &mut self,
         value: BrightnessValue,
-    ) -> Result<BrightnessValue, Status> {
-        let req = tonic::Request::new(value);
-        let resp = self.inner.get_brightness(req).await?;
-        Ok(resp.into_inner())
+    ) -> Result<BrightnessValue, Error> {
+        self.read(
+            value,
+            |mut c, req| async move { c.get_brightness(req).await },
+        )
+        .await
     }
 
     /// Set the brightness value on the emulator
-    pub async fn set_brightness(&mut self, value: BrightnessValue) -> Result<(), Status> {
+    pub async fn set_brightness(&mut self, value: BrightnessValue) -> Result<(), Error> {
         let req = tonic::Request::new(value);
-        self.inner.set_brightness(req).await.map(|_| ())
+        self.inner
+            .set_brightness(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
+        Ok(())
+    }
+
+    /// Current LCD backlight brightness (0-255).
+    pub async fn current_brightness(&mut self) -> Result<u32, Error> {
+        let value = BrightnessValue {
+            target: proto::brightness_value::LightType::Lcd as i32,
+            value: 0,
+        };
+        Ok(self.get_brightness(value).await?.value)
+    }
+
+    /// Step the LCD backlight from `from` to `to` in `steps` even increments spread over `over`.
+    /// Values are clamped to the backlight's 0-255 range; `from` is applied immediately and
+    /// `to` is always the last value written (`steps == 0` behaves like 1).
+    pub async fn ramp_brightness(
+        &mut self,
+        from: i32,
+        to: i32,
+        over: std::time::Duration,
+        steps: u32,
+    ) -> Result<(), Error> {
+        let from = from.clamp(0, MAX_BRIGHTNESS) as f64;
+        let to = to.clamp(0, MAX_BRIGHTNESS) as f64;
+        let steps = steps.max(1);
+        let interval = over / steps;
+        for step in 0..=steps {
+            if step > 0 {
+                tokio::time::sleep(interval).await;
+            }
+            let level = from + (to - from) * step as f64 / steps as f64;
+            self.set_brightness(BrightnessValue {
+                target: proto::brightness_value::LightType::Lcd as i32,
+                value: level.round() as u32,
+            })
+            .await?;
+        }
+        Ok(())
     }
 
     /// Get a sensor value from the emulator
-    pub async fn get_sensor(&mut self, value: SensorValue) -> Result<SensorValue, Status> {
-        let req = tonic::Request::new(value);
-        let resp = self.inner.get_sensor(req).await?;
-        Ok(resp.into_inner())
+    pub async fn get_sensor(&mut self, value: SensorValue) -> Result<SensorValue, Error> {
+        self.read(value, |mut c, req| async move { c.get_sensor(req).await })
+            .await
     }
 
     /// Set a sensor value on the emulator
-    pub async fn set_sensor(&mut self, value: SensorValue) -> Result<(), Status> {
+    pub async fn set_sensor(&mut self, value: SensorValue) -> Result<(), Error> {
         let req = tonic::Request::new(value);
-        self.inner.set_sensor(req).await.map(|_| ())
+        self.inner
+            .set_sensor(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
+        Ok(())
     }
 
     /// Stream sensor values from the emulator
     pub async fn stream_sensor(
         &mut self,
         value: SensorValue,
-    ) -> Result<tonic::Streaming<SensorValue>, Status> {
+    ) -> Result<tonic::Streaming<SensorValue>, Error> {
         let req = tonic::Request::new(value);
-        let resp = self.inner.stream_sensor(req).await?;
+        let resp = self
+            .inner
+            .stream_sensor(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
         Ok(resp.into_inner())
     }
 
@@ -216,58 +992,207 @@ impl DeviceGrpcClient {
     pub async fn get_physical_model(
         &mut self,
         value: PhysicalModelValue,
-    ) -> Result<PhysicalModelValue, Status> {
-        let req = tonic::Request::new(value);
-        let resp = self.inner.get_physical_model(req).await?;
-        Ok(resp.into_inner())
+    ) -> Result<PhysicalModelValue, Error> {
+        self.read(value, |mut c, req| async move {
+            c.get_physical_model(req).await
+        })
+        .await
     }
 
     /// Set the physical model state
-    pub async fn set_physical_model(&mut self, value: PhysicalModelValue) -> Result<(), Status> {
+    pub async fn set_physical_model(&mut self, value: PhysicalModelValue) -> Result<(), Error> {
         let req = tonic::Request::new(value);
-        self.inner.set_physical_model(req).await.map(|_| ())
+        self.inner
+            .set_physical_model(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
+        Ok(())
     }
 
     /// Stream physical model values
     pub async fn stream_physical_model(
         &mut self,
         value: PhysicalModelValue,
-    ) -> Result<tonic::Streaming<PhysicalModelValue>, Status> {
+    ) -> Result<tonic::Streaming<PhysicalModelValue>, Error> {
         let req = tonic::Request::new(value);
-        let resp = self.inner.stream_physical_model(req).await?;
+        let resp = self
+            .inner
+            .stream_physical_model(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
         Ok(resp.into_inner())
     }
 
+    /// Record device motion (position and rotation) for `duration`.
+    /// Each sample is stamped with the time it was received, see `replay_physical_model`.
+    pub async fn record_physical_model(
+        &mut self,
+        duration: std::time::Duration,
+    ) -> Result<Vec<(std::time::Instant, PhysicalModelValue)>, Error> {
+        use proto::physical_model_value::PhysicalType;
+        self.record_physical_model_types(
+            duration,
+            &[PhysicalType::Position, PhysicalType::Rotation],
+        )
+        .await
+    }
+
+    /// Record the given physical model types for `duration`, merged in arrival order.
+    pub async fn record_physical_model_types(
+        &mut self,
+        duration: std::time::Duration,
+        types: &[proto::physical_model_value::PhysicalType],
+    ) -> Result<Vec<(std::time::Instant, PhysicalModelValue)>, Error> {
+        use futures::StreamExt;
+
+        let mut streams = Vec::with_capacity(types.len());
+        for &target in types {
+            let request = PhysicalModelValue {
+                target: target as i32,
+                ..Default::default()
+            };
+            streams.push(self.stream_physical_model(request).await?);
+        }
+        let mut merged = futures::stream::select_all(streams);
+
+        let mut samples = Vec::new();
+        let start = std::time::Instant::now();
+        while let Some(remaining) = duration.checked_sub(start.elapsed()) {
+            match tokio::time::timeout(remaining, merged.next()).await {
+                Ok(Some(Ok(value))) => samples.push((std::time::Instant::now(), value)),
+                Ok(Some(Err(e))) => return Err(e.into()),
+                Ok(None) => break, // all streams ended
+                Err(_) => break,   // duration elapsed
+            }
+        }
+        Ok(samples)
+    }
+
+    /// Replay samples captured by `record_physical_model`, keeping their relative timing.
+    pub async fn replay_physical_model(
+        &mut self,
+        recording: &[(std::time::Instant, PhysicalModelValue)],
+    ) -> Result<(), Error> {
+        let Some(&(first, _)) = recording.first() else {
+            return Ok(());
+        };
+        let start = tokio::time::Instant::now();
+        for (at, value) in recording {
+            tokio::time::sleep_until(start + at.saturating_duration_since(first)).await;
+            let mut value = value.clone();
+            // status is output only
+            value.status = 0;
+            self.set_physical_model(value).await?;
+        }
+        Ok(())
+    }
+
     /// Stream audio from the emulator
     pub async fn stream_audio(
         &mut self,
         format: AudioFormat,
-    ) -> Result<tonic::Streaming<AudioPacket>, Status> {
+    ) -> Result<tonic::Streaming<AudioPacket>, Error> {
         let req = tonic::Request::new(format);
-        let resp = self.inner.stream_audio(req).await?;
+        let resp = self
+            .inner
+            .stream_audio(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
         Ok(resp.into_inner())
     }
 
+    /// RMS and peak level of the audio output per `window` (e.g. 100 ms), computed from the
+    /// PCM of `stream_audio`. Lets tests check that something is playing without decoding.
+    ///
+    /// ```ignore
+    /// let mut levels = Box::pin(client.audio_levels(format, Duration::from_millis(100)).await?);
+    /// let level = levels.next().await.unwrap()?;
+    /// assert!(!level.is_silent(0.01));
+    /// ```
+    pub async fn audio_levels(
+        &mut self,
+        format: AudioFormat,
+        window: std::time::Duration,
+    ) -> Result<impl futures::Stream<Item = Result<video::AudioLevel, Error>>, Error> {
+        use futures::StreamExt;
+
+        let mut meter = video::level::LevelMeter::new(window, format.clone());
+        let packets = self.stream_audio(format).await?;
+        Ok(packets
+            .map(move |packet| match packet {
+                Ok(packet) => meter.push(&packet).into_iter().map(Ok).collect(),
+                Err(status) => vec![Err(Error::from(status))],
+            })
+            .flat_map(futures::stream::iter))
+    }
+
     /// Stream logcat output
     pub async fn stream_logcat(
         &mut self,
         msg: LogMessage,
-    ) -> Result<tonic::Streaming<LogMessage>, Status> {
+    ) -> Result<tonic::Streaming<LogMessage>, Error> {
         let req = tonic::Request::new(msg);
-        let resp = self.inner.stream_logcat(req).await?;
+        let resp = self
+            .inner
+            .stream_logcat(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
+        Ok(resp.into_inner())
+    }
+
+    /// Stream emulator notifications (camera, display, posture, boot, brightness, focus, XR).
+    /// Convert each message with `Event::from` for a typed view.
+    pub async fn stream_notifications(&mut self) -> Result<tonic::Streaming<Notification>, Error> {
+        let req = tonic::Request::new(());
+        let resp = self
+            .inner
+            .stream_notification(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
         Ok(resp.into_inner())
     }
 
-    /// Record audio from the emulator and save it as an MP3 file
+    /// Stream clipboard changes. The current clipboard is sent immediately,
+    /// followed by a message for every change made on the emulator.
+    pub async fn stream_clipboard(&mut self) -> Result<tonic::Streaming<ClipData>, Error> {
+        let req = tonic::Request::new(());
+        let resp = self
+            .inner
+            .stream_clipboard(req)
+            .await
+            .map_err(|status| self.status_error(status))?;
+        Ok(resp.into_inner())
+    }
+
+    /// Record audio from the emulator and save it to `audio_path`.
+    /// The container is chosen from the file extension (MP3 when unknown),
+    /// see `record_audio_as` to pick it explicitly.
+    #[cfg(feature = "video")]
     pub async fn record_audio(
         &mut self,
         audio_path: impl AsRef<std::path::Path>,
         duration_secs: u64,
         sample_rate: u32,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        use std::io::Write;
-        use std::process::{Command, Stdio};
+    ) -> Result<AudioFormat, Error> {
+        let container =
+            AudioContainer::from_path(audio_path.as_ref()).unwrap_or(AudioContainer::Mp3);
+        self.record_audio_as(audio_path, duration_secs, sample_rate, container)
+            .await
+    }
 
+    /// Record audio from the emulator and save it to `audio_path` using `container`.
+    /// `sample_rate` is the requested rate; the emulator may deliver a different format,
+    /// in which case the format reported by the first `AudioPacket` is used to decode the
+    /// stream. Returns the negotiated format so callers can log it.
+    /// Encoding happens in-process through ffmpeg-next, no `ffmpeg` binary is required.
+    #[cfg(feature = "video")]
+    pub async fn record_audio_as(
+        &mut self,
+        audio_path: impl AsRef<std::path::Path>,
+        duration_secs: u64,
+        sample_rate: u32,
+        container: AudioContainer,
+    ) -> Result<AudioFormat, Error> {
         // Set up audio format
         let audio_format = AudioFormat {
             sampling_rate: sample_rate as u64,
@@ -277,60 +1202,54 @@ impl DeviceGrpcClient {
         };
 
         // Start audio stream
-        let mut audio_stream = self.stream_audio(audio_format).await?;
-
-        // Bind sample_rate.to_string() to a variable to extend its lifetime
-        let sample_rate_str = sample_rate.to_string();
-
-        // Build ffmpeg args for audio
-        let ffmpeg_args = vec![
-            "-f",
-            "s16le",
-            "-ar",
-            &sample_rate_str,
-            "-ac",
-            "2",
-            "-i",
-            "-", // read raw audio from stdin
-            "-c:a",
-            "libmp3lame",
-            "-q:a",
-            "2", // high-quality MP3
-            audio_path.as_ref().to_str().ok_or("Invalid path")?,
-        ];
-
-        // Spawn ffmpeg process
-        let mut ffmpeg = Command::new("ffmpeg")
-            .args(&ffmpeg_args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::null())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .expect("failed to start ffmpeg");
-
-        let mut ffmpeg_stdin = ffmpeg.stdin.take().expect("ffmpeg stdin");
+        let mut audio_stream = self.stream_audio(audio_format.clone()).await?;
 
-        // Stream audio packets for the requested duration
+        // The first packet tells us what the emulator actually delivers
+        let max_duration = std::time::Duration::from_secs(duration_secs);
         let start_time = std::time::Instant::now();
-        while start_time.elapsed() < std::time::Duration::from_secs(duration_secs) {
-            match audio_stream.message().await {
-                Ok(Some(audio_packet)) => {
-                    ffmpeg_stdin.write_all(&audio_packet.audio)?;
-                }
-                Ok(None) => break, // stream ended
-                Err(e) => {
-                    eprintln!("error reading audio stream: {}", e);
+        let first_packet = match tokio::time::timeout(max_duration, audio_stream.message()).await {
+            Ok(Ok(Some(packet))) => packet,
+            Ok(Ok(None)) | Err(_) => {
+                return Err(Error::Decode(
+                    "no audio received from the emulator".to_string(),
+                ))
+            }
+            Ok(Err(e)) => return Err(e.into()),
+        };
+        let negotiated = first_packet.format.clone().unwrap_or(audio_format);
+        tracing::debug!("Negotiated audio format: {:?}", negotiated);
+
+        // Keep the negotiated layout so nothing is resampled unless the encoder requires it
+        let out_rate = if negotiated.sampling_rate == 0 {
+            44100
+        } else {
+            negotiated.sampling_rate as u32
+        };
+        let out_channels = match proto::audio_format::Channels::try_from(negotiated.channels) {
+            Ok(proto::audio_format::Channels::Mono) => 1,
+            _ => 2,
+        };
+
+        // Unbounded audio buffer for the duration of the capture
+        let puffer = video::StreamPuffer::new(0, usize::MAX, 0, out_rate, out_channels, 0, 0);
+        puffer.push_audio(first_packet).await;
+
+        // Stream audio packets for the requested duration
+        while let Some(remaining) = max_duration.checked_sub(start_time.elapsed()) {
+            match tokio::time::timeout(remaining, audio_stream.message()).await {
+                Ok(Ok(Some(audio_packet))) => puffer.push_audio(audio_packet).await,
+                Ok(Ok(None)) => break, // stream ended
+                Ok(Err(e)) => {
+                    tracing::warn!("error reading audio stream: {}", e);
                     break;
                 }
+                Err(_) => break, // duration elapsed
             }
         }
 
-        // Close stdin to signal EOF to ffmpeg
-        drop(ffmpeg_stdin);
-        let status = ffmpeg.wait()?;
-        println!("ffmpeg exited with: {:?}", status);
+        puffer.save_audio(audio_path, container).await?;
 
-        Ok(())
+        Ok(negotiated)
     }
 
     /// Record screen and audio (if configured) to file
@@ -594,7 +1513,7 @@ impl DeviceGrpcClient {
         &mut self,
         file_path: impl AsRef<std::path::Path>,
         duration_secs: u64,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), Error> {
         use std::fs::File;
         use std::io::Write;
         use std::time::Duration;
@@ -635,27 +1554,11 @@ impl DeviceGrpcClient {
         &mut self,
         duration_secs: u64,
         custom_config: Option<RecordingConfig>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), Error> {
         use chrono::DateTime;
         // retreave display config to get native resolution
-        let mut config = custom_config.unwrap_or_default();
-        if config.width == 0 || config.height == 0 {
-            let displays_config = self.get_display_configurations().await?;
-            let main_display = displays_config.displays.first().ok_or("No display found")?;
-            config.width = main_display.width;
-            config.height = main_display.height;
-        }
-
-        let img_format = ImageFormat {
-            format: proto::image_format::ImgFormat::Rgb888 as i32,
-            rotation: None,
-            width: config.width,
-            height: config.height,
-            display: config.display,
-            transport: None,
-            folded_display: None,
-            display_mode: 0,
-        };
+        let config = custom_config.unwrap_or_default();
+        let img_format = self.recording_format(&config).await?;
         let mut video_stream = self.stream_screenshot(img_format).await?;
         let max_duration = std::time::Duration::from_secs(duration_secs);
         let start = std::time::Instant::now();
@@ -663,7 +1566,7 @@ impl DeviceGrpcClient {
             match video_stream.message().await {
                 Ok(Some(frame)) => {
                     let dt = DateTime::from_timestamp_micros(frame.timestamp_us as i64).unwrap();
-                    println!(
+                    tracing::trace!(
                         "Received frame with timestamp: {} ,len: {}",
                         dt,
                         frame.image.len()
@@ -671,7 +1574,7 @@ impl DeviceGrpcClient {
                 }
                 Ok(None) => break, // stream ended
                 Err(e) => {
-                    eprintln!("error reading video stream: {}", e);
+                    tracing::warn!("error reading video stream: {}", e);
                     break;
                 }
             }
@@ -680,6 +1583,392 @@ impl DeviceGrpcClient {
 
         Ok(())
     }
+
+    /// Start recording `config.display` to the MP4 at `out_path` in the background.
+    /// The returned session can be paused and resumed; `stop` finalizes the file.
+    /// Closing or dropping the client aborts the recording without writing it.
+    #[cfg(feature = "video")]
+    pub async fn start_recording(
+        &mut self,
+        out_path: impl Into<std::path::PathBuf>,
+        custom_config: Option<RecordingConfig>,
+    ) -> Result<video::RecordingSession, Error> {
+        let config = custom_config.unwrap_or_default();
+        let img_format = self.recording_format(&config).await?;
+        let (video_stream, first_frame, transport) = self
+            .open_frame_stream(img_format, &config.transport)
+            .await?;
+        let frame_info = video::ImageInfo::from_image(&first_frame)?;
+        let audio_stream = if config.include_audio {
+            let audio_format = AudioFormat {
+                sampling_rate: config.audio_sample_rate,
+                channels: proto::audio_format::Channels::Stereo as i32,
+                format: proto::audio_format::SampleFormat::AudFmtS16 as i32,
+                mode: proto::audio_format::DeliveryMode::ModeUnspecified as i32,
+            };
+            Some(self.stream_audio(audio_format).await?)
+        } else {
+            None
+        };
+
+        // Unbounded buffers, the session decides how long to record.
+        // Sized from the delivered frame, which may differ from the requested size
+        let puffer = video::StreamPuffer::new(
+            usize::MAX,
+            if config.include_audio { usize::MAX } else { 0 },
+            config.fps,
+            config.audio_sample_rate as u32,
+            2,
+            frame_info.width,
+            frame_info.height,
+        );
+        puffer.set_drop_excess_frames(config.drop_excess_frames);
+        puffer.set_overlay(config.overlay_timestamp, config.overlay_caption.clone());
+        puffer.push_video(first_frame).await;
+        let session = video::RecordingSession::spawn(
+            puffer,
+            video_stream,
+            transport,
+            audio_stream,
+            out_path.into(),
+        );
+        self.track(session.abort_handle());
+        Ok(session)
+    }
+
+    /// Keep the last `window` of `config.display` (and audio with `config.include_audio`)
+    /// in memory on a background task, "instant replay" style: `ReplayBuffer::save_last`
+    /// writes what just happened to an MP4. Frames beyond `config.fps` are dropped so the
+    /// ring holds the whole window. Capturing ends when the buffer is stopped or dropped,
+    /// or the client is closed or dropped.
+    #[cfg(feature = "video")]
+    pub async fn start_replay_buffer(
+        &mut self,
+        window: std::time::Duration,
+        custom_config: Option<RecordingConfig>,
+    ) -> Result<video::ReplayBuffer, Error> {
+        let config = custom_config.unwrap_or_default();
+        let img_format = self.recording_format(&config).await?;
+        let (video_stream, first_frame, transport) = self
+            .open_frame_stream(img_format, &config.transport)
+            .await?;
+        let frame_info = video::ImageInfo::from_image(&first_frame)?;
+        let audio_stream = if config.include_audio {
+            let audio_format = AudioFormat {
+                sampling_rate: config.audio_sample_rate,
+                channels: proto::audio_format::Channels::Stereo as i32,
+                format: proto::audio_format::SampleFormat::AudFmtS16 as i32,
+                mode: proto::audio_format::DeliveryMode::ModeUnspecified as i32,
+            };
+            Some(self.stream_audio(audio_format).await?)
+        } else {
+            None
+        };
+
+        let window_ms = window.as_millis().max(1) as usize;
+        let max_frames = (config.fps as usize * window_ms).div_ceil(1000) + 1;
+        // audio packets are not sized by us; assume at least 5 ms of sound per packet
+        let max_audio_chunks = if config.include_audio {
+            window_ms.div_ceil(5) + 1
+        } else {
+            0
+        };
+        let puffer = video::StreamPuffer::new(
+            max_frames,
+            max_audio_chunks,
+            config.fps,
+            config.audio_sample_rate as u32,
+            2,
+            frame_info.width,
+            frame_info.height,
+        );
+        puffer.set_drop_excess_frames(true);
+        puffer.set_overlay(config.overlay_timestamp, config.overlay_caption.clone());
+        puffer.push_video(first_frame).await;
+        let replay = video::ReplayBuffer::spawn(puffer, video_stream, transport, audio_stream);
+        self.track(replay.abort_handle());
+        Ok(replay)
+    }
+
+    /// Capture `config.display` (and audio with `config.include_audio`) for `duration`
+    /// without encoding: frames go to numbered `.rgb` files in `dir`, audio to `audio.pcm`,
+    /// and `manifest.json` describes both. Works without a usable FFmpeg; mux later with
+    /// `video::mux_raw_capture` or any tool that reads raw RGB24/PCM.
+    ///
+    /// A video stream or write error ends the capture early and is returned once the
+    /// manifest of what was captured is written. Audio errors only drop the sound.
+    pub async fn record_raw(
+        &mut self,
+        dir: impl Into<std::path::PathBuf>,
+        duration: std::time::Duration,
+        custom_config: Option<RecordingConfig>,
+    ) -> Result<raw_capture::RawManifest, Error> {
+        let config = custom_config.unwrap_or_default();
+        let img_format = self.recording_format(&config).await?;
+        let (mut video_stream, first_frame, transport) = self
+            .open_frame_stream(img_format, &config.transport)
+            .await?;
+        let mut audio_stream = if config.include_audio {
+            let audio_format = AudioFormat {
+                sampling_rate: config.audio_sample_rate,
+                channels: proto::audio_format::Channels::Stereo as i32,
+                format: proto::audio_format::SampleFormat::AudFmtS16 as i32,
+                mode: proto::audio_format::DeliveryMode::ModeUnspecified as i32,
+            };
+            Some(self.stream_audio(audio_format).await?)
+        } else {
+            None
+        };
+
+        let mut writer = raw_capture::RawCaptureWriter::create(dir, config.fps).await?;
+        // the first error ends the capture, but what was written still gets its manifest
+        let mut failure = writer.push_frame(first_frame).await.err();
+        let deadline = tokio::time::sleep(duration);
+        tokio::pin!(deadline);
+        while failure.is_none() {
+            let mut audio_ended = false;
+            tokio::select! {
+                _ = &mut deadline => break,
+                frame = video_stream.message() => match frame {
+                    Ok(Some(mut frame)) => match transport.resolve(&mut frame).await {
+                        Ok(()) => failure = writer.push_frame(frame).await.err(),
+                        Err(e) => tracing::warn!("skipping frame: {}", e),
+                    },
+                    Ok(None) => break, // stream ended
+                    Err(e) => failure = Some(e.into()),
+                },
+                packet = async { audio_stream.as_mut()?.message().await.transpose() },
+                    if audio_stream.is_some() =>
+                {
+                    match packet {
+                        Some(Ok(packet)) => failure = writer.push_audio(packet).await.err(),
+                        Some(Err(e)) => {
+                            // keep capturing video without sound
+                            tracing::warn!("error reading audio stream: {}", e);
+                            audio_ended = true;
+                        }
+                        None => audio_ended = true,
+                    }
+                }
+            }
+            if audio_ended {
+                // keep capturing video
+                audio_stream = None;
+            }
+        }
+        let manifest = writer.finish().await;
+        match failure {
+            Some(e) => Err(e),
+            None => manifest,
+        }
+    }
+
+    /// Record every configured display concurrently for `duration_secs`.
+    /// Each display is written to `display_<id>.mp4` inside `out_dir`, which is created
+    /// if missing.
+    /// Returns the paths of the written files, in display order.
+    #[cfg(feature = "video")]
+    pub async fn record_all_displays(
+        &mut self,
+        duration_secs: u64,
+        out_dir: impl AsRef<std::path::Path>,
+        custom_config: Option<RecordingConfig>,
+    ) -> Result<Vec<std::path::PathBuf>, Error> {
+        let config = custom_config.unwrap_or_default();
+        let displays = self.displays().await?;
+        if displays.is_empty() {
+            return Err(Error::DisplayOutOfRange { index: 0, count: 0 });
+        }
+
+        let max_duration = std::time::Duration::from_secs(duration_secs);
+        let out_dir = out_dir.as_ref();
+        tokio::fs::create_dir_all(out_dir).await?;
+
+        let mut handles = Vec::with_capacity(displays.len());
+        for display in displays {
+            let width = if config.width > 0 {
+                config.width
+            } else {
+                display.width
+            };
+            let height = if config.height > 0 {
+                config.height
+            } else {
+                display.height
+            };
+            let img_format = ImageFormat {
+                format: proto::image_format::ImgFormat::Rgb888 as i32,
+                rotation: None,
+                width,
+                height,
+                display: display.id,
+                transport: None,
+                folded_display: None,
+                display_mode: 0,
+            };
+            let out_path = out_dir.join(format!("display_{}.mp4", display.id));
+            let fps = config.fps;
+            let drop_excess_frames = config.drop_excess_frames;
+            let overlay_timestamp = config.overlay_timestamp;
+            let caption = config.overlay_caption.clone();
+            let mut inner = self.inner.clone();
+
+            let handle = tokio::spawn(async move {
+                let mut video_stream = inner
+                    .stream_screenshot(tonic::Request::new(img_format))
+                    .await?
+                    .into_inner();
+                let (first, info) = first_frame(&mut video_stream).await?;
+                // Unbounded, the emulator may deliver more frames than `fps` asks for
+                let puffer =
+                    video::StreamPuffer::new(usize::MAX, 0, fps, 0, 0, info.width, info.height);
+                puffer.set_drop_excess_frames(drop_excess_frames);
+                puffer.set_overlay(overlay_timestamp, caption);
+                puffer.push_video(first).await;
+
+                let start = std::time::Instant::now();
+                while let Some(remaining) = max_duration.checked_sub(start.elapsed()) {
+                    match tokio::time::timeout(remaining, video_stream.message()).await {
+                        Ok(Ok(Some(frame))) => puffer.push_video(frame).await,
+                        Ok(Ok(None)) => break, // stream ended
+                        Ok(Err(e)) => {
+                            tracing::warn!("error reading video stream: {}", e);
+                            break;
+                        }
+                        Err(_) => break, // duration elapsed
+                    }
+                }
+
+                puffer.save_last_to_mp4(&out_path).await?;
+                Ok::<std::path::PathBuf, Error>(out_path)
+            });
+            self.track(handle.abort_handle());
+            handles.push(handle);
+        }
+
+        let mut written = Vec::with_capacity(handles.len());
+        for handle in handles {
+            written.push(handle.await??);
+        }
+        Ok(written)
+    }
+}
+
+impl Drop for DeviceGrpcClient {
+    fn drop(&mut self) {
+        for task in self.background.get_mut().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+/// Wait for the first frame of a screenshot stream and read its actual geometry.
+async fn first_frame(
+    stream: &mut tonic::Streaming<Image>,
+) -> Result<(Image, video::ImageInfo), Error> {
+    let frame = stream.message().await?.ok_or_else(|| {
+        Error::Decode("screenshot stream ended before the first frame".to_string())
+    })?;
+    let info = video::ImageInfo::from_image(&frame)?;
+    Ok((frame, info))
+}
+
+/// GPS fix sent while driving a route.
+fn route_fix(latitude: f64, longitude: f64, speed: f64, bearing: f64) -> GpsState {
+    GpsState {
+        passive_update: false,
+        latitude,
+        longitude,
+        speed,
+        bearing,
+        altitude: 0.0,
+        satellites: 8,
+    }
+}
+
+/// Status codes worth retrying for idempotent calls.
+fn is_transient(status: &tonic::Status) -> bool {
+    matches!(
+        status.code(),
+        tonic::Code::Unavailable | tonic::Code::DeadlineExceeded
+    )
+}
+
+/// Single-finger touch on the main display; `pressure` 0 lifts the finger.
+fn touch_event(x: i32, y: i32, pressure: i32) -> TouchEvent {
+    let touch = Touch {
+        x,
+        y,
+        identifier: 0,
+        pressure,
+        touch_major: 0,
+        touch_minor: 0,
+        // expiration and orientation are enums/ints; leave as defaults (0)
+        expiration: 0,
+        orientation: 0,
+    };
+    TouchEvent {
+        touches: vec![touch],
+        display: 0,
+    }
+}
+
+/// Encode `image` to `path`, picking the format from the file extension.
+fn save_image(image: &image::DynamicImage, path: &std::path::Path) -> Result<(), Error> {
+    image
+        .save(path)
+        .map_err(|e| Error::Encode(format!("cannot save {}: {}", path.display(), e)))
+}
+
+/// How `DeviceGrpcClient::tap_and_capture` waits before capturing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Settle {
+    /// Sleep for a fixed time
+    Delay(std::time::Duration),
+    /// Wait until the screen is stable, see `DeviceGrpcClient::wait_for_stable_timeout`
+    Stable {
+        poll: std::time::Duration,
+        stable_for: std::time::Duration,
+        threshold: f32,
+        timeout: std::time::Duration,
+    },
+}
+
+impl From<std::time::Duration> for Settle {
+    fn from(delay: std::time::Duration) -> Self {
+        Settle::Delay(delay)
+    }
+}
+
+/// Output container/codec used by `record_audio_as`.
+#[cfg(feature = "video")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioContainer {
+    Mp3,
+    /// Raw AAC stream (ADTS)
+    Aac,
+    /// AAC in an MP4 (`.m4a`) container
+    M4a,
+    Wav,
+    Flac,
+    Opus,
+}
+
+#[cfg(feature = "video")]
+impl AudioContainer {
+    /// Guess the container from a file extension (e.g. "out.flac" -> `Flac`).
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "mp3" => Some(AudioContainer::Mp3),
+            "aac" => Some(AudioContainer::Aac),
+            "m4a" => Some(AudioContainer::M4a),
+            "wav" => Some(AudioContainer::Wav),
+            "flac" => Some(AudioContainer::Flac),
+            "opus" | "ogg" => Some(AudioContainer::Opus),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -696,6 +1985,14 @@ pub struct RecordingConfig {
     pub display: u32,
     /// Audio sample rate (Hz), only used if include_audio is true
     pub audio_sample_rate: u64,
+    /// Drop frames the emulator delivers faster than `fps` (false keeps every frame)
+    pub drop_excess_frames: bool,
+    /// Burn each frame's timestamp (ms) into its top-left corner, to check timing and A/V sync
+    pub overlay_timestamp: bool,
+    /// Text burned in above the timestamp (upper-case letters, digits and basic punctuation)
+    pub overlay_caption: Option<String>,
+    /// How frames reach the client. `record_all_displays` always uses `Transport::Grpc`
+    pub transport: Transport,
 }
 
 impl Default for RecordingConfig {
@@ -707,6 +2004,10 @@ impl Default for RecordingConfig {
             height: 0,
             display: 0,
             audio_sample_rate: 44100,
+            drop_excess_frames: false,
+            overlay_timestamp: false,
+            overlay_caption: None,
+            transport: Transport::Grpc,
         }
     }
 }
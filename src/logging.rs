@@ -0,0 +1,22 @@
+// stderr subscriber for the crate's `tracing` events (feature `log-stderr`)
+
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable holding the filter, in `EnvFilter` syntax (`ro_grpc=debug`, `trace`...).
+pub const LOG_ENV: &str = "RO_GRPC_LOG";
+
+/// Filter used when `RO_GRPC_LOG` is unset or invalid.
+const DEFAULT_FILTER: &str = "ro_grpc=info";
+
+/// Print log events to stderr, filtered by `RO_GRPC_LOG` (`ro_grpc=info` by default).
+///
+/// The library never installs a subscriber itself, so it stays silent unless the host
+/// application sets one up or calls this. Does nothing if a global subscriber is already set.
+pub fn init() {
+    let filter =
+        EnvFilter::try_from_env(LOG_ENV).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
@@ -0,0 +1,157 @@
+// Capture health counters, with Prometheus text exposition
+
+use std::fmt::Write;
+
+/// Snapshot of `StreamPuffer` activity since it was created, see `StreamPuffer::metrics`.
+///
+/// Counters only grow; the `*_buffered` fields are gauges of the current ring buffer fill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PufferMetrics {
+    /// Video frames passed to `push_video`
+    pub frames_received: u64,
+    /// Frames discarded by `set_drop_excess_frames` before reaching the buffer
+    pub frames_rate_limited: u64,
+    /// Buffered frames evicted to make room for newer ones
+    pub frames_evicted: u64,
+    /// Audio packets passed to `push_audio`
+    pub audio_chunks_received: u64,
+    /// Buffered audio packets evicted to make room for newer ones
+    pub audio_chunks_evicted: u64,
+    /// PCM bytes passed to `push_audio`
+    pub audio_bytes: u64,
+    /// Frames currently held, out of `frames_capacity`
+    pub frames_buffered: usize,
+    pub frames_capacity: usize,
+    /// Audio packets currently held, out of `audio_chunks_capacity`
+    pub audio_chunks_buffered: usize,
+    pub audio_chunks_capacity: usize,
+}
+
+impl PufferMetrics {
+    /// Render in the Prometheus text format, metric names prefixed with `prefix`
+    /// (e.g. `"ro_puffer"` gives `ro_puffer_frames_received_total`).
+    pub fn to_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        counter(
+            &mut out,
+            prefix,
+            "frames_received",
+            "Video frames pushed",
+            self.frames_received,
+        );
+        counter(
+            &mut out,
+            prefix,
+            "frames_rate_limited",
+            "Video frames dropped above the target frame rate",
+            self.frames_rate_limited,
+        );
+        counter(
+            &mut out,
+            prefix,
+            "frames_evicted",
+            "Video frames evicted from the full buffer",
+            self.frames_evicted,
+        );
+        counter(
+            &mut out,
+            prefix,
+            "audio_chunks_received",
+            "Audio packets pushed",
+            self.audio_chunks_received,
+        );
+        counter(
+            &mut out,
+            prefix,
+            "audio_chunks_evicted",
+            "Audio packets evicted from the full buffer",
+            self.audio_chunks_evicted,
+        );
+        counter(
+            &mut out,
+            prefix,
+            "audio_bytes",
+            "PCM bytes pushed",
+            self.audio_bytes,
+        );
+        gauge(
+            &mut out,
+            prefix,
+            "frames_buffered",
+            "Video frames currently buffered",
+            self.frames_buffered,
+        );
+        gauge(
+            &mut out,
+            prefix,
+            "frames_capacity",
+            "Video frame buffer capacity",
+            self.frames_capacity,
+        );
+        gauge(
+            &mut out,
+            prefix,
+            "audio_chunks_buffered",
+            "Audio packets currently buffered",
+            self.audio_chunks_buffered,
+        );
+        gauge(
+            &mut out,
+            prefix,
+            "audio_chunks_capacity",
+            "Audio packet buffer capacity",
+            self.audio_chunks_capacity,
+        );
+        out
+    }
+}
+
+/// Snapshot of `DeviceGrpcClient` activity, see `DeviceGrpcClient::metrics`.
+///
+/// Reconnects are not counted: tonic's `Channel` connects lazily and re-establishes a
+/// dropped connection on the next call without telling the client. `connection_errors`
+/// counts what shows instead, the calls that found the emulator unreachable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClientMetrics {
+    /// Read calls repeated after a transient failure, see `with_retry_reads`
+    pub read_retries: u64,
+    /// Calls answered `Unavailable`: every read attempt (retried or not), other unary
+    /// calls, and streams that failed to open
+    pub connection_errors: u64,
+}
+
+impl ClientMetrics {
+    /// Render in the Prometheus text format, metric names prefixed with `prefix`.
+    pub fn to_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        counter(
+            &mut out,
+            prefix,
+            "read_retries",
+            "Read calls retried after Unavailable or DeadlineExceeded",
+            self.read_retries,
+        );
+        counter(
+            &mut out,
+            prefix,
+            "connection_errors",
+            "Read call attempts that found the emulator unavailable",
+            self.connection_errors,
+        );
+        out
+    }
+}
+
+fn counter(out: &mut String, prefix: &str, name: &str, help: &str, value: u64) {
+    let name = format!("{}_{}_total", prefix, name);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn gauge(out: &mut String, prefix: &str, name: &str, help: &str, value: usize) {
+    let name = format!("{}_{}", prefix, name);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
@@ -0,0 +1,106 @@
+// Pluggable text recognition over emulator screenshots (feature `ocr`)
+
+use crate::error::{Error, Result};
+use crate::proto::image_format::ImgFormat;
+use crate::proto::Image;
+use crate::DeviceGrpcClient;
+
+/// Axis aligned box in screenshot pixel coordinates, origin top-left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BoundingBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BoundingBox {
+    /// Center point, suitable for `DeviceGrpcClient::tap`.
+    pub fn center(&self) -> (i32, i32) {
+        (
+            (self.x + self.width / 2) as i32,
+            (self.y + self.height / 2) as i32,
+        )
+    }
+}
+
+/// A piece of text found on screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextRegion {
+    pub text: String,
+    pub bbox: BoundingBox,
+}
+
+/// An OCR backend (tesseract, a cloud service, a test stub...).
+///
+/// `recognize` receives an RGB888 screenshot: `img.image` holds 3 bytes per
+/// pixel and the dimensions are in `img.format`.
+pub trait Ocr: Send + Sync {
+    fn recognize(&self, img: &Image) -> Vec<TextRegion>;
+}
+
+impl DeviceGrpcClient {
+    /// Install the OCR backend used by `screenshot_text`.
+    pub fn set_ocr(&mut self, ocr: impl Ocr + 'static) {
+        self.ocr = Some(Box::new(ocr));
+    }
+
+    /// Take an RGB888 screenshot and run it through the configured OCR backend.
+    pub async fn screenshot_text(&mut self) -> Result<Vec<TextRegion>> {
+        if self.ocr.is_none() {
+            return Err(Error::Other("no OCR backend configured".to_string()));
+        }
+        let image = self.get_screenshot_as(ImgFormat::Rgb888).await?;
+        let ocr = self.ocr.as_ref().expect("checked above");
+        Ok(ocr.recognize(&image))
+    }
+
+    /// OCR the screen and tap the center of the region matching `needle`.
+    ///
+    /// Regions whose text equals `needle` (ignoring surrounding whitespace) win
+    /// over regions that merely contain it. Fails when nothing matches or when
+    /// several regions match equally well, see `tap_text_first` for the lenient
+    /// variant.
+    pub async fn tap_text(&mut self, needle: &str) -> Result<TextRegion> {
+        self.tap_text_with(needle, false).await
+    }
+
+    /// Like `tap_text` but taps the first of several ambiguous matches.
+    pub async fn tap_text_first(&mut self, needle: &str) -> Result<TextRegion> {
+        self.tap_text_with(needle, true).await
+    }
+
+    async fn tap_text_with(&mut self, needle: &str, pick_first: bool) -> Result<TextRegion> {
+        let regions = self.screenshot_text().await?;
+        let matches = find_text(&regions, needle);
+        if matches.is_empty() {
+            return Err(Error::Other(format!(
+                "text {:?} not found on screen",
+                needle
+            )));
+        }
+        if matches.len() > 1 && !pick_first {
+            let found: Vec<&str> = matches.iter().map(|r| r.text.as_str()).collect();
+            return Err(Error::Other(format!(
+                "text {:?} is ambiguous, {} regions match: {:?}",
+                needle,
+                matches.len(),
+                found
+            )));
+        }
+        let region = matches[0].clone();
+        let (x, y) = region.bbox.center();
+        self.tap(x, y).await?;
+        Ok(region)
+    }
+}
+
+/// Regions matching `needle`: exact (trimmed) matches if any, otherwise substring matches.
+pub fn find_text<'a>(regions: &'a [TextRegion], needle: &str) -> Vec<&'a TextRegion> {
+    let needle = needle.trim();
+    let exact: Vec<&TextRegion> = regions.iter().filter(|r| r.text.trim() == needle).collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+    regions.iter().filter(|r| r.text.contains(needle)).collect()
+}
@@ -0,0 +1,39 @@
+// Telephony helpers behind `send_sms` / `incoming_call`
+
+use crate::error::{Error, Result};
+use crate::proto::phone_response::Response;
+use crate::proto::PhoneResponse;
+
+/// Longest number accepted, in digits (E.164 allows 15, leave room for extensions).
+const MAX_DIGITS: usize = 20;
+
+/// Trimmed `number` if it looks like a phone number: digits with an optional leading `+`
+/// and the usual separators (spaces, `-`, `.`, parentheses). The modem does the strict check.
+pub(crate) fn check_number(number: &str) -> Result<String> {
+    let number = number.trim();
+    let body = number.strip_prefix('+').unwrap_or(number);
+    let digits = body.chars().filter(char::is_ascii_digit).count();
+    let well_formed = body
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, ' ' | '-' | '.' | '(' | ')'));
+    if !well_formed || digits == 0 || digits > MAX_DIGITS {
+        return Err(Error::Phone(format!("malformed phone number {:?}", number)));
+    }
+    Ok(number.to_string())
+}
+
+/// `Ok` for `Response::Ok`, otherwise `Error::Phone` naming the operation and the reason.
+pub(crate) fn check_response(operation: &str, response: &PhoneResponse) -> Result<()> {
+    match Response::try_from(response.response) {
+        Ok(Response::Ok) => Ok(()),
+        Ok(reason) => Err(Error::Phone(format!(
+            "{} rejected: {}",
+            operation,
+            reason.as_str_name()
+        ))),
+        Err(_) => Err(Error::Phone(format!(
+            "{} rejected with unknown response {}",
+            operation, response.response
+        ))),
+    }
+}
@@ -0,0 +1,146 @@
+// Encoder-free capture: raw RGB888 frames and PCM on disk, muxed later
+
+use crate::error::{Error, Result};
+use crate::proto::audio_format::SampleFormat;
+use crate::proto::{AudioPacket, Image};
+use crate::video::pcm::{pcm_channels, pcm_sample_rate};
+use crate::video::{packed_rgb888, ImageInfo};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// File name of the manifest inside a raw capture directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// File name of the interleaved PCM inside a raw capture directory.
+pub const AUDIO_FILE: &str = "audio.pcm";
+
+/// Description of a directory written by `DeviceGrpcClient::record_raw`.
+///
+/// Frames are stored one per file as packed RGB888 rows (`width * height * 3` bytes);
+/// audio is a single headerless PCM file in the layout of the first audio packet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawManifest {
+    /// Size of the first frame; later frames may differ (see `RawFrame`)
+    pub width: u32,
+    pub height: u32,
+    /// Requested capture rate; the real timing is in the frame timestamps
+    pub fps: u32,
+    pub frames: Vec<RawFrame>,
+    pub audio: Option<RawAudio>,
+}
+
+/// One frame file of a raw capture.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawFrame {
+    /// File name relative to the capture directory
+    pub file: String,
+    pub width: u32,
+    pub height: u32,
+    /// Emulator capture time in ms
+    pub timestamp_ms: u64,
+}
+
+/// The PCM sidecar of a raw capture.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawAudio {
+    /// File name relative to the capture directory
+    pub file: String,
+    pub sample_rate: u32,
+    pub channels: u32,
+    /// `s16le` or `u8`
+    pub sample_format: String,
+    /// Emulator time (ms) of the first sample
+    pub timestamp_ms: u64,
+    pub bytes: u64,
+}
+
+impl RawManifest {
+    /// Read `manifest.json` from a capture directory.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let json = std::fs::read_to_string(dir.as_ref().join(MANIFEST_FILE))?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::Decode(format!("invalid raw capture manifest: {}", e)))
+    }
+}
+
+/// Streams frames and audio of a capture into a directory as they arrive.
+pub(crate) struct RawCaptureWriter {
+    dir: PathBuf,
+    manifest: RawManifest,
+    audio: Option<tokio::io::BufWriter<tokio::fs::File>>,
+}
+
+impl RawCaptureWriter {
+    pub async fn create(dir: impl Into<PathBuf>, fps: u32) -> Result<Self> {
+        let dir = dir.into();
+        tokio::fs::create_dir_all(&dir).await?;
+        Ok(Self {
+            dir,
+            manifest: RawManifest {
+                width: 0,
+                height: 0,
+                fps,
+                frames: Vec::new(),
+                audio: None,
+            },
+            audio: None,
+        })
+    }
+
+    /// Write `frame` as the next numbered `.rgb` file, converted to packed RGB888 from
+    /// the format the emulator reported (see `video::packed_rgb888`).
+    pub async fn push_frame(&mut self, frame: Image) -> Result<()> {
+        let info = ImageInfo::from_image(&frame)?;
+        let pixels = packed_rgb888(&frame)?;
+        if self.manifest.frames.is_empty() {
+            self.manifest.width = info.width;
+            self.manifest.height = info.height;
+        }
+        let file = format!("frame_{:06}.rgb", self.manifest.frames.len());
+        tokio::fs::write(self.dir.join(&file), &pixels).await?;
+        self.manifest.frames.push(RawFrame {
+            file,
+            width: info.width,
+            height: info.height,
+            timestamp_ms: frame.timestamp_us / 1000,
+        });
+        Ok(())
+    }
+
+    /// Append the samples of `packet`; the first packet fixes the PCM layout.
+    pub async fn push_audio(&mut self, packet: AudioPacket) -> Result<()> {
+        if self.audio.is_none() {
+            let format = packet.format.clone().unwrap_or_default();
+            let file = tokio::fs::File::create(self.dir.join(AUDIO_FILE)).await?;
+            self.audio = Some(tokio::io::BufWriter::new(file));
+            self.manifest.audio = Some(RawAudio {
+                file: AUDIO_FILE.to_string(),
+                sample_rate: pcm_sample_rate(&format),
+                channels: pcm_channels(&format) as u32,
+                sample_format: match SampleFormat::try_from(format.format) {
+                    Ok(SampleFormat::AudFmtU8) => "u8",
+                    _ => "s16le",
+                }
+                .to_string(),
+                timestamp_ms: packet.timestamp / 1000,
+                bytes: 0,
+            });
+        }
+        if let (Some(writer), Some(audio)) = (&mut self.audio, &mut self.manifest.audio) {
+            writer.write_all(&packet.audio).await?;
+            audio.bytes += packet.audio.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Flush the audio and write `manifest.json`.
+    pub async fn finish(mut self) -> Result<RawManifest> {
+        if let Some(writer) = &mut self.audio {
+            writer.flush().await?;
+        }
+        let json = serde_json::to_string_pretty(&self.manifest)
+            .map_err(|e| Error::Other(format!("cannot serialize raw capture manifest: {}", e)))?;
+        tokio::fs::write(self.dir.join(MANIFEST_FILE), json).await?;
+        Ok(self.manifest)
+    }
+}
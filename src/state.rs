@@ -0,0 +1,140 @@
+// Point-in-time device snapshot for reports
+
+use crate::display::Display;
+use crate::error::Result;
+use crate::proto::battery_state::{BatteryCharger, BatteryHealth, BatteryStatus};
+use crate::proto::{BatteryState, DisplayConfigurations, GpsState, Image, VmRunState};
+use crate::video::ImageInfo;
+use crate::vm::VmStatus;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// File name of the screenshot written next to the JSON by `DeviceState::write_report`.
+const REPORT_SCREENSHOT: &str = "screenshot.png";
+/// File name of the JSON written by `DeviceState::write_report`.
+const REPORT_JSON: &str = "state.json";
+
+/// Everything about the device at one moment, as returned by `DeviceGrpcClient::capture_state`.
+///
+/// Serializes to JSON without the screenshot pixels; `write_report` stores the PNG as a
+/// side file and records its name in `screenshot.file`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceState {
+    /// When the capture started, RFC 3339 in UTC
+    pub captured_at: String,
+    pub screenshot: ScreenshotSnapshot,
+    pub battery: BatterySnapshot,
+    pub gps: GpsSnapshot,
+    pub displays: Vec<Display>,
+    pub vm: VmStatus,
+}
+
+/// Main display screenshot of a `DeviceState`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenshotSnapshot {
+    pub width: u32,
+    pub height: u32,
+    pub timestamp_us: u64,
+    /// Side file holding the PNG, relative to the report JSON (set by `write_report`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Encoded PNG
+    #[serde(skip)]
+    pub png: Vec<u8>,
+}
+
+/// `BatteryState` with enums spelled out.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatterySnapshot {
+    pub has_battery: bool,
+    pub is_present: bool,
+    pub charger: String,
+    pub charge_level: i32,
+    pub health: String,
+    pub status: String,
+}
+
+/// `GpsState` as reported by the emulator.
+#[derive(Debug, Clone, Serialize)]
+pub struct GpsSnapshot {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+    pub speed: f64,
+    pub bearing: f64,
+    pub satellites: i32,
+}
+
+impl DeviceState {
+    pub(crate) fn new(
+        captured_at: String,
+        screenshot: Image,
+        battery: &BatteryState,
+        gps: &GpsState,
+        displays: &DisplayConfigurations,
+        vm: &VmRunState,
+    ) -> Result<Self> {
+        let info = ImageInfo::from_image(&screenshot)?;
+        Ok(Self {
+            captured_at,
+            screenshot: ScreenshotSnapshot {
+                width: info.width,
+                height: info.height,
+                timestamp_us: info.timestamp_us,
+                file: None,
+                png: screenshot.image,
+            },
+            battery: BatterySnapshot::from(battery),
+            gps: GpsSnapshot::from(gps),
+            displays: Display::list(displays),
+            vm: VmStatus::from(vm),
+        })
+    }
+
+    /// Write `state.json` and `screenshot.png` into `dir` (created if missing).
+    /// Returns the path of the JSON file.
+    pub fn write_report(&self, dir: impl AsRef<Path>) -> Result<PathBuf> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(REPORT_SCREENSHOT), &self.screenshot.png)?;
+
+        let mut state = self.clone();
+        state.screenshot.file = Some(REPORT_SCREENSHOT.to_string());
+        let json = serde_json::to_string_pretty(&state)
+            .map_err(|e| crate::Error::Other(format!("cannot serialize device state: {}", e)))?;
+        let json_path = dir.join(REPORT_JSON);
+        std::fs::write(&json_path, json)?;
+        Ok(json_path)
+    }
+}
+
+impl From<&BatteryState> for BatterySnapshot {
+    fn from(state: &BatteryState) -> Self {
+        Self {
+            has_battery: state.has_battery,
+            is_present: state.is_present,
+            charger: enum_name(BatteryCharger::try_from(state.charger).map(|c| c.as_str_name())),
+            charge_level: state.charge_level,
+            health: enum_name(BatteryHealth::try_from(state.health).map(|h| h.as_str_name())),
+            status: enum_name(BatteryStatus::try_from(state.status).map(|s| s.as_str_name())),
+        }
+    }
+}
+
+impl From<&GpsState> for GpsSnapshot {
+    fn from(state: &GpsState) -> Self {
+        Self {
+            latitude: state.latitude,
+            longitude: state.longitude,
+            altitude: state.altitude,
+            speed: state.speed,
+            bearing: state.bearing,
+            satellites: state.satellites,
+        }
+    }
+}
+
+/// Proto enum name, or "UNKNOWN" for values this build does not know.
+fn enum_name<E>(name: std::result::Result<&'static str, E>) -> String {
+    name.unwrap_or("UNKNOWN").to_string()
+}
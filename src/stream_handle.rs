@@ -0,0 +1,131 @@
+// Explicit teardown for the server streams returned by `DeviceGrpcClient::stream_*`
+
+use crate::error::Error;
+use futures::task::AtomicWaker;
+use futures::Stream;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// A server stream (screenshots, audio, sensors, logcat, ...) with an explicit `stop`.
+///
+/// Stopping drops the underlying `tonic::Streaming`, which resets the HTTP/2 stream and
+/// cancels the request on the emulator side. A consumer blocked in `message` or `next`
+/// wakes up and sees the end of the stream, so recorders and watchers can be torn down
+/// from another task without aborting it.
+///
+/// ```ignore
+/// let mut frames = StreamHandle::new(client.stream_screenshot(fmt).await?);
+/// let stopper = frames.stopper();
+/// tokio::spawn(async move {
+///     tokio::time::sleep(Duration::from_secs(5)).await;
+///     stopper.stop();
+/// });
+/// while let Some(frame) = frames.message().await? {
+///     // ...
+/// }
+/// ```
+pub struct StreamHandle<T> {
+    stream: Option<tonic::Streaming<T>>,
+    stop: Arc<StopState>,
+}
+
+/// Cloneable remote control that stops a `StreamHandle` from any task.
+#[derive(Clone)]
+pub struct StreamStopper {
+    stop: Arc<StopState>,
+}
+
+#[derive(Default)]
+struct StopState {
+    stopped: AtomicBool,
+    // consumer parked in `poll_next`, woken by `StreamStopper::stop`
+    waker: AtomicWaker,
+}
+
+impl StopState {
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.waker.wake();
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+impl<T> StreamHandle<T> {
+    pub fn new(stream: tonic::Streaming<T>) -> Self {
+        Self {
+            stream: Some(stream),
+            stop: Arc::default(),
+        }
+    }
+
+    /// Cancel the request now. Further reads return `Ok(None)`.
+    pub fn stop(&mut self) {
+        self.stop.stop();
+        self.stream = None;
+    }
+
+    /// Handle for stopping the stream from another task.
+    pub fn stopper(&self) -> StreamStopper {
+        StreamStopper {
+            stop: self.stop.clone(),
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_stopped()
+    }
+
+    /// Next message, `Ok(None)` once the server ends the stream or it was stopped.
+    /// Mirrors `tonic::Streaming::message`.
+    pub async fn message(&mut self) -> Result<Option<T>, Error> {
+        use futures::StreamExt;
+        self.next().await.transpose()
+    }
+}
+
+impl<T> From<tonic::Streaming<T>> for StreamHandle<T> {
+    fn from(stream: tonic::Streaming<T>) -> Self {
+        Self::new(stream)
+    }
+}
+
+impl<T> Stream for StreamHandle<T> {
+    type Item = Result<T, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        // Register before checking the flag so a concurrent `stop` cannot be missed
+        this.stop.waker.register(cx.waker());
+        if this.stop.is_stopped() {
+            this.stream = None;
+            return Poll::Ready(None);
+        }
+        let Some(stream) = this.stream.as_mut() else {
+            return Poll::Ready(None);
+        };
+        match Pin::new(stream).poll_next(cx) {
+            Poll::Ready(None) => {
+                this.stream = None;
+                Poll::Ready(None)
+            }
+            Poll::Ready(Some(item)) => Poll::Ready(Some(item.map_err(Error::from))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl StreamStopper {
+    /// Cancel the request; the consumer's pending or next read ends the stream.
+    pub fn stop(&self) {
+        self.stop.stop();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_stopped()
+    }
+}
@@ -0,0 +1,121 @@
+// Side channels for streamed screenshot frames
+
+use crate::error::{Error, Result};
+use crate::proto::image_format::ImgFormat;
+use crate::proto::image_transport::TransportChannel;
+use crate::proto::{Image, ImageFormat, ImageTransport};
+use std::path::{Path, PathBuf};
+
+/// How the emulator hands streamed frames over, see `RecordingConfig::transport`.
+///
+/// Only streams use it: single screenshots always come back in the gRPC reply.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Transport {
+    /// Pixels travel inside each `Image` message. Works everywhere, including remote
+    /// emulators.
+    #[default]
+    Grpc,
+    /// The emulator writes every frame into the file at `path`, mapped into its memory,
+    /// and sends only the metadata over gRPC. This saves encoding and copying a full
+    /// frame per message (about 7.7 MB for a 1080x2400 RGB888 frame), which pays off for
+    /// large displays at a high `fps`.
+    ///
+    /// Constraints:
+    /// * the emulator must see `path` under the same name, i.e. run on the same host (or
+    ///   share the directory with the container it runs in); a tmpfs such as `/dev/shm`
+    ///   keeps the frames off the disk
+    /// * the file is sized for one frame of the recording (the display's native size
+    ///   where `RecordingConfig::width` or `height` is 0)
+    /// * the emulator overwrites the file in place, so a frame read while the next one
+    ///   is written can tear
+    /// * emulators without MMAP support ignore the request and keep sending pixels
+    ///
+    /// Streams fall back to `Grpc` (with a warning) when the file cannot be prepared or
+    /// the emulator's first frame cannot be read from it.
+    Mmap { path: PathBuf },
+}
+
+impl Transport {
+    /// Memory-mapped transport through the file at `path`, created or truncated when a
+    /// stream starts. The client owns the file; it is left in place afterwards.
+    pub fn mmap(path: impl Into<PathBuf>) -> Self {
+        Transport::Mmap { path: path.into() }
+    }
+
+    pub fn is_grpc(&self) -> bool {
+        matches!(self, Transport::Grpc)
+    }
+
+    /// `ImageFormat::transport` requesting this transport (`None` for `Grpc`).
+    pub fn to_proto(&self) -> Option<ImageTransport> {
+        match self {
+            Transport::Grpc => None,
+            Transport::Mmap { path } => Some(ImageTransport {
+                channel: TransportChannel::Mmap as i32,
+                handle: file_url(path),
+            }),
+        }
+    }
+
+    /// Create the shared file sized for frames of `format`, and return `format` with
+    /// the transport requested.
+    pub(crate) fn prepare(&self, format: &ImageFormat) -> Result<ImageFormat> {
+        let mut format = format.clone();
+        if let Transport::Mmap { path } = self {
+            let len = frame_len(&format).ok_or_else(|| {
+                Error::Other(
+                    "mmap transport needs an explicit size and a raw RGB format".to_string(),
+                )
+            })?;
+            let file = std::fs::File::create(path)?;
+            file.set_len(len)?;
+        }
+        format.transport = self.to_proto();
+        Ok(format)
+    }
+
+    /// Fill in the pixels of a `frame` that came without them. Frames that carry their
+    /// pixels (always the case with `Grpc`) are left as they are.
+    pub(crate) async fn resolve(&self, frame: &mut Image) -> Result<()> {
+        let Transport::Mmap { path } = self else {
+            return Ok(());
+        };
+        if !frame.image.is_empty() {
+            return Ok(());
+        }
+        let len = frame
+            .format
+            .as_ref()
+            .and_then(frame_len)
+            .ok_or_else(|| Error::Decode("mmap frame without a raw size".to_string()))?;
+        let mut pixels = tokio::fs::read(path).await?;
+        if (pixels.len() as u64) < len {
+            return Err(Error::Decode(format!(
+                "mmap file {} holds {} bytes, frame needs {}",
+                path.display(),
+                pixels.len(),
+                len
+            )));
+        }
+        pixels.truncate(len as usize);
+        frame.image = pixels;
+        Ok(())
+    }
+}
+
+/// Bytes of one raw frame of `format`, `None` for PNG or an unset size.
+fn frame_len(format: &ImageFormat) -> Option<u64> {
+    let bytes_per_pixel = match ImgFormat::try_from(format.format).ok()? {
+        ImgFormat::Rgb888 => 3,
+        ImgFormat::Rgba8888 => 4,
+        ImgFormat::Png => return None,
+    };
+    let len = format.width as u64 * format.height as u64 * bytes_per_pixel;
+    (len > 0).then_some(len)
+}
+
+/// `file://` URL of `path`, made absolute as the emulator resolves it on its own.
+fn file_url(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    format!("file://{}", path.display())
+}
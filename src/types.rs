@@ -0,0 +1,28 @@
+// Curated re-exports of the generated proto types
+//
+// Code outside this crate should prefer these names over deep `proto::...` paths;
+// nested proto enums are flattened and prefixed where the bare name is ambiguous.
+
+pub use crate::proto::{
+    AudioFormat, AudioPacket, BatteryState, BrightnessValue, ClipData, DisplayConfiguration,
+    DisplayConfigurations, GpsState, Image, ImageFormat, KeyboardEvent, LogMessage, LogcatEntry,
+    MouseEvent, Notification, PhoneCall, PhysicalModelValue, Posture, Rotation, SensorValue,
+    SmsMessage, Touch, TouchEvent, VmRunState,
+};
+
+pub use crate::proto::audio_format::{
+    Channels as AudioChannels, DeliveryMode as AudioDeliveryMode, SampleFormat as AudioSampleFormat,
+};
+pub use crate::proto::battery_state::{BatteryCharger, BatteryHealth, BatteryStatus};
+pub use crate::proto::brightness_value::LightType;
+pub use crate::proto::display_configuration::DisplayFlags;
+pub use crate::proto::image_format::ImgFormat;
+pub use crate::proto::keyboard_event::{KeyCodeType, KeyEventType};
+pub use crate::proto::log_message::LogType;
+pub use crate::proto::logcat_entry::LogLevel;
+pub use crate::proto::phone_call::Operation as PhoneOperation;
+pub use crate::proto::physical_model_value::PhysicalType;
+pub use crate::proto::posture::PostureValue;
+pub use crate::proto::rotation::SkinRotation;
+pub use crate::proto::sensor_value::SensorType;
+pub use crate::proto::vm_run_state::RunState;
@@ -0,0 +1,44 @@
+// gRPC channel over a Unix domain socket, for `unix:///path` endpoints
+
+use crate::error::Error;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::net::UnixStream;
+use tonic::codegen::http::Uri;
+use tonic::codegen::Service;
+use tonic::transport::{Channel, Endpoint};
+
+/// Connect a channel to the controller listening on the socket at `path`.
+pub(crate) async fn connect(path: &str) -> Result<Channel, Error> {
+    // The URI only fills the HTTP/2 :authority header, the connector ignores it
+    let channel = Endpoint::from_static("http://localhost")
+        .connect_with_connector(UdsConnector {
+            path: Arc::new(PathBuf::from(path)),
+        })
+        .await?;
+    Ok(channel)
+}
+
+/// Opens a new `UnixStream` to `path` for every connection tonic asks for.
+#[derive(Clone)]
+struct UdsConnector {
+    path: Arc<PathBuf>,
+}
+
+impl Service<Uri> for UdsConnector {
+    type Response = UnixStream;
+    type Error = std::io::Error;
+    type Future = Pin<Box<dyn Future<Output = std::io::Result<UnixStream>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let path = self.path.clone();
+        Box::pin(async move { UnixStream::connect(path.as_path()).await })
+    }
+}
@@ -0,0 +1,191 @@
+use crate::error::{Error, Result};
+use crate::proto::image_format::ImgFormat;
+use crate::proto::rotation::SkinRotation;
+use crate::proto::Image;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, RgbImage, RgbaImage};
+use std::borrow::Cow;
+
+/// Geometry and encoding of a screenshot as actually delivered by the emulator.
+///
+/// Requesting a width/height of 0 yields the native resolution; this is where the
+/// resulting size shows up, so prefer it over the size that was asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    pub format: ImgFormat,
+    pub rotation: SkinRotation,
+    pub timestamp_us: u64,
+}
+
+impl ImageInfo {
+    /// Read the geometry from `img.format` (the deprecated `Image.width`/`height` fields are ignored).
+    pub fn from_image(img: &Image) -> Result<Self> {
+        let fmt = img
+            .format
+            .as_ref()
+            .ok_or_else(|| Error::Decode("screenshot carries no image format".to_string()))?;
+        let format = ImgFormat::try_from(fmt.format)
+            .map_err(|_| Error::Decode(format!("unknown image format {}", fmt.format)))?;
+        let rotation = fmt
+            .rotation
+            .as_ref()
+            .and_then(|r| SkinRotation::try_from(r.rotation).ok())
+            .unwrap_or(SkinRotation::Portrait);
+        Ok(Self {
+            width: fmt.width,
+            height: fmt.height,
+            format,
+            rotation,
+            timestamp_us: img.timestamp_us,
+        })
+    }
+}
+
+/// Convert an emulator screenshot into an `image` crate image, ready to crop,
+/// resize or save in any format the `image` crate supports.
+///
+/// PNG payloads are decoded (the container is sniffed, so JPEG and friends work too).
+/// Raw RGB888 / RGBA8888 payloads are wrapped without decoding; their dimensions come
+/// from `img.format`, which the emulator fills with the size it actually rendered
+/// (the deprecated `Image.width`/`height` fields are ignored).
+pub fn image_to_dynamic(img: &Image) -> Result<DynamicImage> {
+    let ImageInfo {
+        width,
+        height,
+        format,
+        ..
+    } = ImageInfo::from_image(img)?;
+    let bytes_per_pixel = match format {
+        ImgFormat::Png => {
+            return image::load_from_memory(&img.image)
+                .map_err(|e| Error::Decode(format!("cannot decode screenshot: {}", e)));
+        }
+        ImgFormat::Rgb888 => 3,
+        ImgFormat::Rgba8888 => 4,
+    };
+    if img.image.len() as u64 != width as u64 * height as u64 * bytes_per_pixel {
+        return Err(Error::Decode(format!(
+            "{:?} payload of {} bytes does not match {}x{}",
+            format,
+            img.image.len(),
+            width,
+            height
+        )));
+    }
+    let raw = img.image.clone();
+    let converted = match format {
+        ImgFormat::Rgba8888 => {
+            RgbaImage::from_raw(width, height, raw).map(DynamicImage::ImageRgba8)
+        }
+        _ => RgbImage::from_raw(width, height, raw).map(DynamicImage::ImageRgb8),
+    };
+    Ok(converted.expect("buffer length checked above"))
+}
+
+/// Rectangle in screenshot pixel coordinates, origin top-left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Cut `rect` out of `img`. Fails (instead of clamping) when `rect` is empty or
+/// reaches past the image edges.
+pub fn crop(img: &DynamicImage, rect: Rect) -> Result<DynamicImage> {
+    let (width, height) = img.dimensions();
+    let fits = rect.width > 0
+        && rect.height > 0
+        && rect.x as u64 + rect.width as u64 <= width as u64
+        && rect.y as u64 + rect.height as u64 <= height as u64;
+    if !fits {
+        return Err(Error::Other(format!(
+            "region {:?} is not within the {}x{} screenshot",
+            rect, width, height
+        )));
+    }
+    Ok(img.crop_imm(rect.x, rect.y, rect.width, rect.height))
+}
+
+/// Shrink `img` so its longer side is at most `max_dim`, keeping the aspect ratio.
+/// Images that already fit are returned unchanged (never upscaled).
+pub fn scale_to_fit(img: &DynamicImage, max_dim: u32) -> Result<DynamicImage> {
+    if max_dim == 0 {
+        return Err(Error::Other("max_dim must be at least 1".to_string()));
+    }
+    let (width, height) = img.dimensions();
+    if width <= max_dim && height <= max_dim {
+        return Ok(img.clone());
+    }
+    Ok(img.resize(max_dim, max_dim, FilterType::Triangle))
+}
+
+/// The pixels of `img` as tightly packed RGB888 rows (`width * height * 3` bytes), the
+/// layout the recording encoder expects, keyed off the format the emulator reported in
+/// `img.format` rather than the one requested.
+///
+/// A packed RGB888 payload is borrowed as is. RGBA8888 loses its alpha channel, PNG is
+/// decoded, and rows padded to a wider stride (the stride is inferred from the payload
+/// length, with or without padding after the last row) are cut back to the image width.
+/// The proto has no BGR(A) variant, so channel order is always taken as RGB.
+pub fn packed_rgb888(img: &Image) -> Result<Cow<'_, [u8]>> {
+    let ImageInfo {
+        width,
+        height,
+        format,
+        ..
+    } = ImageInfo::from_image(img)?;
+    let bytes_per_pixel = match format {
+        ImgFormat::Png => {
+            let decoded = image::load_from_memory(&img.image)
+                .map_err(|e| Error::Decode(format!("cannot decode screenshot: {}", e)))?
+                .into_rgb8();
+            if decoded.dimensions() != (width, height) {
+                return Err(Error::Decode(format!(
+                    "PNG of {}x{} reported as {}x{}",
+                    decoded.width(),
+                    decoded.height(),
+                    width,
+                    height
+                )));
+            }
+            return Ok(Cow::Owned(decoded.into_raw()));
+        }
+        ImgFormat::Rgb888 => 3,
+        ImgFormat::Rgba8888 => 4,
+    };
+    let (rows, row_len, len) = (
+        height as usize,
+        width as usize * bytes_per_pixel,
+        img.image.len(),
+    );
+    if bytes_per_pixel == 3 && len == rows * row_len && rows > 0 && row_len > 0 {
+        return Ok(Cow::Borrowed(&img.image));
+    }
+    let stride = if rows == 0 || row_len == 0 {
+        None
+    } else if len % rows == 0 && len / rows >= row_len {
+        Some(len / rows)
+    } else if rows > 1 && len > row_len && (len - row_len) % (rows - 1) == 0 {
+        // padding only between rows
+        Some((len - row_len) / (rows - 1)).filter(|&stride| stride >= row_len)
+    } else {
+        None
+    };
+    let stride = stride.ok_or_else(|| {
+        Error::Decode(format!(
+            "{:?} payload of {} bytes does not match {}x{}",
+            format, len, width, height
+        ))
+    })?;
+    let mut packed = Vec::with_capacity(width as usize * rows * 3);
+    for row in img.image.chunks(stride).take(rows) {
+        for pixel in row[..row_len].chunks_exact(bytes_per_pixel) {
+            packed.extend_from_slice(&pixel[..3]);
+        }
+    }
+    Ok(Cow::Owned(packed))
+}
@@ -0,0 +1,36 @@
+use crate::proto::Image;
+use crate::video::image_to_dynamic;
+
+/// Per-channel difference below which two pixels are considered equal
+/// (absorbs compression noise and cursor blinking anti-aliasing).
+const CHANNEL_TOLERANCE: u8 = 8;
+
+/// Fraction (0.0..=1.0) of pixels that differ between two screenshots.
+///
+/// Accepts PNG, RGBA8888 and RGB888 images. Images of different dimensions or
+/// that cannot be decoded are reported as completely different (1.0).
+pub fn frame_diff(a: &Image, b: &Image) -> f32 {
+    let (Some((aw, ah, a_px)), Some((bw, bh, b_px))) = (to_rgb(a), to_rgb(b)) else {
+        return 1.0;
+    };
+    if aw != bw || ah != bh || a_px.is_empty() {
+        return 1.0;
+    }
+    let changed = a_px
+        .chunks_exact(3)
+        .zip(b_px.chunks_exact(3))
+        .filter(|(p, q)| {
+            p.iter()
+                .zip(q.iter())
+                .any(|(x, y)| x.abs_diff(*y) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    changed as f32 / (aw as usize * ah as usize) as f32
+}
+
+/// Decode `img` into (width, height, packed RGB bytes).
+fn to_rgb(img: &Image) -> Option<(u32, u32, Vec<u8>)> {
+    let rgb = image_to_dynamic(img).ok()?.into_rgb8();
+    let (w, h) = rgb.dimensions();
+    Some((w, h, rgb.into_raw()))
+}
@@ -0,0 +1,84 @@
+// Loudness of the emulator audio stream, for "is anything playing" checks
+
+use crate::proto::{AudioFormat, AudioPacket};
+use crate::video::pcm::{pcm_channels, pcm_sample_rate, pcm_to_f32};
+use std::time::Duration;
+
+/// Level of one window of audio, both in 0.0 (silence) ..= 1.0 (full scale).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioLevel {
+    pub rms: f32,
+    pub peak: f32,
+    /// Emulator timestamp (µs) of the packet that completed the window
+    pub timestamp_us: u64,
+}
+
+impl AudioLevel {
+    /// Whether the loudest sample of the window stays below `threshold`.
+    pub fn is_silent(&self, threshold: f32) -> bool {
+        self.peak < threshold
+    }
+
+    /// RMS in dBFS; `-inf` for digital silence.
+    pub fn rms_dbfs(&self) -> f32 {
+        20.0 * self.rms.log10()
+    }
+}
+
+/// Folds PCM packets into one `AudioLevel` per `window` of audio.
+pub(crate) struct LevelMeter {
+    window: Duration,
+    fallback: AudioFormat,
+    // samples (all channels) still needed to complete the window
+    remaining: u64,
+    sum_squares: f64,
+    peak: f32,
+    count: u64,
+}
+
+impl LevelMeter {
+    /// `fallback` describes packets that arrive without a format.
+    pub(crate) fn new(window: Duration, fallback: AudioFormat) -> Self {
+        Self {
+            window,
+            fallback,
+            remaining: 0,
+            sum_squares: 0.0,
+            peak: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Levels of all windows completed by `packet`.
+    pub(crate) fn push(&mut self, packet: &AudioPacket) -> Vec<AudioLevel> {
+        let format = packet.format.as_ref().unwrap_or(&self.fallback);
+        let window_samples = self.window_samples(format);
+        let mut levels = Vec::new();
+        for sample in pcm_to_f32(&packet.audio, format) {
+            if self.count == 0 {
+                self.remaining = window_samples;
+            }
+            self.sum_squares += f64::from(sample) * f64::from(sample);
+            self.peak = self.peak.max(sample.abs());
+            self.count += 1;
+            self.remaining -= 1;
+            if self.remaining == 0 {
+                levels.push(AudioLevel {
+                    rms: (self.sum_squares / self.count as f64).sqrt() as f32,
+                    peak: self.peak,
+                    timestamp_us: packet.timestamp,
+                });
+                self.sum_squares = 0.0;
+                self.peak = 0.0;
+                self.count = 0;
+            }
+        }
+        levels
+    }
+
+    fn window_samples(&self, format: &AudioFormat) -> u64 {
+        let rate = pcm_sample_rate(format) as f64;
+        let frames = (rate * self.window.as_secs_f64()).round() as u64;
+        frames.max(1) * pcm_channels(format) as u64
+    }
+}
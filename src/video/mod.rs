@@ -1,6 +1,37 @@
+#[cfg(feature = "video")]
 pub mod stream;
 
+#[cfg(feature = "video")]
 pub use stream::VideoRecoarder;
+#[cfg(feature = "video")]
 pub mod stream_puffer;
 
-pub use stream_puffer::StreamPuffer;
+#[cfg(feature = "video")]
+pub use stream_puffer::{SaveOutcome, StreamPuffer, VideoSaveReport};
+pub mod diff;
+
+pub use diff::frame_diff;
+#[cfg(feature = "video")]
+pub mod session;
+
+#[cfg(feature = "video")]
+pub use session::RecordingSession;
+#[cfg(feature = "video")]
+pub mod replay;
+
+#[cfg(feature = "video")]
+pub use replay::ReplayBuffer;
+pub mod convert;
+
+pub use convert::{crop, image_to_dynamic, packed_rgb888, scale_to_fit, ImageInfo, Rect};
+pub mod level;
+
+pub use level::AudioLevel;
+#[cfg(feature = "video")]
+pub mod mux;
+
+#[cfg(feature = "video")]
+pub use mux::mux_raw_capture;
+#[cfg(feature = "video")]
+mod overlay;
+pub(crate) mod pcm;
@@ -0,0 +1,104 @@
+// Encode a raw capture directory (see `DeviceGrpcClient::record_raw`) into a video file
+
+use crate::error::{Error, Result};
+use crate::proto::audio_format::{Channels, DeliveryMode, SampleFormat};
+use crate::proto::image_format::ImgFormat;
+use crate::proto::{AudioFormat, AudioPacket, Image, ImageFormat};
+use crate::raw_capture::RawManifest;
+use crate::video::{StreamPuffer, VideoSaveReport};
+use std::path::Path;
+
+/// Length of the audio packets the PCM sidecar is cut into.
+const AUDIO_CHUNK_MS: u64 = 20;
+
+/// Encode the raw capture in `dir` to `out_path`: WebM when the extension is `.webm`,
+/// MP4 otherwise.
+pub async fn mux_raw_capture(
+    dir: impl AsRef<Path>,
+    out_path: impl AsRef<Path>,
+) -> Result<VideoSaveReport> {
+    let dir = dir.as_ref();
+    let manifest = RawManifest::load(dir)?;
+    if manifest.frames.is_empty() {
+        return Err(Error::Encode(format!(
+            "raw capture {} has no frames",
+            dir.display()
+        )));
+    }
+
+    let pcm = match &manifest.audio {
+        Some(audio) => Some((audio, tokio::fs::read(dir.join(&audio.file)).await?)),
+        None => None,
+    };
+    let (sample_rate, channels) = pcm
+        .as_ref()
+        .map_or((0, 0), |(audio, _)| (audio.sample_rate, audio.channels));
+    let puffer = StreamPuffer::new(
+        manifest.frames.len(),
+        usize::MAX,
+        manifest.fps,
+        sample_rate,
+        channels,
+        manifest.width,
+        manifest.height,
+    );
+
+    for frame in &manifest.frames {
+        let data = tokio::fs::read(dir.join(&frame.file)).await?;
+        puffer
+            .push_video(Image {
+                format: Some(ImageFormat {
+                    format: ImgFormat::Rgb888 as i32,
+                    width: frame.width,
+                    height: frame.height,
+                    ..Default::default()
+                }),
+                image: data,
+                timestamp_us: frame.timestamp_ms * 1000,
+                ..Default::default()
+            })
+            .await;
+    }
+
+    if let Some((audio, pcm)) = pcm {
+        let format = AudioFormat {
+            sampling_rate: audio.sample_rate as u64,
+            channels: if audio.channels == 1 {
+                Channels::Mono as i32
+            } else {
+                Channels::Stereo as i32
+            },
+            format: if audio.sample_format == "u8" {
+                SampleFormat::AudFmtU8 as i32
+            } else {
+                SampleFormat::AudFmtS16 as i32
+            },
+            mode: DeliveryMode::ModeUnspecified as i32,
+        };
+        let frame_bytes = audio.channels as u64 * if audio.sample_format == "u8" { 1 } else { 2 };
+        let chunk_frames = (audio.sample_rate as u64 * AUDIO_CHUNK_MS / 1000).max(1);
+        for (i, chunk) in pcm
+            .chunks((chunk_frames * frame_bytes) as usize)
+            .enumerate()
+        {
+            let offset_ms = i as u64 * chunk_frames * 1000 / audio.sample_rate as u64;
+            puffer
+                .push_audio(AudioPacket {
+                    format: Some(format.clone()),
+                    timestamp: (audio.timestamp_ms + offset_ms) * 1000,
+                    audio: chunk.to_vec(),
+                })
+                .await;
+        }
+    }
+
+    let out_path = out_path.as_ref();
+    let webm = out_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("webm"));
+    if webm {
+        puffer.save_last_to_webm(out_path).await
+    } else {
+        puffer.save_last_to_mp4(out_path).await
+    }
+}
@@ -0,0 +1,151 @@
+// Burn-in text (frame timestamp, caption) for recordings, drawn with a 5x7 bitmap font
+
+/// Glyph size in font pixels, before scaling.
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// Font pixels between glyphs and lines, and around the text box.
+const GLYPH_SPACING: u32 = 1;
+/// Output rows per font pixel are `height / SCALE_DIVISOR` (at least 1).
+const SCALE_DIVISOR: u32 = 240;
+
+/// What `encode_video` burns into every frame. Both parts off by default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Overlay {
+    pub timestamp: bool,
+    pub caption: Option<String>,
+}
+
+impl Overlay {
+    pub fn is_enabled(&self) -> bool {
+        self.timestamp || self.caption.is_some()
+    }
+
+    /// Draw the caption and `timestamp_ms` into the top-left corner of a packed RGB24
+    /// buffer with rows `stride` bytes apart.
+    pub fn draw(&self, data: &mut [u8], stride: usize, width: u32, height: u32, timestamp_ms: u64) {
+        let mut lines = Vec::with_capacity(2);
+        if let Some(caption) = &self.caption {
+            lines.push(caption.clone());
+        }
+        if self.timestamp {
+            lines.push(format!("{} MS", timestamp_ms));
+        }
+        let canvas = Canvas {
+            data,
+            stride,
+            width,
+            height,
+        };
+        canvas.draw_lines(&lines, (height / SCALE_DIVISOR).max(1));
+    }
+}
+
+struct Canvas<'a> {
+    data: &'a mut [u8],
+    stride: usize,
+    width: u32,
+    height: u32,
+}
+
+impl Canvas<'_> {
+    /// Black box with white text, clipped to the frame.
+    fn draw_lines(mut self, lines: &[String], scale: u32) {
+        let advance = (GLYPH_WIDTH + GLYPH_SPACING) * scale;
+        let line_height = (GLYPH_HEIGHT + GLYPH_SPACING) * scale;
+        let margin = GLYPH_SPACING * scale;
+        let columns = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u32;
+        if columns == 0 {
+            return;
+        }
+        self.fill(
+            0,
+            0,
+            columns * advance + margin,
+            lines.len() as u32 * line_height + margin,
+            [0, 0, 0],
+        );
+        for (row, line) in lines.iter().enumerate() {
+            let y = margin + row as u32 * line_height;
+            for (col, c) in line.chars().enumerate() {
+                let x = margin + col as u32 * advance;
+                for (gy, bits) in glyph(c).iter().enumerate() {
+                    for gx in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - gx)) != 0 {
+                            self.fill(
+                                x + gx * scale,
+                                y + gy as u32 * scale,
+                                scale,
+                                scale,
+                                [255, 255, 255],
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn fill(&mut self, x: u32, y: u32, w: u32, h: u32, rgb: [u8; 3]) {
+        let x_end = (x + w).min(self.width) as usize;
+        for row in y..(y + h).min(self.height) {
+            let start = row as usize * self.stride;
+            for px in x as usize..x_end {
+                self.data[start + px * 3..start + px * 3 + 3].copy_from_slice(&rgb);
+            }
+        }
+    }
+}
+
+/// Rows of a 5x7 glyph, most significant of the low 5 bits on the left.
+/// Letters are upper case only; characters without a glyph render as `?`.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        ' ' => [0x00; 7],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
@@ -0,0 +1,41 @@
+// Helpers for the raw PCM layouts of `AudioFormat`
+
+use crate::proto::audio_format::{Channels, SampleFormat};
+use crate::proto::AudioFormat;
+
+/// Sample rate of an emulator audio format; the proto defaults to 44100 Hz when unset.
+pub(crate) fn pcm_sample_rate(format: &AudioFormat) -> u32 {
+    if format.sampling_rate == 0 {
+        44100
+    } else {
+        format.sampling_rate as u32
+    }
+}
+
+/// Number of interleaved channels of an emulator audio format.
+pub(crate) fn pcm_channels(format: &AudioFormat) -> usize {
+    match Channels::try_from(format.channels) {
+        Ok(Channels::Mono) => 1,
+        _ => 2,
+    }
+}
+
+/// Size in bytes of a single sample of an emulator audio format.
+#[cfg(feature = "video")]
+pub(crate) fn pcm_bytes_per_sample(format: &AudioFormat) -> usize {
+    match SampleFormat::try_from(format.format) {
+        Ok(SampleFormat::AudFmtU8) => 1,
+        _ => 2,
+    }
+}
+
+/// Decode raw PCM bytes (u8 or s16le) into interleaved f32 samples in [-1.0, 1.0].
+pub(crate) fn pcm_to_f32(data: &[u8], format: &AudioFormat) -> Vec<f32> {
+    match SampleFormat::try_from(format.format) {
+        Ok(SampleFormat::AudFmtU8) => data.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+        _ => data
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+    }
+}
@@ -0,0 +1,109 @@
+use crate::error::Error;
+use crate::proto::{AudioPacket, Image};
+use crate::video::{StreamPuffer, VideoSaveReport};
+use crate::Transport;
+use std::path::Path;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tonic::Streaming;
+
+/// Rolling capture started with `DeviceGrpcClient::start_replay_buffer`.
+///
+/// Frames (and audio) stream into a ring buffer on a background task, the oldest falling
+/// out as new ones arrive, so `save_last` can dump something that already happened.
+/// Capturing stops on `stop` or when the buffer is dropped.
+pub struct ReplayBuffer {
+    puffer: StreamPuffer,
+    task: JoinHandle<()>,
+}
+
+impl ReplayBuffer {
+    pub(crate) fn spawn(
+        puffer: StreamPuffer,
+        video: Streaming<Image>,
+        transport: Transport,
+        audio: Option<Streaming<AudioPacket>>,
+    ) -> Self {
+        let task = tokio::spawn(fill(puffer.clone(), video, transport, audio));
+        Self { puffer, task }
+    }
+
+    /// Aborts the capture task, for `DeviceGrpcClient::close`.
+    pub(crate) fn abort_handle(&self) -> tokio::task::AbortHandle {
+        self.task.abort_handle()
+    }
+
+    /// Encode the last `duration` of buffered video (and audio) into the MP4 at `out_path`.
+    /// Capturing goes on meanwhile; a `duration` longer than the buffer saves all of it.
+    pub async fn save_last(
+        &self,
+        duration: Duration,
+        out_path: impl AsRef<Path>,
+    ) -> Result<VideoSaveReport, Error> {
+        let (_, end_ms) = self
+            .puffer
+            .buffered_range_ms()
+            .ok_or_else(|| Error::Encode("no video frames available to save".to_string()))?;
+        let start_ms = end_ms.saturating_sub(duration.as_millis() as u64);
+        self.puffer
+            .save_range_to_mp4(out_path, start_ms, end_ms)
+            .await
+    }
+
+    /// The ring buffer itself, e.g. for `save_last_to_gif` or `metrics`.
+    pub fn puffer(&self) -> &StreamPuffer {
+        &self.puffer
+    }
+
+    /// False once the emulator closed the video stream (or it failed); what was buffered
+    /// until then can still be saved.
+    pub fn is_capturing(&self) -> bool {
+        !self.task.is_finished()
+    }
+
+    /// Stop capturing and wait for the background task to end.
+    pub async fn stop(mut self) {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+    }
+}
+
+impl Drop for ReplayBuffer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Push every frame and audio packet into `puffer` until the video stream ends.
+async fn fill(
+    puffer: StreamPuffer,
+    mut video: Streaming<Image>,
+    transport: Transport,
+    mut audio: Option<Streaming<AudioPacket>>,
+) {
+    let mut audio_open = audio.is_some();
+    loop {
+        tokio::select! {
+            frame = video.message() => match frame {
+                Ok(Some(mut frame)) => match transport.resolve(&mut frame).await {
+                    Ok(()) => puffer.push_video(frame).await,
+                    Err(e) => tracing::warn!("skipping frame: {}", e),
+                },
+                Ok(None) => break, // stream ended
+                Err(e) => {
+                    tracing::warn!("error reading video stream: {}", e);
+                    break;
+                }
+            },
+            packet = async { audio.as_mut().unwrap().message().await }, if audio_open => match packet {
+                Ok(Some(packet)) => puffer.push_audio(packet).await,
+                Ok(None) => audio_open = false,
+                Err(e) => {
+                    // keep buffering video without sound
+                    tracing::warn!("error reading audio stream: {}", e);
+                    audio_open = false;
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::error::Error;
+use crate::proto::{AudioPacket, Image};
+use crate::video::StreamPuffer;
+use crate::Transport;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tonic::Streaming;
+
+/// Handle to a background recording started with `DeviceGrpcClient::start_recording`.
+///
+/// Dropping the handle without calling `stop` ends the capture and still writes
+/// the file, but errors are lost.
+pub struct RecordingSession {
+    paused: Arc<AtomicBool>,
+    stop_tx: Option<oneshot::Sender<()>>,
+    task: JoinHandle<Result<PathBuf, Error>>,
+}
+
+impl RecordingSession {
+    pub(crate) fn spawn(
+        puffer: StreamPuffer,
+        video: Streaming<Image>,
+        transport: Transport,
+        audio: Option<Streaming<AudioPacket>>,
+        out_path: PathBuf,
+    ) -> Self {
+        let paused = Arc::new(AtomicBool::new(false));
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let task = tokio::spawn(capture(
+            puffer,
+            video,
+            transport,
+            audio,
+            paused.clone(),
+            stop_rx,
+            out_path,
+        ));
+        Self {
+            paused,
+            stop_tx: Some(stop_tx),
+            task,
+        }
+    }
+
+    /// Aborts the capture task, for `DeviceGrpcClient::close`.
+    pub(crate) fn abort_handle(&self) -> tokio::task::AbortHandle {
+        self.task.abort_handle()
+    }
+
+    /// Stop buffering frames and audio until `resume`. The paused span is cut from the output.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Continue buffering after `pause`.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// End the capture, encode everything recorded so far and return the MP4 path.
+    pub async fn stop(mut self) -> Result<PathBuf, Error> {
+        if let Some(tx) = self.stop_tx.take() {
+            let _ = tx.send(());
+        }
+        self.task.await?
+    }
+}
+
+/// Shifts stream timestamps (microseconds) back by the time spent paused,
+/// so the first frame after `resume` directly follows the last one before `pause`.
+#[derive(Default)]
+struct PauseClock {
+    // timestamp of the first packet dropped in the current pause
+    paused_since: Option<u64>,
+    offset_us: u64,
+}
+
+impl PauseClock {
+    /// `None` while paused (drop the packet), otherwise the adjusted timestamp.
+    fn adjust(&mut self, paused: bool, ts_us: u64) -> Option<u64> {
+        if paused {
+            self.paused_since.get_or_insert(ts_us);
+            return None;
+        }
+        if let Some(since) = self.paused_since.take() {
+            self.offset_us += ts_us.saturating_sub(since);
+        }
+        Some(ts_us.saturating_sub(self.offset_us))
+    }
+}
+
+async fn capture(
+    puffer: StreamPuffer,
+    mut video: Streaming<Image>,
+    transport: Transport,
+    mut audio: Option<Streaming<AudioPacket>>,
+    paused: Arc<AtomicBool>,
+    mut stop_rx: oneshot::Receiver<()>,
+    out_path: PathBuf,
+) -> Result<PathBuf, Error> {
+    let mut video_clock = PauseClock::default();
+    let mut audio_clock = PauseClock::default();
+    let mut audio_open = audio.is_some();
+
+    loop {
+        tokio::select! {
+            // also fires when the session handle is dropped
+            _ = &mut stop_rx => break,
+            frame = video.message() => match frame {
+                Ok(Some(mut frame)) => {
+                    let is_paused = paused.load(Ordering::SeqCst);
+                    if let Some(ts) = video_clock.adjust(is_paused, frame.timestamp_us) {
+                        frame.timestamp_us = ts;
+                        match transport.resolve(&mut frame).await {
+                            Ok(()) => puffer.push_video(frame).await,
+                            Err(e) => tracing::warn!("skipping frame: {}", e),
+                        }
+                    }
+                }
+                Ok(None) => break, // stream ended
+                Err(e) => {
+                    tracing::warn!("error reading video stream: {}", e);
+                    break;
+                }
+            },
+            packet = async { audio.as_mut().unwrap().message().await }, if audio_open => match packet {
+                Ok(Some(mut packet)) => {
+                    let is_paused = paused.load(Ordering::SeqCst);
+                    if let Some(ts) = audio_clock.adjust(is_paused, packet.timestamp) {
+                        packet.timestamp = ts;
+                        puffer.push_audio(packet).await;
+                    }
+                }
+                Ok(None) => audio_open = false,
+                Err(e) => {
+                    // keep recording video without sound
+                    tracing::warn!("error reading audio stream: {}", e);
+                    audio_open = false;
+                }
+            },
+        }
+    }
+
+    puffer.save_last_to_mp4(&out_path).await?;
+    Ok(out_path)
+}
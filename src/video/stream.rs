@@ -4,6 +4,7 @@
 
 use crate::proto::emulator_controller_client::EmulatorControllerClient;
 use crate::proto::{AudioPacket, DisplayConfigurations, Image};
+use crate::Display;
 use anyhow::Result;
 use ffmpeg_next as ffmpeg;
 use std::path::{Path, PathBuf};
@@ -87,24 +88,24 @@ impl VideoRecoarder {
         self
     }
 
-    pub async fn start(&mut self) {
+    pub async fn start(&mut self) -> Result<()> {
         if self.width == 0 || self.height == 0 {
-            let display_config = self.get_display_configurations().await.unwrap();
-            let display = display_config
-                .displays
-                .get(self.display_index as usize)
-                .unwrap();
+            let display_config = self.get_display_configurations().await?;
+            let display = Display::select(&display_config, self.display_index)?;
             self.width = display.width;
             self.height = display.height;
         }
-        println!(
-            "\x1bStarting recording display {} with resolution {}x{}\x1b[0m",
-            self.display_index, self.width, self.height
+        tracing::info!(
+            "Starting recording display {} with resolution {}x{}",
+            self.display_index,
+            self.width,
+            self.height
         );
+        Ok(())
     }
     pub fn stop(&self) {
         // Implementation to stop recording goes here.
-        println!("\x1b[1m--------------------\nStopping recording...\x1b[0m");
+        tracing::info!("Stopping recording");
     }
 }
 
@@ -574,15 +575,12 @@ impl Recoarder {
         if !self.is_running.load(Ordering::SeqCst) {
             *self.start_time.lock().unwrap() = Some(Instant::now());
             self.is_running.store(true, Ordering::SeqCst);
-            println!(
-                "\x1bStarting recording to {}\x1b[0m",
-                self.output_file.display()
-            );
+            tracing::info!("Starting recording to {}", self.output_file.display());
             while self.is_running.load(Ordering::SeqCst) {}
         }
     }
     pub fn stop(&self) {
         self.is_running.store(false, Ordering::SeqCst);
-        println!("\x1b[1m--------------------\nStopping recording...\x1b[0m");
+        tracing::info!("Stopping recording");
     }
 }
@@ -1,20 +1,48 @@
-use crate::proto::{AudioPacket, Image};
+use crate::error::Error;
+use crate::metrics::PufferMetrics;
+use crate::proto::audio_format::{Channels, SampleFormat};
+use crate::proto::{AudioFormat, AudioPacket, Image};
+use crate::video::overlay::Overlay;
+use crate::video::pcm::{pcm_bytes_per_sample, pcm_channels, pcm_sample_rate, pcm_to_f32};
+use crate::video::{packed_rgb888, ImageInfo};
+use crate::AudioContainer;
+use bytes::Bytes;
 use ffmpeg_next as ffmpeg;
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
 
+/// Upper bound on the ring buffer capacity reserved up front; larger buffers grow on demand.
+const MAX_PREALLOCATED_ENTRIES: usize = 4096;
+
+/// Width in pixels of a single thumbnail in `save_contact_sheet`.
+const CONTACT_SHEET_THUMB_WIDTH: u32 = 240;
+
+/// Largest fraction of buffered frames `save_last_to_*` may drop as malformed
+/// before giving up with an error instead of writing a truncated video.
+const MAX_SKIPPED_FRAME_RATIO: f64 = 0.5;
+
+// Payloads are `Bytes` so snapshotting the buffers only bumps reference counts.
+// Both buffers are stamped on the same clock, see `capture_ms`.
 #[derive(Debug, Clone)]
 struct VideoFrame {
-    timestamp_ms: u32,
-    data: Vec<u8>,
+    timestamp_ms: u64,
+    // resolution reported in `Image::format`, 0 when the emulator left it unset
+    width: u32,
+    height: u32,
+    data: Bytes,
 }
 
 #[derive(Debug, Clone)]
 struct AudioChunk {
-    timestamp_ms: u32,
-    data: Vec<u8>,
+    timestamp_ms: u64,
+    // PCM layout of `data`, as reported by the emulator (or the puffer defaults)
+    format: AudioFormat,
+    data: Bytes,
 }
 
 #[derive(Clone)]
@@ -23,9 +51,25 @@ pub struct StreamPuffer {
 }
 
 struct StreamPufferInner {
-    // ring buffers protected by RwLock for better read performance
-    video_buf: RwLock<VecDeque<VideoFrame>>,
-    audio_buf: RwLock<VecDeque<AudioChunk>>,
+    // ring buffers; every access is a short, non-async critical section (push one entry or
+    // copy out the cheap `Bytes` handles), so a plain mutex is enough
+    video_buf: Mutex<VecDeque<VideoFrame>>,
+    audio_buf: Mutex<VecDeque<AudioChunk>>,
+    // last audio format reported by the emulator in `AudioPacket::format`
+    detected_audio_format: Mutex<Option<AudioFormat>>,
+    // when set, `push_video` drops frames arriving faster than `target_fps`
+    drop_excess_frames: AtomicBool,
+    // capture timestamp (us) the next kept frame is due at, while dropping excess frames
+    next_frame_due_us: Mutex<Option<u64>>,
+    // text burned into every frame when saving
+    overlay: Mutex<Overlay>,
+    // counters reported by `metrics`
+    frames_received: AtomicU64,
+    frames_rate_limited: AtomicU64,
+    frames_evicted: AtomicU64,
+    audio_chunks_received: AtomicU64,
+    audio_chunks_evicted: AtomicU64,
+    audio_bytes: AtomicU64,
     // configuration
     max_frames: usize,
     max_audio_chunks: usize,
@@ -49,8 +93,22 @@ impl StreamPuffer {
         height: u32,
     ) -> Self {
         let inner = StreamPufferInner {
-            video_buf: RwLock::new(VecDeque::with_capacity(max_frames)),
-            audio_buf: RwLock::new(VecDeque::with_capacity(max_audio_chunks)),
+            video_buf: Mutex::new(VecDeque::with_capacity(
+                max_frames.min(MAX_PREALLOCATED_ENTRIES),
+            )),
+            audio_buf: Mutex::new(VecDeque::with_capacity(
+                max_audio_chunks.min(MAX_PREALLOCATED_ENTRIES),
+            )),
+            detected_audio_format: Mutex::new(None),
+            drop_excess_frames: AtomicBool::new(false),
+            next_frame_due_us: Mutex::new(None),
+            overlay: Mutex::new(Overlay::default()),
+            frames_received: AtomicU64::new(0),
+            frames_rate_limited: AtomicU64::new(0),
+            frames_evicted: AtomicU64::new(0),
+            audio_chunks_received: AtomicU64::new(0),
+            audio_chunks_evicted: AtomicU64::new(0),
+            audio_bytes: AtomicU64::new(0),
             max_frames,
             max_audio_chunks,
             target_fps,
@@ -65,54 +123,324 @@ impl StreamPuffer {
     }
 
     /// Push a `Image` received from the emulator into the video buffer.
-    /// The `Image` is expected to be raw RGB888 bytes (as requested via ImageFormat::Rgb888).
+    /// Payloads are stored as packed RGB888 according to the format the emulator reports:
+    /// RGBA8888, PNG and padded rows are converted (see `video::packed_rgb888`). Frames
+    /// without a format are assumed to be packed RGB888 at the configured size.
     /// High-performance: minimizes lock time and uses pre-allocated capacity.
+    /// With `set_drop_excess_frames(true)`, frames arriving faster than `target_fps` are discarded.
+    /// Discarded and evicted frames are counted in `metrics`.
     pub async fn push_video(&self, img: Image) {
-        let frame = VideoFrame {
-            timestamp_ms: (img.timestamp_us / 1000) as u32,
-            data: img.image,
+        self.inner.frames_received.fetch_add(1, Ordering::Relaxed);
+        if self.inner.drop_excess_frames.load(Ordering::Relaxed) && self.inner.target_fps > 0 {
+            let interval_us = 1_000_000 / self.inner.target_fps as u64;
+            let mut next_due = self.inner.next_frame_due_us.lock().unwrap();
+            if !keep_frame_at(&mut next_due, img.timestamp_us, interval_us) {
+                self.inner
+                    .frames_rate_limited
+                    .fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+
+        let (width, height) =
+            ImageInfo::from_image(&img).map_or((0, 0), |info| (info.width, info.height));
+        // converted copy, unless the payload already is packed RGB888 (or unusable, in
+        // which case it is kept and skipped when saving)
+        let packed = match packed_rgb888(&img) {
+            Ok(Cow::Owned(packed)) => Some(packed),
+            Ok(Cow::Borrowed(_)) => None,
+            Err(e) => {
+                if img.format.is_some() {
+                    warn!("cannot repack frame as RGB888: {}", e);
+                }
+                None
+            }
         };
 
-        let mut buf = self.inner.video_buf.write().await;
-        if buf.len() >= self.inner.max_frames {
-            buf.pop_front();
+        let mut buf = self.inner.video_buf.lock().unwrap();
+        let frame = VideoFrame {
+            timestamp_ms: capture_ms(img.timestamp_us, buf.back().map(|f| f.timestamp_ms)),
+            width,
+            height,
+            data: Bytes::from(packed.unwrap_or(img.image)),
+        };
+        if buf.len() >= self.inner.max_frames && buf.pop_front().is_some() {
+            self.inner.frames_evicted.fetch_add(1, Ordering::Relaxed);
         }
         buf.push_back(frame);
     }
 
     /// Push an audio packet into the audio buffer.
-    /// The `AudioPacket` is expected to contain raw PCM s16le samples (as used elsewhere in crate).
+    /// The PCM layout is taken from `AudioPacket::format`; packets without a format are assumed
+    /// to be s16le at the puffer's configured sample rate and channel count.
+    /// Samples are converted to the configured layout when saving.
     /// High-performance: minimizes lock time and uses pre-allocated capacity.
+    /// Evicted packets are counted in `metrics`.
     pub async fn push_audio(&self, pkt: AudioPacket) {
-        let chunk = AudioChunk {
-            timestamp_ms: (pkt.timestamp / 1000) as u32,
-            data: pkt.audio,
+        self.inner
+            .audio_chunks_received
+            .fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .audio_bytes
+            .fetch_add(pkt.audio.len() as u64, Ordering::Relaxed);
+        let format = match pkt.format {
+            Some(fmt) => {
+                let mut detected = self.inner.detected_audio_format.lock().unwrap();
+                if detected.as_ref() != Some(&fmt) {
+                    *detected = Some(fmt.clone());
+                }
+                fmt
+            }
+            None => AudioFormat {
+                sampling_rate: self.inner.audio_sample_rate as u64,
+                channels: if self.inner.audio_channels == 1 {
+                    Channels::Mono as i32
+                } else {
+                    Channels::Stereo as i32
+                },
+                format: SampleFormat::AudFmtS16 as i32,
+                mode: 0,
+            },
         };
 
-        let mut buf = self.inner.audio_buf.write().await;
-        if buf.len() >= self.inner.max_audio_chunks {
-            buf.pop_front();
+        let mut buf = self.inner.audio_buf.lock().unwrap();
+        let chunk = AudioChunk {
+            timestamp_ms: capture_ms(pkt.timestamp, buf.back().map(|c| c.timestamp_ms)),
+            format,
+            data: Bytes::from(pkt.audio),
+        };
+        if buf.len() >= self.inner.max_audio_chunks && buf.pop_front().is_some() {
+            self.inner
+                .audio_chunks_evicted
+                .fetch_add(1, Ordering::Relaxed);
         }
         buf.push_back(chunk);
     }
 
-    /// Save the buffered video/audio into an MP4 file at `out_path`.
+    /// Counters and buffer fill since the puffer was created, cheap enough to poll.
+    pub fn metrics(&self) -> PufferMetrics {
+        let frames_buffered = self.inner.video_buf.lock().unwrap().len();
+        let audio_chunks_buffered = self.inner.audio_buf.lock().unwrap().len();
+        PufferMetrics {
+            frames_received: self.inner.frames_received.load(Ordering::Relaxed),
+            frames_rate_limited: self.inner.frames_rate_limited.load(Ordering::Relaxed),
+            frames_evicted: self.inner.frames_evicted.load(Ordering::Relaxed),
+            audio_chunks_received: self.inner.audio_chunks_received.load(Ordering::Relaxed),
+            audio_chunks_evicted: self.inner.audio_chunks_evicted.load(Ordering::Relaxed),
+            audio_bytes: self.inner.audio_bytes.load(Ordering::Relaxed),
+            frames_buffered,
+            frames_capacity: self.inner.max_frames,
+            audio_chunks_buffered,
+            audio_chunks_capacity: self.inner.max_audio_chunks,
+        }
+    }
+
+    /// Drop incoming video frames so the buffer holds roughly `target_fps` frames per second,
+    /// based on the capture timestamps. Off by default, which keeps every frame (lossless capture).
+    /// Only affects frames pushed after the call.
+    pub fn set_drop_excess_frames(&self, enabled: bool) {
+        self.inner
+            .drop_excess_frames
+            .store(enabled, Ordering::Relaxed);
+        *self.inner.next_frame_due_us.lock().unwrap() = None;
+    }
+
+    /// Whether frames arriving faster than `target_fps` are being dropped.
+    pub fn drops_excess_frames(&self) -> bool {
+        self.inner.drop_excess_frames.load(Ordering::Relaxed)
+    }
+
+    /// Burn the frame timestamp (in ms) and/or `caption` into the top-left corner of every
+    /// saved frame, e.g. to check frame timing and A/V sync. Off by default; drawing costs
+    /// a pass over each frame. Applies to the next save.
+    pub fn set_overlay(&self, timestamp: bool, caption: Option<String>) {
+        *self.inner.overlay.lock().unwrap() = Overlay { timestamp, caption };
+    }
+
+    /// The audio format most recently reported by the emulator, if any packet carried one.
+    pub async fn detected_audio_format(&self) -> Option<AudioFormat> {
+        self.inner.detected_audio_format.lock().unwrap().clone()
+    }
+
+    /// Stretches of buffered audio lasting at least `min_duration` in which no sample rises
+    /// above `threshold_dbfs`, as `(start_ms, end_ms)` in Unix epoch ms (the capture clock).
+    ///
+    /// The threshold is in dBFS: 0.0 is full scale, -40.0 is 1% of full scale and -60.0 a
+    /// typical noise floor. Gaps between packets do not break a silent stretch.
+    pub async fn detect_silence(
+        &self,
+        threshold_dbfs: f32,
+        min_duration: std::time::Duration,
+    ) -> Vec<(u64, u64)> {
+        let audio: Vec<AudioChunk> = self
+            .inner
+            .audio_buf
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        let threshold = 10f32.powf(threshold_dbfs / 20.0);
+        let min_ms = min_duration.as_millis() as u64;
+
+        let mut ranges = Vec::new();
+        // start and latest end of the silent stretch in progress
+        let mut silent: Option<(u64, u64)> = None;
+        let mut close = |silent: &mut Option<(u64, u64)>| {
+            if let Some((start, end)) = silent.take() {
+                if end - start >= min_ms {
+                    ranges.push((start, end));
+                }
+            }
+        };
+        for chunk in &audio {
+            let channels = pcm_channels(&chunk.format);
+            let rate = pcm_sample_rate(&chunk.format) as f64;
+            let samples = pcm_to_f32(&chunk.data, &chunk.format);
+            for (i, frame) in samples.chunks_exact(channels).enumerate() {
+                let at = chunk.timestamp_ms + (i as f64 * 1000.0 / rate) as u64;
+                if frame.iter().all(|s| s.abs() <= threshold) {
+                    let start = silent.map_or(at, |(start, _)| start);
+                    // a frame lasts until the next one starts
+                    let end = chunk.timestamp_ms + ((i + 1) as f64 * 1000.0 / rate) as u64;
+                    silent = Some((start, end.max(start)));
+                } else {
+                    close(&mut silent);
+                }
+            }
+        }
+        close(&mut silent);
+        ranges
+    }
+
+    /// Timestamps (Unix epoch ms) of the oldest and newest buffered frames, `None` when empty.
+    pub fn buffered_range_ms(&self) -> Option<(u64, u64)> {
+        let buf = self.inner.video_buf.lock().unwrap();
+        Some((buf.front()?.timestamp_ms, buf.back()?.timestamp_ms))
+    }
+
+    /// Save the buffered video/audio into an MP4 file (MPEG-4 video, AAC audio) at `out_path`.
     /// Uses ffmpeg-next library for direct encoding without external processes.
     /// Performance optimized: no temp files, direct frame encoding, proper timestamp handling.
-    pub async fn save_last_to_mp4(&self, out_path: impl AsRef<Path>) -> Result<(), String> {
+    pub async fn save_last_to_mp4(
+        &self,
+        out_path: impl AsRef<Path>,
+    ) -> Result<VideoSaveReport, Error> {
+        self.save_to(
+            out_path,
+            VideoContainer::Mp4,
+            None,
+            EncodeControl::default(),
+        )
+        .await
+    }
+
+    /// `save_last_to_mp4` for long buffers: `progress` gets `(frames_encoded, total)` after
+    /// every frame (on the encoding thread), and setting `cancel` stops the encode at the
+    /// next frame. A cancelled save deletes the partial file and returns
+    /// `SaveOutcome::Cancelled`.
+    pub async fn save_last_to_mp4_cancellable(
+        &self,
+        out_path: impl AsRef<Path>,
+        cancel: Arc<AtomicBool>,
+        progress: impl FnMut(usize, usize) + Send + 'static,
+    ) -> Result<SaveOutcome, Error> {
+        let control = EncodeControl {
+            cancel: Some(cancel),
+            progress: Some(Box::new(progress)),
+        };
+        match self
+            .save_to(out_path, VideoContainer::Mp4, None, control)
+            .await
+        {
+            Ok(report) => Ok(SaveOutcome::Completed(report)),
+            Err(Error::Cancelled) => Ok(SaveOutcome::Cancelled),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Like `save_last_to_mp4`, but only the buffered frames and audio whose timestamps fall
+    /// in `start_ms..=end_ms`, in Unix epoch ms like the `Image`/`AudioPacket` timestamps.
+    /// Fails when the range is empty or lies entirely outside the buffered video.
+    pub async fn save_range_to_mp4(
+        &self,
+        out_path: impl AsRef<Path>,
+        start_ms: u64,
+        end_ms: u64,
+    ) -> Result<VideoSaveReport, Error> {
+        self.save_to(
+            out_path,
+            VideoContainer::Mp4,
+            Some((start_ms, end_ms)),
+            EncodeControl::default(),
+        )
+        .await
+    }
+
+    /// Save the buffered video/audio into a WebM file (VP9 video, Opus audio) at `out_path`,
+    /// suitable for embedding in web pages. Otherwise behaves like `save_last_to_mp4`.
+    pub async fn save_last_to_webm(
+        &self,
+        out_path: impl AsRef<Path>,
+    ) -> Result<VideoSaveReport, Error> {
+        self.save_to(
+            out_path,
+            VideoContainer::Webm,
+            None,
+            EncodeControl::default(),
+        )
+        .await
+    }
+
+    /// Encode the buffers, restricted to `range` (inclusive, in ms) when given.
+    async fn save_to(
+        &self,
+        out_path: impl AsRef<Path>,
+        container: VideoContainer,
+        range: Option<(u64, u64)>,
+        mut control: EncodeControl,
+    ) -> Result<VideoSaveReport, Error> {
         // Clone buffers to avoid holding locks during encoding
-        let video_frames = {
-            let guard = self.inner.video_buf.read().await;
+        let mut video_frames = {
+            let guard = self.inner.video_buf.lock().unwrap();
             guard.iter().cloned().collect::<Vec<_>>()
         };
 
-        let audio_chunks = {
-            let guard = self.inner.audio_buf.read().await;
+        let mut audio_chunks = {
+            let guard = self.inner.audio_buf.lock().unwrap();
             guard.iter().cloned().collect::<Vec<_>>()
         };
 
         if video_frames.is_empty() {
-            return Err("no video frames available to save".to_string());
+            return Err(Error::Encode(
+                "no video frames available to save".to_string(),
+            ));
+        }
+
+        if let Some((start_ms, end_ms)) = range {
+            let buffered_start = video_frames.first().unwrap().timestamp_ms;
+            let buffered_end = video_frames.last().unwrap().timestamp_ms;
+            if end_ms <= start_ms {
+                return Err(Error::Encode(format!(
+                    "empty range {} - {} ms",
+                    start_ms, end_ms
+                )));
+            }
+            if start_ms > buffered_end || end_ms < buffered_start {
+                return Err(Error::Encode(format!(
+                    "range {} - {} ms is outside the buffered video ({} - {} ms)",
+                    start_ms, end_ms, buffered_start, buffered_end
+                )));
+            }
+            let in_range = |ts: u64| ts >= start_ms && ts <= end_ms;
+            video_frames.retain(|f| in_range(f.timestamp_ms));
+            audio_chunks.retain(|c| in_range(c.timestamp_ms));
+            if video_frames.is_empty() {
+                return Err(Error::Encode(format!(
+                    "no video frames between {} and {} ms",
+                    start_ms, end_ms
+                )));
+            }
         }
 
         // Calculate overlap range
@@ -126,14 +454,14 @@ impl StreamPuffer {
             let audio_start = audio_chunks.first().unwrap().timestamp_ms;
             let audio_end = audio_chunks.last().unwrap().timestamp_ms;
 
-            println!(
+            debug!(
                 "Video range: {} - {} ms (duration: {} ms, {} frames)",
                 video_start,
                 video_end,
                 video_end - video_start,
                 video_frames.len()
             );
-            println!(
+            debug!(
                 "Audio range: {} - {} ms (duration: {} ms, {} chunks)",
                 audio_start,
                 audio_end,
@@ -144,41 +472,28 @@ impl StreamPuffer {
             let overlap_start = video_start.max(audio_start);
             let overlap_end = video_end.min(audio_end);
 
-            println!(
+            debug!(
                 "Overlap range: {} - {} ms (duration: {} ms)",
                 overlap_start,
                 overlap_end,
                 overlap_end.saturating_sub(overlap_start)
             );
 
-            // If no overlap, save video-only
+            // If no overlap, save video-only. Otherwise both streams are kept whole: they
+            // are placed on a common timeline when encoding, so a stream starting late
+            // keeps its offset instead of being trimmed to the other one.
             if overlap_end <= overlap_start {
-                println!("No timestamp overlap found, saving video-only");
+                warn!("No timestamp overlap found, saving video-only");
                 (false, video_frames, Vec::new())
             } else {
-                // Filter to overlapping frames
-                let fv: Vec<_> = video_frames
-                    .into_iter()
-                    .filter(|f| f.timestamp_ms >= overlap_start && f.timestamp_ms <= overlap_end)
-                    .collect();
-
-                let fa: Vec<_> = audio_chunks
-                    .into_iter()
-                    .filter(|c| c.timestamp_ms >= overlap_start && c.timestamp_ms <= overlap_end)
-                    .collect();
-
-                println!(
-                    "Filtered to {} video frames and {} audio chunks",
-                    fv.len(),
-                    fa.len()
-                );
-
-                (true, fv, fa)
+                (true, video_frames, audio_chunks)
             }
         };
 
         if filtered_video.is_empty() {
-            return Err("no video frames available after filtering".to_string());
+            return Err(Error::Encode(
+                "no video frames available after filtering".to_string(),
+            ));
         }
 
         // Run encoding in blocking task to avoid blocking async runtime
@@ -188,9 +503,10 @@ impl StreamPuffer {
         let fps = self.inner.target_fps;
         let sample_rate = self.inner.audio_sample_rate;
         let channels = self.inner.audio_channels;
+        let overlay = self.inner.overlay.lock().unwrap().clone();
 
-        tokio::task::spawn_blocking(move || {
-            Self::encode_to_mp4(
+        let report = tokio::task::spawn_blocking(move || {
+            Self::encode_video(
                 &out_path,
                 filtered_video,
                 filtered_audio,
@@ -200,17 +516,19 @@ impl StreamPuffer {
                 sample_rate,
                 channels,
                 have_audio,
+                container,
+                &overlay,
+                &mut control,
             )
         })
-        .await
-        .map_err(|e| format!("Task join error: {}", e))??;
+        .await??;
 
-        Ok(())
+        Ok(report)
     }
 
-    /// Internal method to encode video/audio to MP4 using ffmpeg-next.
+    /// Internal method to encode video/audio to `container` using ffmpeg-next.
     /// Must be called from a blocking context (not async).
-    fn encode_to_mp4(
+    fn encode_video(
         out_path: &Path,
         video_frames: Vec<VideoFrame>,
         audio_chunks: Vec<AudioChunk>,
@@ -220,94 +538,91 @@ impl StreamPuffer {
         sample_rate: u32,
         channels: u32,
         have_audio: bool,
-    ) -> Result<(), String> {
+        container: VideoContainer,
+        overlay: &Overlay,
+        control: &mut EncodeControl,
+    ) -> Result<VideoSaveReport, Error> {
         use ffmpeg::codec;
         use ffmpeg::format;
         use ffmpeg::software::scaling;
         use ffmpeg::{frame, Rational};
 
+        // Trust the stream over the configuration, e.g. the display rotated since setup.
+        // Frames at another resolution (rotation, folding) are letterboxed to this size.
+        let (config_width, config_height) = (width, height);
+        let (width, height) = stream_dimensions(&video_frames, config_width, config_height);
+        let frames_skipped = video_frames
+            .iter()
+            .filter(|f| source_dimensions(f, config_width, config_height).is_none())
+            .count();
+        if frames_skipped as f64 > video_frames.len() as f64 * MAX_SKIPPED_FRAME_RATIO {
+            return Err(Error::Encode(format!(
+                "{} of {} frames are not complete RGB888 images of their reported size",
+                frames_skipped,
+                video_frames.len()
+            )));
+        }
+
         // Initialize ffmpeg once
-        ffmpeg::init().map_err(|e| format!("FFmpeg init error: {}", e))?;
+        ffmpeg::init().map_err(|e| Error::ffmpeg("FFmpeg init error", e))?;
 
-        // Create output context
-        let path_str = out_path.to_str().ok_or("Invalid output path")?;
-        let mut octx =
-            format::output(&path_str).map_err(|e| format!("Cannot create output: {}", e))?;
+        // Create output context with an explicit muxer so the extension doesn't matter
+        let path_str = out_path
+            .to_str()
+            .ok_or_else(|| Error::Encode("Invalid output path".to_string()))?;
+        let mut octx = format::output_as(&path_str, container.muxer())
+            .map_err(|e| Error::ffmpeg("Cannot create output", e))?;
 
         // --- Video Stream Setup ---
         let global_header = octx.format().flags().contains(format::Flags::GLOBAL_HEADER);
 
-        // Use MPEG4 codec (simpler than H264, no preset requirements)
-        let codec = codec::encoder::find(codec::Id::MPEG4).ok_or("MPEG4 encoder not found")?;
+        let video_codec_id = container.video_codec();
+        let codec = codec::encoder::find(video_codec_id)
+            .ok_or_else(|| Error::Encode(format!("{:?} encoder not found", video_codec_id)))?;
 
         let mut ost = octx
             .add_stream(codec)
-            .map_err(|e| format!("Cannot add video stream: {}", e))?;
+            .map_err(|e| Error::ffmpeg("Cannot add video stream", e))?;
         let video_stream_index = ost.index();
 
         // Get encoder from codec context
         let mut video_encoder = codec::Context::new()
             .encoder()
             .video()
-            .map_err(|e| format!("Cannot create video encoder: {}", e))?;
+            .map_err(|e| Error::ffmpeg("Cannot create video encoder", e))?;
 
         video_encoder.set_width(width);
         video_encoder.set_height(height);
         video_encoder.set_format(ffmpeg::format::Pixel::YUV420P);
-        video_encoder.set_time_base(Rational::new(1, fps as i32));
+        // Frame PTS are in milliseconds; both encoders must agree (libvpx rate control
+        // relies on it)
+        video_encoder.set_time_base(Rational::new(1, 1_000));
         video_encoder.set_frame_rate(Some(Rational::new(fps as i32, 1)));
 
         if global_header {
             video_encoder.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
         }
 
-        // Open MPEG4 encoder (no preset issues)
         let mut video_encoder = video_encoder
             .open_as(codec)
-            .map_err(|e| format!("Cannot open video encoder: {}", e))?;
+            .map_err(|e| Error::ffmpeg("Cannot open video encoder", e))?;
         ost.set_parameters(&video_encoder);
 
         // --- Audio Stream Setup (if needed) ---
-        let mut audio_encoder_opt = None;
-        let mut audio_stream_idx = 0;
-
-        if have_audio && !audio_chunks.is_empty() {
-            let audio_codec =
-                codec::encoder::find(codec::Id::AAC).ok_or("AAC encoder not found")?;
-
-            let mut ast = octx
-                .add_stream(audio_codec)
-                .map_err(|e| format!("Cannot add audio stream: {}", e))?;
-            audio_stream_idx = ast.index();
-
-            let mut audio_enc = codec::Context::new()
-                .encoder()
-                .audio()
-                .map_err(|e| format!("Cannot create audio encoder: {}", e))?;
-
-            audio_enc.set_rate(sample_rate as i32);
-            audio_enc.set_channel_layout(ffmpeg::ChannelLayout::default(channels as i32));
-            // AAC encoder requires float planar format
-            audio_enc.set_format(ffmpeg::format::Sample::F32(
-                ffmpeg::format::sample::Type::Planar,
-            ));
-            // Use millisecond time_base for audio to match video
-            audio_enc.set_time_base(Rational::new(1, 1_000));
-
-            if global_header {
-                audio_enc.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
-            }
-
-            let audio_enc = audio_enc
-                .open_as(audio_codec)
-                .map_err(|e| format!("Cannot open audio encoder: {}", e))?;
-            ast.set_parameters(&audio_enc);
-            audio_encoder_opt = Some(audio_enc);
-        }
+        let mut audio_output = if have_audio && !audio_chunks.is_empty() {
+            Some(AudioOutput::add_stream(
+                &mut octx,
+                container.audio_codec(),
+                sample_rate,
+                channels,
+            )?)
+        } else {
+            None
+        };
 
         // Write header
         octx.write_header()
-            .map_err(|e| format!("Cannot write header: {}", e))?;
+            .map_err(|e| Error::ffmpeg("Cannot write header", e))?;
 
         // --- Create RGB to YUV scaler ---
         let mut scaler = scaling::Context::get(
@@ -319,50 +634,83 @@ impl StreamPuffer {
             height,
             scaling::Flags::BILINEAR,
         )
-        .map_err(|e| format!("Cannot create scaler: {}", e))?;
+        .map_err(|e| Error::ffmpeg("Cannot create scaler", e))?;
 
-        let first_timestamp = video_frames.first().unwrap().timestamp_ms;
+        // RGB scalers for frames whose resolution differs from the output, by source size
+        let mut letterbox_scalers = HashMap::new();
+
+        // Both streams' PTS count from the earliest buffered timestamp of either, so the
+        // gap between their starts survives in the output
+        let video_start_ms = video_frames.first().unwrap().timestamp_ms;
+        let recording_start_ms = match audio_chunks.first() {
+            Some(chunk) if audio_output.is_some() => video_start_ms.min(chunk.timestamp_ms),
+            _ => video_start_ms,
+        };
         // --- Encode Video Frames ---
         for (idx, vframe) in video_frames.iter().enumerate() {
-            // Create RGB frame
-            let mut rgb_frame = frame::Video::new(ffmpeg::format::Pixel::RGB24, width, height);
-
+            if control.is_cancelled() {
+                drop(octx);
+                let _ = std::fs::remove_file(out_path);
+                return Err(Error::Cancelled);
+            }
+            control.report(idx, video_frames.len());
             // Copy RGB data (assuming RGB888 format: width * height * 3 bytes)
-            let expected_size = (width * height * 3) as usize;
-            if vframe.data.len() != expected_size {
-                eprintln!(
-                    "Warning: frame {} has size {} bytes, expected {}",
+            let Some((src_width, src_height)) =
+                source_dimensions(vframe, config_width, config_height)
+            else {
+                warn!(
+                    "skipping frame {} ({}x{}, {} bytes), not a complete RGB888 image",
                     idx,
-                    vframe.data.len(),
-                    expected_size
+                    vframe.width,
+                    vframe.height,
+                    vframe.data.len()
                 );
                 continue;
-            }
+            };
+
+            // Create RGB frame at the source resolution
+            let mut rgb_frame =
+                frame::Video::new(ffmpeg::format::Pixel::RGB24, src_width, src_height);
 
             // Copy RGB data line by line respecting stride
+            let row_len = src_width as usize * 3;
             let stride = rgb_frame.stride(0);
             let data = rgb_frame.data_mut(0);
-            for y in 0..height as usize {
-                let src_offset = y * width as usize * 3;
+            for y in 0..src_height as usize {
+                let src_offset = y * row_len;
                 let dst_offset = y * stride;
-                data[dst_offset..dst_offset + width as usize * 3]
-                    .copy_from_slice(&vframe.data[src_offset..src_offset + width as usize * 3]);
+                data[dst_offset..dst_offset + row_len]
+                    .copy_from_slice(&vframe.data[src_offset..src_offset + row_len]);
+            }
+
+            if (src_width, src_height) != (width, height) {
+                rgb_frame = letterbox_rgb(&mut letterbox_scalers, &rgb_frame, width, height)?;
+            }
+            if overlay.is_enabled() {
+                let stride = rgb_frame.stride(0);
+                overlay.draw(
+                    rgb_frame.data_mut(0),
+                    stride,
+                    width,
+                    height,
+                    vframe.timestamp_ms,
+                );
             }
 
             // Convert RGB to YUV420P
             let mut yuv_frame = frame::Video::new(ffmpeg::format::Pixel::YUV420P, width, height);
             scaler
                 .run(&rgb_frame, &mut yuv_frame)
-                .map_err(|e| format!("Scaling error: {}", e))?;
+                .map_err(|e| Error::ffmpeg("Scaling error", e))?;
 
             // Set PTS from actual frame timestamp (already in milliseconds)
             // Matches our time_base of 1/1000
-            yuv_frame.set_pts(Some((vframe.timestamp_ms - first_timestamp) as i64));
+            yuv_frame.set_pts(Some((vframe.timestamp_ms - recording_start_ms) as i64));
 
             // Encode
             video_encoder
                 .send_frame(&yuv_frame)
-                .map_err(|e| format!("Send frame error: {}", e))?;
+                .map_err(|e| Error::ffmpeg("Send frame error", e))?;
 
             // Receive packets
             let mut encoded = ffmpeg::Packet::empty();
@@ -374,14 +722,16 @@ impl StreamPuffer {
                 );
                 encoded
                     .write_interleaved(&mut octx)
-                    .map_err(|e| format!("Write packet error: {}", e))?;
+                    .map_err(|e| Error::ffmpeg("Write packet error", e))?;
             }
         }
 
+        control.report(video_frames.len(), video_frames.len());
+
         // Flush video encoder
         video_encoder
             .send_eof()
-            .map_err(|e| format!("Send EOF error: {}", e))?;
+            .map_err(|e| Error::ffmpeg("Send EOF error", e))?;
         let mut encoded = ffmpeg::Packet::empty();
         while video_encoder.receive_packet(&mut encoded).is_ok() {
             encoded.set_stream(video_stream_index);
@@ -391,128 +741,847 @@ impl StreamPuffer {
             );
             encoded
                 .write_interleaved(&mut octx)
-                .map_err(|e| format!("Write packet error: {}", e))?;
+                .map_err(|e| Error::ffmpeg("Write packet error", e))?;
         }
 
         // --- Encode Audio (if available) ---
-        if let Some(mut audio_encoder) = audio_encoder_opt {
-            // AAC requires float planar (fltp) format with exactly 1024 samples per frame
-            let frame_size = audio_encoder.frame_size() as usize;
-
-            // Buffer to accumulate samples for AAC frames (interleaved f32)
-            let mut sample_buffer: Vec<f32> = Vec::new();
-            let mut total_samples_processed = 0usize;
-
+        if let Some(audio_output) = audio_output.as_mut() {
             let total_audio_bytes: usize = audio_chunks.iter().map(|c| c.data.len()).sum();
-            let total_audio_samples = total_audio_bytes / 2; // i16 is 2 bytes
-            println!(
-                "Processing {} audio chunks ({} bytes, {} samples) for AAC encoding",
+            let total_audio_samples: usize = audio_chunks
+                .iter()
+                .map(|c| c.data.len() / pcm_bytes_per_sample(&c.format))
+                .sum();
+            debug!(
+                "Processing {} audio chunks ({} bytes, {} samples) for {:?} encoding",
                 audio_chunks.len(),
                 total_audio_bytes,
-                total_audio_samples
+                total_audio_samples,
+                container.audio_codec()
             );
 
-            for (idx, achunk) in audio_chunks.iter().enumerate() {
-                // Convert s16le bytes to i16 samples, then normalize to f32 [-1.0, 1.0]
-                let samples_i16: Vec<i16> = achunk
-                    .data
-                    .chunks_exact(2)
-                    .map(|b| i16::from_le_bytes([b[0], b[1]]))
-                    .collect();
+            audio_output.encode_chunks(&mut octx, &audio_chunks, recording_start_ms)?;
+        }
 
-                if samples_i16.is_empty() {
-                    continue;
-                }
+        // Write trailer
+        octx.write_trailer()
+            .map_err(|e| Error::ffmpeg("Cannot write trailer", e))?;
 
-                if idx < 3 {
-                    println!(
-                        "  Chunk {}: {} bytes -> {} samples",
-                        idx,
-                        achunk.data.len(),
-                        samples_i16.len()
-                    );
-                }
+        Ok(VideoSaveReport {
+            frames_written: video_frames.len() - frames_skipped,
+            frames_skipped,
+            width,
+            height,
+        })
+    }
 
-                // Convert i16 to f32 and add to buffer (interleaved)
-                // i16 range is -32768 to 32767, normalize to -1.0 to 1.0
-                for sample in samples_i16 {
-                    sample_buffer.push(sample as f32 / 32768.0);
-                }
+    /// Save the buffered audio alone into `out_path`, encoded according to `container`.
+    /// Audio is converted to the puffer's configured sample rate and channel count and then
+    /// to whatever sample format the chosen encoder expects.
+    pub async fn save_audio(
+        &self,
+        out_path: impl AsRef<Path>,
+        container: AudioContainer,
+    ) -> Result<(), Error> {
+        // Clone buffer to avoid holding the lock during encoding
+        let audio_chunks = {
+            let guard = self.inner.audio_buf.lock().unwrap();
+            guard.iter().cloned().collect::<Vec<_>>()
+        };
 
-                // Process complete AAC frames
-                while sample_buffer.len() >= frame_size * channels as usize {
-                    let mut audio_frame = frame::Audio::new(
-                        ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Planar),
-                        frame_size,
-                        ffmpeg::ChannelLayout::STEREO,
-                    );
-
-                    // Split interleaved buffer into planar channels
-                    // Process left channel
-                    {
-                        let left_out = audio_frame.plane_mut::<f32>(0);
-                        for i in 0..frame_size {
-                            left_out[i] = sample_buffer[i * 2];
-                        }
-                    }
-                    // Process right channel
-                    {
-                        let right_out = audio_frame.plane_mut::<f32>(1);
-                        for i in 0..frame_size {
-                            right_out[i] = sample_buffer[i * 2 + 1];
-                        }
-                    }
-
-                    // Remove processed samples
-                    sample_buffer.drain(0..frame_size * channels as usize);
-
-                    // Calculate PTS based on sample position in stream
-                    // Each sample represents 1/sample_rate seconds
-                    let pts_ms = (total_samples_processed as i64 * 1000) / sample_rate as i64;
-                    audio_frame.set_pts(Some(pts_ms));
-                    total_samples_processed += frame_size;
-
-                    // Encode audio frame
-                    audio_encoder
-                        .send_frame(&audio_frame)
-                        .map_err(|e| format!("Send audio frame error: {}", e))?;
-
-                    // Receive packets
-                    let mut encoded = ffmpeg::Packet::empty();
-                    while audio_encoder.receive_packet(&mut encoded).is_ok() {
-                        encoded.set_stream(audio_stream_idx);
-                        encoded.rescale_ts(
-                            Rational::new(1, 1_000),
-                            octx.stream(audio_stream_idx).unwrap().time_base(),
-                        );
-                        encoded
-                            .write_interleaved(&mut octx)
-                            .map_err(|e| format!("Write audio packet error: {}", e))?;
-                    }
-                }
-            }
+        if audio_chunks.is_empty() {
+            return Err(Error::Encode(
+                "no audio chunks available to save".to_string(),
+            ));
+        }
 
-            // Flush audio encoder
-            audio_encoder
-                .send_eof()
-                .map_err(|e| format!("Send audio EOF error: {}", e))?;
-            let mut encoded = ffmpeg::Packet::empty();
-            while audio_encoder.receive_packet(&mut encoded).is_ok() {
-                encoded.set_stream(audio_stream_idx);
-                encoded.rescale_ts(
-                    Rational::new(1, 1_000),
-                    octx.stream(audio_stream_idx).unwrap().time_base(),
+        let out_path = out_path.as_ref().to_path_buf();
+        let sample_rate = self.inner.audio_sample_rate;
+        let channels = self.inner.audio_channels.max(1);
+
+        tokio::task::spawn_blocking(move || {
+            Self::encode_audio(&out_path, audio_chunks, sample_rate, channels, container)
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    /// Internal method to encode buffered audio to a standalone file using ffmpeg-next.
+    /// Must be called from a blocking context (not async).
+    fn encode_audio(
+        out_path: &Path,
+        audio_chunks: Vec<AudioChunk>,
+        sample_rate: u32,
+        channels: u32,
+        container: AudioContainer,
+    ) -> Result<(), Error> {
+        use ffmpeg::codec;
+        use ffmpeg::format;
+
+        ffmpeg::init().map_err(|e| Error::ffmpeg("FFmpeg init error", e))?;
+
+        let (codec_id, muxer) = match container {
+            AudioContainer::Mp3 => (codec::Id::MP3, "mp3"),
+            AudioContainer::Aac => (codec::Id::AAC, "adts"),
+            AudioContainer::M4a => (codec::Id::AAC, "ipod"),
+            AudioContainer::Wav => (codec::Id::PCM_S16LE, "wav"),
+            AudioContainer::Flac => (codec::Id::FLAC, "flac"),
+            AudioContainer::Opus => (codec::Id::OPUS, "ogg"),
+        };
+
+        // Create output context with an explicit muxer so the extension doesn't matter
+        let path_str = out_path
+            .to_str()
+            .ok_or_else(|| Error::Encode("Invalid output path".to_string()))?;
+        let mut octx = format::output_as(&path_str, muxer)
+            .map_err(|e| Error::ffmpeg("Cannot create output", e))?;
+
+        let mut audio_output = AudioOutput::add_stream(&mut octx, codec_id, sample_rate, channels)?;
+
+        octx.write_header()
+            .map_err(|e| Error::ffmpeg("Cannot write header", e))?;
+
+        // a standalone audio file starts with its first chunk
+        let start_ms = audio_chunks.first().map_or(0, |chunk| chunk.timestamp_ms);
+        audio_output.encode_chunks(&mut octx, &audio_chunks, start_ms)?;
+
+        octx.write_trailer()
+            .map_err(|e| Error::ffmpeg("Cannot write trailer", e))?;
+
+        Ok(())
+    }
+
+    /// Save the buffered video frames into an animated GIF at `out_path`.
+    /// Frames are downscaled to at most `max_width` pixels wide (aspect ratio preserved)
+    /// and thinned out so that no more than `fps` frames per second are written.
+    /// Frame delays follow the captured `timestamp_ms` deltas. Audio is ignored.
+    /// As with `save_last_to_mp4`, frames at another resolution than the first one
+    /// (rotation, folding) are letterboxed into it.
+    pub async fn save_last_to_gif(
+        &self,
+        out_path: impl AsRef<Path>,
+        max_width: u32,
+        fps: u32,
+    ) -> Result<(), Error> {
+        // Clone buffer to avoid holding the lock during encoding
+        let video_frames = {
+            let guard = self.inner.video_buf.lock().unwrap();
+            guard.iter().cloned().collect::<Vec<_>>()
+        };
+
+        if video_frames.is_empty() {
+            return Err(Error::Encode(
+                "no video frames available to save".to_string(),
+            ));
+        }
+
+        let out_path = out_path.as_ref().to_path_buf();
+        let width = self.inner.width;
+        let height = self.inner.height;
+
+        tokio::task::spawn_blocking(move || {
+            Self::encode_to_gif(&out_path, video_frames, width, height, max_width, fps)
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    /// Internal method to encode RGB888 frames to an animated GIF using the `gif` crate.
+    /// Must be called from a blocking context (not async).
+    fn encode_to_gif(
+        out_path: &Path,
+        video_frames: Vec<VideoFrame>,
+        config_width: u32,
+        config_height: u32,
+        max_width: u32,
+        fps: u32,
+    ) -> Result<(), Error> {
+        let (width, height) = stream_dimensions(&video_frames, config_width, config_height);
+        // GIF dimensions are u16, clamp the target size accordingly
+        let max_width = if max_width == 0 { width } else { max_width };
+        let out_width = width.min(max_width).min(u16::MAX as u32).max(1);
+        let out_height = ((height as u64 * out_width as u64) / width.max(1) as u64)
+            .clamp(1, u16::MAX as u64) as u32;
+
+        // Minimum delay between written frames, derived from the requested fps
+        let min_interval_ms = 1000 / fps.max(1) as u64;
+
+        // Keep only frames that are at least `min_interval_ms` apart
+        let mut selected: Vec<(&VideoFrame, (u32, u32))> = Vec::new();
+        for (idx, vframe) in video_frames.iter().enumerate() {
+            let Some(dims) = source_dimensions(vframe, config_width, config_height) else {
+                warn!(
+                    "skipping frame {} ({}x{}, {} bytes), not a complete RGB888 image",
+                    idx,
+                    vframe.width,
+                    vframe.height,
+                    vframe.data.len()
                 );
-                encoded
-                    .write_interleaved(&mut octx)
-                    .map_err(|e| format!("Write audio packet error: {}", e))?;
+                continue;
+            };
+            match selected.last() {
+                Some((last, _))
+                    if vframe.timestamp_ms.saturating_sub(last.timestamp_ms) < min_interval_ms => {}
+                _ => selected.push((vframe, dims)),
             }
         }
 
-        // Write trailer
-        octx.write_trailer()
-            .map_err(|e| format!("Cannot write trailer: {}", e))?;
+        if selected.is_empty() {
+            return Err(Error::Encode(
+                "no video frames available after filtering".to_string(),
+            ));
+        }
+
+        let file = std::fs::File::create(out_path)?;
+        let mut encoder = gif::Encoder::new(
+            std::io::BufWriter::new(file),
+            out_width as u16,
+            out_height as u16,
+            &[],
+        )
+        .map_err(|e| Error::Encode(format!("Cannot create GIF encoder: {}", e)))?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| Error::Encode(format!("Cannot set GIF repeat: {}", e)))?;
+
+        for (idx, (vframe, (src_width, src_height))) in selected.iter().enumerate() {
+            // Delay until the next written frame; the last frame uses the fps interval
+            let delay_ms = match selected.get(idx + 1) {
+                Some((next, _)) => next.timestamp_ms.saturating_sub(vframe.timestamp_ms),
+                None => min_interval_ms,
+            }
+            .max(min_interval_ms);
+
+            let pixels =
+                letterbox_scale_rgb(&vframe.data, *src_width, *src_height, out_width, out_height);
+
+            // Quantize to a 256 color palette (speed 10 is the gif crate's recommended default)
+            let mut frame =
+                gif::Frame::from_rgb_speed(out_width as u16, out_height as u16, &pixels, 10);
+            // GIF delays are expressed in hundredths of a second
+            frame.delay = (delay_ms / 10).clamp(1, u16::MAX as u64) as u16;
+
+            encoder
+                .write_frame(&frame)
+                .map_err(|e| Error::Encode(format!("Write GIF frame error: {}", e)))?;
+        }
 
         Ok(())
     }
+
+    /// Save a contact sheet (thumbnail grid) of the buffered video into a PNG at `out_path`.
+    /// `cols * rows` evenly spaced frames are sampled from the buffer, each scaled down to
+    /// `CONTACT_SHEET_THUMB_WIDTH` pixels wide, and tiled left-to-right, top-to-bottom.
+    /// Thumbnails take the first frame's aspect ratio; other frames are letterboxed into it.
+    pub async fn save_contact_sheet(
+        &self,
+        out_path: impl AsRef<Path>,
+        cols: u32,
+        rows: u32,
+    ) -> Result<(), Error> {
+        if cols == 0 || rows == 0 {
+            return Err(Error::Encode(
+                "contact sheet needs at least one column and one row".to_string(),
+            ));
+        }
+
+        // Clone buffer to avoid holding the lock during encoding
+        let video_frames = {
+            let guard = self.inner.video_buf.lock().unwrap();
+            guard.iter().cloned().collect::<Vec<_>>()
+        };
+
+        if video_frames.is_empty() {
+            return Err(Error::Encode(
+                "no video frames available to save".to_string(),
+            ));
+        }
+
+        let out_path = out_path.as_ref().to_path_buf();
+        let width = self.inner.width;
+        let height = self.inner.height;
+
+        tokio::task::spawn_blocking(move || {
+            Self::encode_contact_sheet(&out_path, video_frames, width, height, cols, rows)
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    /// Same as `save_contact_sheet`, but picks a near-square grid holding `count` thumbnails.
+    pub async fn save_contact_sheet_count(
+        &self,
+        out_path: impl AsRef<Path>,
+        count: u32,
+    ) -> Result<(), Error> {
+        if count == 0 {
+            return Err(Error::Encode(
+                "contact sheet needs at least one thumbnail".to_string(),
+            ));
+        }
+        let cols = (count as f64).sqrt().ceil() as u32;
+        let rows = count.div_ceil(cols);
+        self.save_contact_sheet(out_path, cols, rows).await
+    }
+
+    /// Internal method to tile sampled RGB888 frames into a PNG grid.
+    /// Must be called from a blocking context (not async).
+    fn encode_contact_sheet(
+        out_path: &Path,
+        video_frames: Vec<VideoFrame>,
+        config_width: u32,
+        config_height: u32,
+        cols: u32,
+        rows: u32,
+    ) -> Result<(), Error> {
+        let (width, height) = stream_dimensions(&video_frames, config_width, config_height);
+        let frames: Vec<(&VideoFrame, (u32, u32))> = video_frames
+            .iter()
+            .filter_map(|f| Some((f, source_dimensions(f, config_width, config_height)?)))
+            .collect();
+
+        if frames.is_empty() {
+            return Err(Error::Encode(
+                "no video frames available after filtering".to_string(),
+            ));
+        }
+
+        let thumb_w = width.min(CONTACT_SHEET_THUMB_WIDTH).max(1);
+        let thumb_h = ((height as u64 * thumb_w as u64) / width.max(1) as u64).max(1) as u32;
+
+        let sheet_w = cols * thumb_w;
+        let sheet_h = rows * thumb_h;
+        let mut sheet = image::RgbImage::new(sheet_w, sheet_h);
+
+        // Sample evenly spaced frames, always including the first and last one
+        let slots = (cols * rows) as usize;
+        let picks = slots.min(frames.len());
+        for slot in 0..picks {
+            let frame_idx = if picks == 1 {
+                0
+            } else {
+                slot * (frames.len() - 1) / (picks - 1)
+            };
+            let (frame, (src_width, src_height)) = frames[frame_idx];
+            let thumb = letterbox_scale_rgb(&frame.data, src_width, src_height, thumb_w, thumb_h);
+
+            let x0 = (slot as u32 % cols) * thumb_w;
+            let y0 = (slot as u32 / cols) * thumb_h;
+            for (i, px) in thumb.chunks_exact(3).enumerate() {
+                let x = x0 + i as u32 % thumb_w;
+                let y = y0 + i as u32 / thumb_w;
+                sheet.put_pixel(x, y, image::Rgb([px[0], px[1], px[2]]));
+            }
+        }
+
+        sheet
+            .save_with_format(out_path, image::ImageFormat::Png)
+            .map_err(|e| Error::Encode(format!("Cannot write contact sheet: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// Outcome of `StreamPuffer::save_last_to_mp4` / `save_last_to_webm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoSaveReport {
+    /// Frames encoded into the output file
+    pub frames_written: usize,
+    /// Frames dropped because their payload was not a complete RGB888 image
+    pub frames_skipped: usize,
+    /// Resolution of the written video, that of the first frame; frames captured at another
+    /// resolution (rotation, folding) are letterboxed into it
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Result of `StreamPuffer::save_last_to_mp4_cancellable`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveOutcome {
+    /// The whole buffer was written
+    Completed(VideoSaveReport),
+    /// The cancel flag was raised first; the partial file was removed
+    Cancelled,
+}
+
+/// Cancellation flag and progress callback of a save, checked by `encode_video` per frame.
+#[derive(Default)]
+struct EncodeControl {
+    cancel: Option<Arc<AtomicBool>>,
+    progress: Option<Box<dyn FnMut(usize, usize) + Send>>,
+}
+
+impl EncodeControl {
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+    }
+
+    fn report(&mut self, encoded: usize, total: usize) {
+        if let Some(progress) = self.progress.as_mut() {
+            progress(encoded, total);
+        }
+    }
+}
+
+/// Video container written by the `save_last_to_*` methods, which fixes the codecs used.
+#[derive(Debug, Clone, Copy)]
+enum VideoContainer {
+    /// MPEG-4 video with AAC audio
+    Mp4,
+    /// VP9 video with Opus audio
+    Webm,
+}
+
+impl VideoContainer {
+    fn muxer(self) -> &'static str {
+        match self {
+            VideoContainer::Mp4 => "mp4",
+            VideoContainer::Webm => "webm",
+        }
+    }
+
+    fn video_codec(self) -> ffmpeg::codec::Id {
+        match self {
+            VideoContainer::Mp4 => ffmpeg::codec::Id::MPEG4,
+            VideoContainer::Webm => ffmpeg::codec::Id::VP9,
+        }
+    }
+
+    fn audio_codec(self) -> ffmpeg::codec::Id {
+        match self {
+            VideoContainer::Mp4 => ffmpeg::codec::Id::AAC,
+            VideoContainer::Webm => ffmpeg::codec::Id::OPUS,
+        }
+    }
+}
+
+/// An audio encoder attached to an output stream, fed with buffered emulator PCM.
+struct AudioOutput {
+    encoder: ffmpeg::encoder::Audio,
+    // converts our interleaved f32 samples into the encoder's sample format
+    resampler: ffmpeg::software::resampling::Context,
+    stream_idx: usize,
+    layout: ffmpeg::ChannelLayout,
+    channels: usize,
+    rate: u32,
+}
+
+impl AudioOutput {
+    /// Add a `codec_id` audio stream to `octx`. Must be called before writing the header.
+    /// Uses the encoder's preferred sample format and the closest supported rate to `sample_rate`.
+    fn add_stream(
+        octx: &mut ffmpeg::format::context::Output,
+        codec_id: ffmpeg::codec::Id,
+        sample_rate: u32,
+        channels: u32,
+    ) -> Result<Self, Error> {
+        use ffmpeg::codec;
+        use ffmpeg::format;
+        use ffmpeg::software::resampling;
+        use ffmpeg::Rational;
+
+        let global_header = octx.format().flags().contains(format::Flags::GLOBAL_HEADER);
+
+        let audio_codec = codec::encoder::find(codec_id)
+            .ok_or_else(|| Error::Encode(format!("{:?} encoder not found", codec_id)))?;
+        let caps = audio_codec
+            .audio()
+            .map_err(|e| Error::ffmpeg("Not an audio encoder", e))?;
+
+        let enc_format = caps
+            .formats()
+            .and_then(|mut formats| formats.next())
+            .unwrap_or(format::Sample::F32(format::sample::Type::Planar));
+        let enc_rate = match caps.rates() {
+            Some(rates) => {
+                let rates: Vec<i32> = rates.collect();
+                rates
+                    .iter()
+                    .copied()
+                    .filter(|&r| r >= sample_rate as i32)
+                    .min()
+                    .or_else(|| rates.iter().copied().max())
+                    .map(|r| r as u32)
+                    .unwrap_or(sample_rate)
+            }
+            None => sample_rate,
+        };
+
+        let mut ast = octx
+            .add_stream(audio_codec)
+            .map_err(|e| Error::ffmpeg("Cannot add audio stream", e))?;
+        let stream_idx = ast.index();
+
+        let layout = ffmpeg::ChannelLayout::default(channels as i32);
+        let mut audio_enc = codec::Context::new()
+            .encoder()
+            .audio()
+            .map_err(|e| Error::ffmpeg("Cannot create audio encoder", e))?;
+        audio_enc.set_rate(enc_rate as i32);
+        audio_enc.set_channel_layout(layout);
+        audio_enc.set_format(enc_format);
+        // Sample-accurate time_base
+        audio_enc.set_time_base(Rational::new(1, enc_rate as i32));
+        if global_header {
+            audio_enc.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
+        }
+
+        let encoder = audio_enc
+            .open_as(audio_codec)
+            .map_err(|e| Error::ffmpeg("Cannot open audio encoder", e))?;
+        ast.set_parameters(&encoder);
+
+        let resampler = resampling::Context::get(
+            format::Sample::F32(format::sample::Type::Packed),
+            layout,
+            enc_rate,
+            enc_format,
+            layout,
+            enc_rate,
+        )
+        .map_err(|e| Error::ffmpeg("Cannot create resampler", e))?;
+
+        Ok(Self {
+            encoder,
+            resampler,
+            stream_idx,
+            layout,
+            channels: channels as usize,
+            rate: enc_rate,
+        })
+    }
+
+    /// Convert, encode and write every chunk, then flush the encoder. The output timeline
+    /// starts at `start_ms` on the capture clock: audio beginning later is delayed by the
+    /// difference. Chunks are then laid out back to back from the first one's timestamp.
+    fn encode_chunks(
+        &mut self,
+        octx: &mut ffmpeg::format::context::Output,
+        audio_chunks: &[AudioChunk],
+        start_ms: u64,
+    ) -> Result<(), Error> {
+        // Variable frame size encoders (PCM, FLAC) report 0
+        let frame_size = match self.encoder.frame_size() {
+            0 => 1024,
+            n => n as usize,
+        };
+        let channels = self.channels;
+        let time_base = ffmpeg::Rational::new(1, self.rate as i32);
+
+        let mut sample_buffer: Vec<f32> = Vec::new();
+        let offset_ms = audio_chunks
+            .first()
+            .map_or(0, |chunk| chunk.timestamp_ms.saturating_sub(start_ms));
+        let mut pts = (offset_ms * self.rate as u64 / 1000) as i64;
+        for achunk in audio_chunks {
+            // Decode to interleaved f32 [-1.0, 1.0], then match the encoder layout
+            let src_channels = pcm_channels(&achunk.format);
+            let decoded = pcm_to_f32(&achunk.data, &achunk.format);
+            let remixed = remix_channels(&decoded, src_channels, channels);
+            let samples = resample_linear(
+                &remixed,
+                channels,
+                pcm_sample_rate(&achunk.format),
+                self.rate,
+            );
+            sample_buffer.extend_from_slice(&samples);
+
+            while sample_buffer.len() >= frame_size * channels {
+                let frame_samples: Vec<f32> =
+                    sample_buffer.drain(0..frame_size * channels).collect();
+                self.send_frame(&frame_samples, pts)?;
+                pts += frame_size as i64;
+                write_audio_packets(octx, &mut self.encoder, self.stream_idx, time_base)?;
+            }
+        }
+
+        // Pad the tail with silence so fixed frame size encoders accept it
+        if !sample_buffer.is_empty() {
+            sample_buffer.resize(frame_size * channels, 0.0);
+            self.send_frame(&sample_buffer, pts)?;
+            write_audio_packets(octx, &mut self.encoder, self.stream_idx, time_base)?;
+        }
+
+        // Flush audio encoder
+        self.encoder
+            .send_eof()
+            .map_err(|e| Error::ffmpeg("Send audio EOF error", e))?;
+        write_audio_packets(octx, &mut self.encoder, self.stream_idx, time_base)
+    }
+
+    fn send_frame(&mut self, samples: &[f32], pts: i64) -> Result<(), Error> {
+        send_audio_frame(
+            &mut self.encoder,
+            &mut self.resampler,
+            samples,
+            self.channels,
+            self.layout,
+            self.rate,
+            pts,
+        )
+    }
+}
+
+/// Convert one frame worth of interleaved f32 samples to the encoder format and submit it.
+fn send_audio_frame(
+    encoder: &mut ffmpeg::encoder::Audio,
+    resampler: &mut ffmpeg::software::resampling::Context,
+    samples: &[f32],
+    channels: usize,
+    layout: ffmpeg::ChannelLayout,
+    rate: u32,
+    pts: i64,
+) -> Result<(), Error> {
+    use ffmpeg::{format, frame};
+
+    let mut input = frame::Audio::new(
+        format::Sample::F32(format::sample::Type::Packed),
+        samples.len() / channels,
+        layout,
+    );
+    input.set_rate(rate);
+    {
+        // Packed audio lives in a single plane; copy the raw sample bytes
+        let bytes = input.data_mut(0);
+        for (i, sample) in samples.iter().enumerate() {
+            bytes[i * 4..i * 4 + 4].copy_from_slice(&sample.to_ne_bytes());
+        }
+    }
+
+    let mut converted = frame::Audio::empty();
+    resampler
+        .run(&input, &mut converted)
+        .map_err(|e| Error::ffmpeg("Audio conversion error", e))?;
+    converted.set_pts(Some(pts));
+
+    encoder
+        .send_frame(&converted)
+        .map_err(|e| Error::ffmpeg("Send audio frame error", e))
+}
+
+/// Drain encoded packets from `encoder` into the output stream `stream_idx`.
+fn write_audio_packets(
+    octx: &mut ffmpeg::format::context::Output,
+    encoder: &mut ffmpeg::encoder::Audio,
+    stream_idx: usize,
+    encoder_time_base: ffmpeg::Rational,
+) -> Result<(), Error> {
+    let mut encoded = ffmpeg::Packet::empty();
+    while encoder.receive_packet(&mut encoded).is_ok() {
+        encoded.set_stream(stream_idx);
+        encoded.rescale_ts(
+            encoder_time_base,
+            octx.stream(stream_idx).unwrap().time_base(),
+        );
+        encoded
+            .write_interleaved(octx)
+            .map_err(|e| Error::ffmpeg("Write audio packet error", e))?;
+    }
+    Ok(())
+}
+
+/// Capture time in ms of a packet stamped `timestamp_us`, on the clock shared by the video
+/// and audio buffers; `previous_ms` is the last entry of the same buffer.
+///
+/// `Image::timestamp_us` and `AudioPacket::timestamp` are both documented in
+/// emulator_controller.proto as Unix epoch microseconds taken on the host, so dividing by
+/// 1000 puts them on one Unix epoch ms clock with no per-stream offset. That clock is kept
+/// as u64: epoch ms does not fit in 32 bits. A timestamp older than `previous_ms` is clamped
+/// to it so each buffer stays monotonic, as the encoders require.
+fn capture_ms(timestamp_us: u64, previous_ms: Option<u64>) -> u64 {
+    (timestamp_us / 1000).max(previous_ms.unwrap_or(0))
+}
+
+/// Decide whether a frame captured at `timestamp_us` is kept when thinning to one frame per
+/// `interval_us`. Due times advance on a fixed grid so the average rate converges to the target
+/// instead of drifting low when the source rate is not a multiple of it.
+fn keep_frame_at(next_due: &mut Option<u64>, timestamp_us: u64, interval_us: u64) -> bool {
+    match *next_due {
+        // Frame arrived early, skip it
+        Some(due) if timestamp_us < due && due - timestamp_us <= interval_us => false,
+        // On schedule; fall back to the frame's own time if we lagged more than one interval
+        Some(due) if timestamp_us >= due && timestamp_us - due < interval_us => {
+            *next_due = Some(due + interval_us);
+            true
+        }
+        // First frame, a gap in the stream, or timestamps went backwards: restart the grid
+        _ => {
+            *next_due = Some(timestamp_us + interval_us);
+            true
+        }
+    }
+}
+
+/// Resolution of `frame`'s RGB888 payload: the size reported by the emulator, or the
+/// configured one when unset. `None` if the payload is not a complete image of that size.
+fn source_dimensions(
+    frame: &VideoFrame,
+    config_width: u32,
+    config_height: u32,
+) -> Option<(u32, u32)> {
+    let dims = if frame.width > 0 && frame.height > 0 {
+        (frame.width, frame.height)
+    } else {
+        (config_width, config_height)
+    };
+    (dims.0 > 0 && frame.data.len() == (dims.0 as usize * dims.1 as usize * 3)).then_some(dims)
+}
+
+/// Output resolution for a recording: that of the first usable frame, so later rotations are
+/// letterboxed into the initial orientation. Falls back to the configured size.
+fn stream_dimensions(frames: &[VideoFrame], config_width: u32, config_height: u32) -> (u32, u32) {
+    frames
+        .iter()
+        .find_map(|f| source_dimensions(f, config_width, config_height))
+        .unwrap_or((config_width, config_height))
+}
+
+/// Scale an RGB24 frame to fit inside `width` x `height` keeping its aspect ratio, centred on
+/// black bars. Scalers are cached per source size in `scalers`.
+fn letterbox_rgb(
+    scalers: &mut HashMap<(u32, u32), ffmpeg::software::scaling::Context>,
+    src: &ffmpeg::frame::Video,
+    width: u32,
+    height: u32,
+) -> Result<ffmpeg::frame::Video, Error> {
+    use ffmpeg::format::Pixel;
+    use ffmpeg::frame;
+    use ffmpeg::software::scaling;
+
+    let (src_width, src_height) = (src.width(), src.height());
+    let (fit_width, fit_height) = fit_within(src_width, src_height, width, height);
+
+    let scaler = match scalers.entry((src_width, src_height)) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => entry.insert(
+            scaling::Context::get(
+                Pixel::RGB24,
+                src_width,
+                src_height,
+                Pixel::RGB24,
+                fit_width,
+                fit_height,
+                scaling::Flags::BILINEAR,
+            )
+            .map_err(|e| Error::ffmpeg("Cannot create letterbox scaler", e))?,
+        ),
+    };
+    let mut fitted = frame::Video::empty();
+    scaler
+        .run(src, &mut fitted)
+        .map_err(|e| Error::ffmpeg("Scaling error", e))?;
+
+    // Paste the fitted image centred on a black canvas
+    let mut canvas = frame::Video::new(Pixel::RGB24, width, height);
+    let canvas_stride = canvas.stride(0);
+    let canvas_data = canvas.data_mut(0);
+    canvas_data.fill(0);
+    let x_offset = ((width - fit_width) / 2) as usize * 3;
+    let y_offset = ((height - fit_height) / 2) as usize;
+    let row_len = fit_width as usize * 3;
+    let fitted_stride = fitted.stride(0);
+    let fitted_data = fitted.data(0);
+    for y in 0..fit_height as usize {
+        let dst = (y + y_offset) * canvas_stride + x_offset;
+        let src = y * fitted_stride;
+        canvas_data[dst..dst + row_len].copy_from_slice(&fitted_data[src..src + row_len]);
+    }
+    Ok(canvas)
+}
+
+/// Convert interleaved samples from `src_channels` to `dst_channels`.
+/// Downmixing to mono averages all channels; otherwise channels are repeated cyclically.
+fn remix_channels(samples: &[f32], src_channels: usize, dst_channels: usize) -> Vec<f32> {
+    if src_channels == dst_channels || src_channels == 0 {
+        return samples.to_vec();
+    }
+    let frames = samples.len() / src_channels;
+    let mut out = Vec::with_capacity(frames * dst_channels);
+    for frame in samples.chunks_exact(src_channels) {
+        if dst_channels == 1 {
+            out.push(frame.iter().sum::<f32>() / src_channels as f32);
+        } else {
+            for ch in 0..dst_channels {
+                out.push(frame[ch % src_channels]);
+            }
+        }
+    }
+    out
+}
+
+/// Linear-interpolation resampler for interleaved samples.
+fn resample_linear(samples: &[f32], channels: usize, src_rate: u32, dst_rate: u32) -> Vec<f32> {
+    if src_rate == dst_rate || samples.is_empty() || channels == 0 {
+        return samples.to_vec();
+    }
+    let in_frames = samples.len() / channels;
+    let out_frames = (in_frames as u64 * dst_rate as u64 / src_rate as u64) as usize;
+    let step = src_rate as f64 / dst_rate as f64;
+    let mut out = Vec::with_capacity(out_frames * channels);
+    for i in 0..out_frames {
+        let pos = i as f64 * step;
+        let i0 = (pos.floor() as usize).min(in_frames - 1);
+        let i1 = (i0 + 1).min(in_frames - 1);
+        let t = (pos - i0 as f64) as f32;
+        for ch in 0..channels {
+            let a = samples[i0 * channels + ch];
+            let b = samples[i1 * channels + ch];
+            out.push(a + (b - a) * t);
+        }
+    }
+    out
+}
+
+/// Largest size with the aspect ratio of `src_width` x `src_height` fitting inside
+/// `width` x `height`.
+fn fit_within(src_width: u32, src_height: u32, width: u32, height: u32) -> (u32, u32) {
+    if src_width as u64 * height as u64 > src_height as u64 * width as u64 {
+        (
+            width,
+            ((src_height as u64 * width as u64) / src_width as u64).max(1) as u32,
+        )
+    } else {
+        (
+            ((src_width as u64 * height as u64) / src_height as u64).max(1) as u32,
+            height,
+        )
+    }
+}
+
+/// `scale_rgb` into `dst_w` x `dst_h` keeping the source aspect ratio, centred on black bars.
+fn letterbox_scale_rgb(data: &[u8], src_w: u32, src_h: u32, dst_w: u32, dst_h: u32) -> Vec<u8> {
+    let (fit_w, fit_h) = fit_within(src_w, src_h, dst_w, dst_h);
+    let fitted = scale_rgb(data, src_w, src_h, fit_w, fit_h);
+    if (fit_w, fit_h) == (dst_w, dst_h) {
+        return fitted;
+    }
+    let mut out = vec![0; (dst_w * dst_h * 3) as usize];
+    let (x0, y0) = ((dst_w - fit_w) / 2, (dst_h - fit_h) / 2);
+    let row_len = fit_w as usize * 3;
+    for (y, row) in fitted.chunks_exact(row_len).enumerate() {
+        let offset = ((y0 as usize + y) * dst_w as usize + x0 as usize) * 3;
+        out[offset..offset + row_len].copy_from_slice(row);
+    }
+    out
+}
+
+/// Nearest-neighbour resize of a tightly packed RGB888 buffer.
+fn scale_rgb(data: &[u8], src_w: u32, src_h: u32, dst_w: u32, dst_h: u32) -> Vec<u8> {
+    if src_w == dst_w && src_h == dst_h {
+        return data.to_vec();
+    }
+    let mut out = Vec::with_capacity((dst_w * dst_h * 3) as usize);
+    for y in 0..dst_h {
+        let sy = (y as u64 * src_h as u64 / dst_h as u64) as usize;
+        for x in 0..dst_w {
+            let sx = (x as u64 * src_w as u64 / dst_w as u64) as usize;
+            let offset = (sy * src_w as usize + sx) * 3;
+            out.extend_from_slice(&data[offset..offset + 3]);
+        }
+    }
+    out
 }
@@ -0,0 +1,43 @@
+// Friendly view over the emulator's VM run state
+
+use crate::proto::vm_run_state::RunState;
+use crate::proto::VmRunState;
+
+/// Observable VM state, as returned by `DeviceGrpcClient::vm_status`.
+///
+/// Only the states `GetVmState` can report; transitions such as reset or restart
+/// are requested with the dedicated client methods instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum VmStatus {
+    Running,
+    Paused,
+    /// Paused while a snapshot is loaded.
+    RestoringSnapshot,
+    /// Paused while a snapshot is taken or exported.
+    SavingSnapshot,
+    /// Shutting down or already gone.
+    Shutdown,
+    /// The guest hit an internal error.
+    Error,
+    Unknown,
+}
+
+impl From<RunState> for VmStatus {
+    fn from(state: RunState) -> Self {
+        match state {
+            RunState::Running | RunState::Start => VmStatus::Running,
+            RunState::Paused | RunState::Stop => VmStatus::Paused,
+            RunState::RestoreVm => VmStatus::RestoringSnapshot,
+            RunState::SaveVm => VmStatus::SavingSnapshot,
+            RunState::Shutdown | RunState::Terminate => VmStatus::Shutdown,
+            RunState::InternalError => VmStatus::Error,
+            RunState::Unknown | RunState::Reset | RunState::Restart => VmStatus::Unknown,
+        }
+    }
+}
+
+impl From<&VmRunState> for VmStatus {
+    fn from(state: &VmRunState) -> Self {
+        RunState::try_from(state.state).map_or(VmStatus::Unknown, VmStatus::from)
+    }
+}
@@ -0,0 +1,164 @@
+// Browser remote control over HTTP (feature `web`)
+//
+// Endpoints:
+// - `GET /stream`: the screen as MJPEG (`multipart/x-mixed-replace`) at up to 15 fps, usable
+//   as an `<img src>`
+// - `POST /tap`: JSON body `{"x": 100, "y": 200}`, answers 204 once the tap is sent
+// - `GET /`: a minimal page showing the stream, clicks are forwarded as taps
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::body::{Bytes, StreamBody};
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::StreamExt;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::error::{Error, Result};
+use crate::proto::image_format::ImgFormat;
+use crate::proto::{Image, ImageFormat};
+use crate::DeviceGrpcClient;
+
+/// Multipart boundary between the JPEG parts of `GET /stream`.
+const BOUNDARY: &str = "ro-grpc-frame";
+
+/// JPEG quality of the streamed frames (1-100).
+const JPEG_QUALITY: u8 = 75;
+
+/// Shortest gap between two parts of `GET /stream`, about 15 fps. Frames arriving in
+/// between are replaced by newer ones, so the last frame of a burst is always sent.
+const MIN_FRAME_INTERVAL: std::time::Duration = std::time::Duration::from_millis(66);
+
+const INDEX: &str = r#"<!doctype html>
+<title>RoAnalyzer</title>
+<img id="screen" src="/stream" style="max-height:95vh">
+<script>
+const screen = document.getElementById("screen");
+screen.addEventListener("click", (e) => {
+  const x = Math.round(e.offsetX * screen.naturalWidth / screen.clientWidth);
+  const y = Math.round(e.offsetY * screen.naturalHeight / screen.clientHeight);
+  fetch("/tap", {method: "POST", headers: {"Content-Type": "application/json"}, body: JSON.stringify({x, y})});
+});
+</script>
+"#;
+
+/// Body of `POST /tap`, in screen pixels.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct TapRequest {
+    pub x: i32,
+    pub y: i32,
+}
+
+type SharedClient = Arc<Mutex<DeviceGrpcClient>>;
+
+/// Routes for the web bridge, ready to be merged into a larger axum app.
+pub fn router(client: DeviceGrpcClient) -> Router {
+    Router::new()
+        .route("/", get(index))
+        .route("/stream", get(stream))
+        .route("/tap", post(tap))
+        .with_state(Arc::new(Mutex::new(client)))
+}
+
+/// Serve `router(client)` on `addr` until the server fails.
+pub async fn serve(client: DeviceGrpcClient, addr: SocketAddr) -> Result<()> {
+    axum::Server::try_bind(&addr)
+        .map_err(|e| Error::Other(format!("cannot bind {}: {}", addr, e)))?
+        .serve(router(client).into_make_service())
+        .await
+        .map_err(|e| Error::Other(format!("web server failed: {}", e)))
+}
+
+async fn index() -> Html<&'static str> {
+    Html(INDEX)
+}
+
+async fn stream(State(client): State<SharedClient>) -> Response {
+    let fmt = ImageFormat {
+        format: ImgFormat::Rgb888.into(),
+        ..Default::default()
+    };
+    // the lock only covers opening the stream, taps keep working while it runs
+    let frames = match client.lock().await.stream_screenshot(fmt).await {
+        Ok(frames) => frames,
+        Err(e) => return error_response(e),
+    };
+    // Full-resolution frames arrive faster than they encode: only the latest is kept
+    let (latest_tx, latest_rx) = tokio::sync::watch::channel(None::<Image>);
+    tokio::spawn(async move {
+        let mut frames = frames;
+        while let Some(frame) = frames.next().await {
+            match frame {
+                Ok(frame) => {
+                    if latest_tx.send(Some(frame)).is_err() {
+                        break; // the viewer went away
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("screen stream ended: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+    let start = tokio::time::Instant::now();
+    let parts = futures::stream::unfold((latest_rx, start), |(mut latest, due)| async move {
+        tokio::time::sleep_until(due).await;
+        latest.changed().await.ok()?;
+        let frame = latest.borrow_and_update().clone()?;
+        let due = tokio::time::Instant::now() + MIN_FRAME_INTERVAL;
+        // JPEG encoding a full frame takes milliseconds, keep it off the runtime workers
+        let part = tokio::task::spawn_blocking(move || mjpeg_part(&frame))
+            .await
+            .map_err(Error::from)
+            .and_then(|part| part);
+        Some((part, (latest, due)))
+    });
+    (
+        [(
+            header::CONTENT_TYPE,
+            format!("multipart/x-mixed-replace; boundary={}", BOUNDARY),
+        )],
+        StreamBody::new(parts),
+    )
+        .into_response()
+}
+
+async fn tap(State(client): State<SharedClient>, Json(req): Json<TapRequest>) -> Response {
+    match client.lock().await.tap(req.x, req.y).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// `frame` as one JPEG part of the multipart stream.
+fn mjpeg_part(frame: &Image) -> Result<Bytes> {
+    let jpeg = encode_jpeg(frame)?;
+    let mut part = format!(
+        "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+        BOUNDARY,
+        jpeg.len()
+    )
+    .into_bytes();
+    part.extend_from_slice(&jpeg);
+    part.extend_from_slice(b"\r\n");
+    Ok(Bytes::from(part))
+}
+
+fn encode_jpeg(frame: &Image) -> Result<Vec<u8>> {
+    let rgb = crate::video::image_to_dynamic(frame)?.into_rgb8();
+    let mut out = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+        .encode_image(&rgb)
+        .map_err(|e| Error::Encode(format!("cannot encode JPEG frame: {}", e)))?;
+    Ok(out)
+}
+
+fn error_response(e: Error) -> Response {
+    (StatusCode::BAD_GATEWAY, e.to_string()).into_response()
+}
@@ -0,0 +1,64 @@
+// Running emulators from discovery files
+
+use ro_grpc::discovery::{discover_in, discover_token_in};
+use ro_grpc::EmulatorEndpoint;
+
+/// Far above any real pid limit, so never a live process.
+const DEAD_PID: u32 = 0x7fff_fff0;
+
+// stale files are only detected where process liveness can be checked
+#[cfg(unix)]
+#[test]
+fn live_emulators_are_parsed_and_stale_files_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    // this test process stands in for a running emulator
+    let pid = std::process::id();
+    std::fs::write(
+        dir.path().join(format!("pid_{}.ini", pid)),
+        "port.serial=5554\ngrpc.port=8554\ngrpc.token=s3cr3t\navd.name=Pixel_7\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join(format!("pid_{}.ini", DEAD_PID)),
+        "port.serial=5556\ngrpc.port=8556\n",
+    )
+    .unwrap();
+    // not a discovery file
+    std::fs::write(dir.path().join("notes.txt"), "grpc.port=8558\n").unwrap();
+
+    let endpoints = discover_in(dir.path()).unwrap();
+    assert_eq!(
+        endpoints,
+        vec![EmulatorEndpoint {
+            pid,
+            port: 8554,
+            token: Some("s3cr3t".to_string()),
+            avd_name: Some("Pixel_7".to_string()),
+        }]
+    );
+    assert_eq!(endpoints[0].uri(), "http://127.0.0.1:8554");
+}
+
+#[test]
+fn token_is_read_from_matching_discovery_file() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join(format!("pid_{}.ini", std::process::id())),
+        "grpc.port=8554\ngrpc.token=s3cr3t\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        discover_token_in(dir.path(), 8554).unwrap().as_deref(),
+        Some("s3cr3t")
+    );
+    assert_eq!(discover_token_in(dir.path(), 8556).unwrap(), None);
+}
+
+#[test]
+fn missing_discovery_dir_means_no_emulators() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(discover_in(&dir.path().join("avd/running"))
+        .unwrap()
+        .is_empty());
+}
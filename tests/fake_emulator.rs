@@ -0,0 +1,583 @@
+// Client round trips against the in-process fake emulator, run with `--features test-server`
+#![cfg(feature = "test-server")]
+
+use std::time::Duration;
+
+use image::GenericImageView;
+use ro_grpc::fake_emulator::spawn_fake_emulator;
+use ro_grpc::proto::image_format::ImgFormat;
+use ro_grpc::proto::AudioFormat;
+use ro_grpc::video::{image_to_dynamic, packed_rgb888, Rect};
+use ro_grpc::{DeviceGrpcClient, Settle, StreamHandle, VmStatus};
+use std::borrow::Cow;
+
+async fn connect() -> (DeviceGrpcClient, tokio::sync::oneshot::Sender<()>) {
+    let (addr, shutdown) = spawn_fake_emulator().await.expect("spawn fake emulator");
+    let client = DeviceGrpcClient::connect(format!("http://{}", addr))
+        .await
+        .expect("connect to fake emulator");
+    (client, shutdown)
+}
+
+#[tokio::test]
+async fn clipboard_round_trip() {
+    let (mut client, _shutdown) = connect().await;
+    client.set_clipboard("hello").await.unwrap();
+    assert_eq!(client.get_clipboard().await.unwrap(), "hello");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn connect_over_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("controller.sock");
+    let _shutdown = ro_grpc::fake_emulator::spawn_fake_emulator_unix(&socket)
+        .await
+        .expect("spawn fake emulator");
+    let mut client = DeviceGrpcClient::connect(format!("unix://{}", socket.display()))
+        .await
+        .expect("connect over unix socket");
+    client.set_clipboard("over uds").await.unwrap();
+    assert_eq!(client.get_clipboard().await.unwrap(), "over uds");
+}
+
+#[tokio::test]
+async fn phone_and_sms() {
+    let (mut client, _shutdown) = connect().await;
+    client.send_sms("+1 (650) 555-1221", "hello").await.unwrap();
+    client.incoming_call("5551234").await.unwrap();
+    client.accept_call("5551234").await.unwrap();
+    client.end_call("5551234").await.unwrap();
+
+    for bad in ["", "+", "call me", "555#1234"] {
+        assert!(
+            matches!(
+                client.incoming_call(bad).await,
+                Err(ro_grpc::Error::Phone(_))
+            ),
+            "{:?} accepted",
+            bad
+        );
+    }
+}
+
+#[tokio::test]
+async fn screenshot_is_checkerboard() {
+    let (mut client, _shutdown) = connect().await;
+    let png = client.get_screenshot().await.unwrap();
+    let decoded = image::load_from_memory(&png.image).unwrap().to_rgb8();
+    assert_eq!(decoded.dimensions(), (320, 640));
+    assert_eq!(decoded.get_pixel(0, 0).0, [255, 255, 255]);
+    assert_eq!(decoded.get_pixel(32, 0).0, [0, 0, 0]);
+
+    let raw = client.get_screenshot_as(ImgFormat::Rgb888).await.unwrap();
+    assert_eq!(raw.image.len(), 320 * 640 * 3);
+}
+
+#[tokio::test]
+async fn screenshot_converts_to_dynamic_image() {
+    let (mut client, _shutdown) = connect().await;
+    let rgb = client.screenshot_image().await.unwrap();
+    assert_eq!((rgb.width(), rgb.height()), (320, 640));
+
+    // Every wire format yields the same pixels
+    for format in [ImgFormat::Png, ImgFormat::Rgba8888] {
+        let img = client.get_screenshot_as(format).await.unwrap();
+        let converted = image_to_dynamic(&img).unwrap();
+        assert_eq!(converted.to_rgb8(), rgb.to_rgb8());
+    }
+
+    let mut truncated = client.get_screenshot_as(ImgFormat::Rgb888).await.unwrap();
+    truncated.image.pop();
+    assert!(image_to_dynamic(&truncated).is_err());
+}
+
+#[tokio::test]
+async fn frames_repack_to_tight_rgb888() {
+    let (mut client, _shutdown) = connect().await;
+    let rgb = client.get_screenshot_as(ImgFormat::Rgb888).await.unwrap();
+    assert!(matches!(packed_rgb888(&rgb).unwrap(), Cow::Borrowed(_)));
+
+    for format in [ImgFormat::Png, ImgFormat::Rgba8888] {
+        let img = client.get_screenshot_as(format).await.unwrap();
+        assert_eq!(packed_rgb888(&img).unwrap(), rgb.image);
+    }
+
+    // rows padded to a 1024-byte stride, with and without padding after the last row
+    let row_len = 320 * 3;
+    let mut padded = rgb.clone();
+    padded.image = rgb
+        .image
+        .chunks(row_len)
+        .flat_map(|row| row.iter().copied().chain([0xAB; 1024 - 320 * 3]))
+        .collect();
+    assert_eq!(packed_rgb888(&padded).unwrap(), rgb.image);
+    padded.image.truncate(padded.image.len() - (1024 - row_len));
+    assert_eq!(packed_rgb888(&padded).unwrap(), rgb.image);
+
+    let mut truncated = rgb.clone();
+    truncated.image.truncate(row_len);
+    assert!(packed_rgb888(&truncated).is_err());
+}
+
+#[tokio::test]
+async fn save_screenshot_region_and_thumbnail() {
+    let (mut client, _shutdown) = connect().await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let region = dir.path().join("region.png");
+    let rect = Rect {
+        x: 32,
+        y: 0,
+        width: 64,
+        height: 32,
+    };
+    client.save_screenshot_region(&region, rect).await.unwrap();
+    let saved = image::open(&region).unwrap().to_rgb8();
+    assert_eq!(saved.dimensions(), (64, 32));
+    // starts on a black square, then a white one
+    assert_eq!(saved.get_pixel(0, 0).0, [0, 0, 0]);
+    assert_eq!(saved.get_pixel(32, 0).0, [255, 255, 255]);
+
+    let outside = Rect { x: 300, ..rect };
+    assert!(client
+        .save_screenshot_region(&region, outside)
+        .await
+        .is_err());
+
+    let thumb = dir.path().join("thumb.jpg");
+    client.save_screenshot_scaled(&thumb, 100).await.unwrap();
+    assert_eq!(image::open(&thumb).unwrap().dimensions(), (50, 100));
+}
+
+#[tokio::test]
+async fn capture_state_writes_report() {
+    let (mut client, _shutdown) = connect().await;
+    let state = client.capture_state().await.unwrap();
+    assert_eq!(
+        (state.screenshot.width, state.screenshot.height),
+        (320, 640)
+    );
+    assert_eq!(state.vm, VmStatus::Running);
+    assert_eq!(state.displays.len(), 1);
+
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = state.write_report(dir.path()).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(json_path).unwrap()).unwrap();
+    assert_eq!(json["screenshot"]["file"], "screenshot.png");
+    assert_eq!(json["vm"], "Running");
+    let png = image::open(dir.path().join("screenshot.png")).unwrap();
+    assert_eq!(png.dimensions(), (320, 640));
+}
+
+#[tokio::test]
+async fn display_configurations() {
+    let (mut client, _shutdown) = connect().await;
+    let configs = client.get_display_configurations().await.unwrap();
+    assert_eq!(configs.displays.len(), 1);
+    assert_eq!(configs.displays[0].width, 320);
+}
+
+#[tokio::test]
+async fn screenshot_all_covers_every_display() {
+    let (mut client, _shutdown) = connect().await;
+    let mut configs = client.get_display_configurations().await.unwrap();
+    let mut second = configs.displays[0].clone();
+    second.display = 3;
+    configs.displays.push(second);
+    client.set_display_configurations(configs).await.unwrap();
+
+    let shots = client.screenshot_all().await.unwrap();
+    let displays: Vec<(u32, u32)> = shots
+        .iter()
+        .map(|(index, image)| (*index, image.format.as_ref().unwrap().display))
+        .collect();
+    assert_eq!(displays, [(0, 0), (1, 3)]);
+    for (_, image) in &shots {
+        let decoded = image::load_from_memory(&image.image).unwrap();
+        assert_eq!(decoded.dimensions(), (320, 640));
+    }
+}
+
+#[tokio::test]
+async fn recording_addresses_displays_by_id() {
+    let (mut client, _shutdown) = connect().await;
+    let mut configs = client.get_display_configurations().await.unwrap();
+    let mut second = configs.displays[0].clone();
+    second.display = 5;
+    second.width = 160;
+    second.height = 320;
+    configs.displays.push(second);
+    client.set_display_configurations(configs).await.unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let config = ro_grpc::RecordingConfig {
+        display: 1,
+        ..Default::default()
+    };
+    let manifest = client
+        .record_raw(dir.path(), Duration::from_millis(100), Some(config))
+        .await
+        .unwrap();
+    assert_eq!((manifest.width, manifest.height), (160, 320));
+
+    // an explicit size does not skip the display check
+    let config = ro_grpc::RecordingConfig {
+        display: 2,
+        width: 64,
+        height: 48,
+        ..Default::default()
+    };
+    assert!(matches!(
+        client
+            .record_raw(dir.path(), Duration::from_millis(100), Some(config))
+            .await,
+        Err(ro_grpc::Error::DisplayOutOfRange { index: 2, count: 2 })
+    ));
+}
+
+#[tokio::test]
+async fn audio_stream_reports_format() {
+    let (mut client, _shutdown) = connect().await;
+    let mut stream = client
+        .stream_audio(AudioFormat {
+            sampling_rate: 16000,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let packet = stream.message().await.unwrap().expect("first audio packet");
+    assert_eq!(packet.format.unwrap().sampling_rate, 16000);
+    // 20 ms of 16 kHz mono s16
+    assert_eq!(packet.audio.len(), 320 * 2);
+}
+
+#[tokio::test]
+async fn audio_levels_of_tone() {
+    use futures::StreamExt;
+
+    let (mut client, _shutdown) = connect().await;
+    let levels = client
+        .audio_levels(
+            AudioFormat {
+                sampling_rate: 16000,
+                ..Default::default()
+            },
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap();
+    let levels: Vec<_> = levels.take(3).collect().await;
+    for level in levels {
+        // half-scale sine: peak 0.5, rms 0.5 / sqrt(2)
+        let level = level.unwrap();
+        assert!((level.peak - 0.5).abs() < 0.01, "{:?}", level);
+        assert!((level.rms - 0.354).abs() < 0.01, "{:?}", level);
+        assert!(!level.is_silent(0.01));
+    }
+}
+
+#[tokio::test]
+async fn record_raw_writes_frames_audio_and_manifest() {
+    let (mut client, _shutdown) = connect().await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = ro_grpc::RecordingConfig {
+        include_audio: true,
+        width: 160,
+        height: 320,
+        audio_sample_rate: 16000,
+        ..Default::default()
+    };
+    let manifest = client
+        .record_raw(dir.path(), Duration::from_millis(300), Some(config))
+        .await
+        .unwrap();
+
+    assert_eq!((manifest.width, manifest.height), (160, 320));
+    assert!(
+        manifest.frames.len() >= 3,
+        "{} frames",
+        manifest.frames.len()
+    );
+    for frame in &manifest.frames {
+        let data = std::fs::read(dir.path().join(&frame.file)).unwrap();
+        assert_eq!(data.len(), 160 * 320 * 3);
+    }
+    let audio = manifest.audio.as_ref().expect("audio sidecar");
+    assert_eq!((audio.sample_rate, audio.channels), (16000, 2));
+    let pcm = std::fs::metadata(dir.path().join(&audio.file)).unwrap();
+    assert!(pcm.len() > 0);
+    assert_eq!(pcm.len(), audio.bytes);
+    assert_eq!(ro_grpc::RawManifest::load(dir.path()).unwrap(), manifest);
+}
+
+#[tokio::test]
+async fn record_raw_keeps_manifest_when_stream_fails() {
+    let (addr, _shutdown) = spawn_fake_emulator().await.unwrap();
+    let endpoint = format!("http://{}", addr);
+    let mut client = DeviceGrpcClient::connect(endpoint.clone()).await.unwrap();
+    let mut control = DeviceGrpcClient::connect(endpoint).await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let config = ro_grpc::RecordingConfig {
+        width: 64,
+        height: 48,
+        include_audio: true,
+        audio_sample_rate: 16000,
+        ..Default::default()
+    };
+
+    let (recorded, _) = tokio::join!(
+        client.record_raw(dir.path(), Duration::from_secs(5), Some(config)),
+        async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            control.shutdown().await.unwrap();
+        }
+    );
+    match recorded {
+        Err(ro_grpc::Error::Status(status)) => {
+            assert_eq!(status.code(), tonic::Code::Unavailable)
+        }
+        other => panic!("expected the stream error, got {:?}", other.map(|_| ())),
+    }
+    let manifest = ro_grpc::RawManifest::load(dir.path()).unwrap();
+    assert!(!manifest.frames.is_empty());
+    let audio = manifest.audio.as_ref().expect("audio sidecar");
+    let pcm = std::fs::metadata(dir.path().join(&audio.file)).unwrap();
+    assert_eq!(pcm.len(), audio.bytes);
+}
+
+#[tokio::test]
+async fn record_raw_reads_frames_through_mmap() {
+    let (mut client, _shutdown) = connect().await;
+    let dir = tempfile::tempdir().unwrap();
+    let shared = dir.path().join("frames.mmap");
+    let config = ro_grpc::RecordingConfig {
+        width: 64,
+        height: 48,
+        transport: ro_grpc::Transport::mmap(&shared),
+        ..Default::default()
+    };
+    let out = dir.path().join("raw");
+    let manifest = client
+        .record_raw(&out, Duration::from_millis(200), Some(config))
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::metadata(&shared).unwrap().len(), 64 * 48 * 3);
+    assert!(!manifest.frames.is_empty());
+    for frame in &manifest.frames {
+        let data = std::fs::read(out.join(&frame.file)).unwrap();
+        assert_eq!(data.len(), 64 * 48 * 3);
+        // top-left checker square is white, the blank file would read black
+        assert_eq!(&data[..3], &[255, 255, 255]);
+    }
+}
+
+#[tokio::test]
+async fn unusable_mmap_file_falls_back_to_grpc() {
+    let (mut client, _shutdown) = connect().await;
+    let dir = tempfile::tempdir().unwrap();
+    let shared = dir.path().join("missing").join("frames.mmap");
+    let config = ro_grpc::RecordingConfig {
+        transport: ro_grpc::Transport::mmap(&shared),
+        ..Default::default()
+    };
+    let out = dir.path().join("raw");
+    let manifest = client
+        .record_raw(&out, Duration::from_millis(200), Some(config))
+        .await
+        .unwrap();
+
+    assert!(!shared.exists());
+    assert!(!manifest.frames.is_empty());
+    let data = std::fs::read(out.join(&manifest.frames[0].file)).unwrap();
+    assert_eq!(
+        data.len(),
+        manifest.width as usize * manifest.height as usize * 3
+    );
+}
+
+#[tokio::test]
+async fn stopped_stream_ends_pending_reader() {
+    let (mut client, _shutdown) = connect().await;
+    let mut stream = StreamHandle::new(
+        client
+            .stream_audio(AudioFormat {
+                sampling_rate: 16000,
+                ..Default::default()
+            })
+            .await
+            .unwrap(),
+    );
+    assert!(stream.message().await.unwrap().is_some());
+
+    let stopper = stream.stopper();
+    let reader = tokio::spawn(async move {
+        let mut received = 0;
+        while stream.message().await.unwrap().is_some() {
+            received += 1;
+        }
+        received
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    stopper.stop();
+    tokio::time::timeout(Duration::from_secs(1), reader)
+        .await
+        .expect("reader ends after stop")
+        .unwrap();
+    assert!(stopper.is_stopped());
+}
+
+#[tokio::test]
+async fn static_screen_is_stable() {
+    let (mut client, _shutdown) = connect().await;
+    client
+        .wait_for_stable_timeout(
+            Duration::from_millis(20),
+            Duration::from_millis(60),
+            0.01,
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn tap_and_capture_returns_screenshot() {
+    let (mut client, _shutdown) = connect().await;
+    let golden = client.get_screenshot().await.unwrap();
+
+    let shot = client
+        .tap_and_capture(10, 20, Duration::from_millis(10))
+        .await
+        .unwrap();
+    assert_eq!(shot.image, golden.image);
+
+    let settle = Settle::Stable {
+        poll: Duration::from_millis(20),
+        stable_for: Duration::from_millis(60),
+        threshold: 0.01,
+        timeout: Duration::from_secs(5),
+    };
+    let shot = client.tap_and_capture(10, 20, settle).await.unwrap();
+    assert_eq!(shot.image, golden.image);
+}
+
+#[tokio::test]
+async fn close_ends_the_client() {
+    let (mut client, _shutdown) = connect().await;
+    client.get_screenshot().await.unwrap();
+    client.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn unreachable_emulator_counts_connection_errors() {
+    let (client, shutdown) = connect().await;
+    let mut client = client.with_retry_reads(1);
+    client.get_clipboard().await.unwrap();
+    shutdown.send(()).unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert!(client.get_clipboard().await.is_err());
+    let metrics = client.metrics();
+    assert_eq!(metrics.read_retries, 1);
+    assert_eq!(metrics.connection_errors, 2);
+
+    // setters and streams are not retried, but count too
+    assert!(client.set_clipboard("lost").await.is_err());
+    assert!(client.stream_clipboard().await.is_err());
+    assert_eq!(client.metrics().connection_errors, 4);
+}
+
+#[cfg(feature = "video")]
+#[tokio::test]
+async fn closing_the_client_stops_its_replay_buffer() {
+    let (mut client, _shutdown) = connect().await;
+    let replay = client
+        .start_replay_buffer(Duration::from_secs(1), None)
+        .await
+        .unwrap();
+    assert!(replay.is_capturing());
+    client.close().await.unwrap();
+    assert!(!replay.is_capturing());
+    // what was captured before is still there
+    assert!(replay.puffer().buffered_range_ms().is_some());
+}
+
+#[cfg(feature = "video")]
+#[tokio::test]
+async fn record_all_displays_creates_the_output_dir() {
+    let (mut client, _shutdown) = connect().await;
+    let config = ro_grpc::RecordingConfig {
+        fps: 10,
+        width: 64,
+        height: 48,
+        ..Default::default()
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().join("nested").join("recordings");
+    let written = client
+        .record_all_displays(1, &out_dir, Some(config))
+        .await
+        .unwrap();
+    assert!(!written.is_empty());
+    for path in written {
+        assert!(path.starts_with(&out_dir));
+        assert!(path.exists());
+    }
+}
+
+#[tokio::test]
+async fn vm_pause_resume_shutdown() {
+    let (mut client, _shutdown) = connect().await;
+    assert_eq!(client.vm_status().await.unwrap(), VmStatus::Running);
+    client.pause().await.unwrap();
+    assert_eq!(client.vm_status().await.unwrap(), VmStatus::Paused);
+    client.resume().await.unwrap();
+    client
+        .wait_for_vm_state(VmStatus::Running, Duration::from_secs(1))
+        .await
+        .unwrap();
+    client
+        .shutdown_and_wait(Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(client.vm_status().await.unwrap(), VmStatus::Shutdown);
+    // already shut down, waiting for it to run again times out
+    let err = client
+        .wait_for_vm_state(VmStatus::Running, Duration::from_millis(300))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ro_grpc::Error::Timeout(_)));
+}
+
+#[cfg(feature = "video")]
+#[tokio::test]
+async fn replay_buffer_saves_the_recent_window() {
+    let (mut client, _shutdown) = connect().await;
+    let config = ro_grpc::RecordingConfig {
+        fps: 10,
+        width: 64,
+        height: 48,
+        ..Default::default()
+    };
+    let replay = client
+        .start_replay_buffer(Duration::from_millis(500), Some(config))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert!(replay.is_capturing());
+    // the ring only holds the window, older frames were evicted
+    assert!(replay.puffer().metrics().frames_evicted > 0);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("replay.mp4");
+    let report = replay
+        .save_last(Duration::from_millis(300), &path)
+        .await
+        .unwrap();
+    assert!((1..=5).contains(&report.frames_written), "{:?}", report);
+    assert!(path.exists());
+    replay.stop().await;
+}
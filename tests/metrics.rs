@@ -0,0 +1,35 @@
+// Prometheus rendering of the metrics snapshots
+
+use ro_grpc::metrics::{ClientMetrics, PufferMetrics};
+
+#[test]
+fn puffer_metrics_render_counters_and_gauges() {
+    let text = PufferMetrics {
+        frames_received: 12,
+        frames_evicted: 2,
+        frames_buffered: 10,
+        frames_capacity: 10,
+        ..Default::default()
+    }
+    .to_prometheus("ro_puffer");
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines.contains(&"# TYPE ro_puffer_frames_received_total counter"));
+    assert!(lines.contains(&"ro_puffer_frames_received_total 12"));
+    assert!(lines.contains(&"ro_puffer_frames_evicted_total 2"));
+    assert!(lines.contains(&"ro_puffer_audio_bytes_total 0"));
+    assert!(lines.contains(&"# TYPE ro_puffer_frames_buffered gauge"));
+    assert!(lines.contains(&"ro_puffer_frames_buffered 10"));
+    // HELP, TYPE and value for each of the ten metrics
+    assert_eq!(lines.len(), 30);
+}
+
+#[test]
+fn client_metrics_render_retries() {
+    let text = ClientMetrics {
+        read_retries: 3,
+        connection_errors: 4,
+    }
+    .to_prometheus("ro_client");
+    assert!(text.contains("ro_client_read_retries_total 3\n"));
+    assert!(text.ends_with("ro_client_connection_errors_total 4\n"));
+}
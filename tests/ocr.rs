@@ -0,0 +1,90 @@
+// Example OCR backend wired through the `Ocr` trait, run with `--features ocr`
+#![cfg(feature = "ocr")]
+
+use ro_grpc::ocr::{find_text, BoundingBox, Ocr, TextRegion};
+use ro_grpc::proto::image_format::ImgFormat;
+use ro_grpc::proto::{Image, ImageFormat};
+
+/// Reports every non-black row band as a single "Hello" region.
+struct StubOcr;
+
+impl Ocr for StubOcr {
+    fn recognize(&self, img: &Image) -> Vec<TextRegion> {
+        let fmt = img.format.clone().unwrap_or_default();
+        let stride = fmt.width as usize * 3;
+        let lit: Vec<u32> = img
+            .image
+            .chunks(stride)
+            .enumerate()
+            .filter(|(_, row)| row.iter().any(|&b| b != 0))
+            .map(|(y, _)| y as u32)
+            .collect();
+        match (lit.first(), lit.last()) {
+            (Some(&top), Some(&bottom)) => vec![TextRegion {
+                text: "Hello".to_string(),
+                bbox: BoundingBox {
+                    x: 0,
+                    y: top,
+                    width: fmt.width,
+                    height: bottom - top + 1,
+                },
+            }],
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn rgb_image(width: u32, height: u32, lit_rows: std::ops::Range<u32>) -> Image {
+    let mut data = vec![0u8; (width * height * 3) as usize];
+    for y in lit_rows {
+        let start = (y * width * 3) as usize;
+        data[start..start + (width * 3) as usize].fill(255);
+    }
+    Image {
+        format: Some(ImageFormat {
+            format: ImgFormat::Rgb888.into(),
+            width,
+            height,
+            ..Default::default()
+        }),
+        image: data,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn stub_backend_finds_region() {
+    let regions = StubOcr.recognize(&rgb_image(20, 10, 4..6));
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].text, "Hello");
+    assert_eq!(
+        regions[0].bbox,
+        BoundingBox {
+            x: 0,
+            y: 4,
+            width: 20,
+            height: 2
+        }
+    );
+    assert_eq!(regions[0].bbox.center(), (10, 5));
+}
+
+#[test]
+fn stub_backend_blank_screen() {
+    assert!(StubOcr.recognize(&rgb_image(20, 10, 0..0)).is_empty());
+}
+
+#[test]
+fn find_text_prefers_exact_match() {
+    let region = |text: &str| TextRegion {
+        text: text.to_string(),
+        bbox: BoundingBox::default(),
+    };
+    let regions = vec![region("OK"), region("OK, got it"), region("Cancel")];
+    let exact = find_text(&regions, " OK ");
+    assert_eq!(exact.len(), 1);
+    assert_eq!(exact[0].text, "OK");
+    assert_eq!(find_text(&regions, "got").len(), 1);
+    assert_eq!(find_text(&regions, "C").len(), 1);
+    assert!(find_text(&regions, "Settings").is_empty());
+}
@@ -0,0 +1,322 @@
+// StreamPuffer buffering and analysis without an emulator
+#![cfg(feature = "video")]
+
+use std::time::Duration;
+
+use ro_grpc::proto::audio_format::{Channels, SampleFormat};
+use ro_grpc::proto::{AudioFormat, AudioPacket, Image};
+use ro_grpc::video::StreamPuffer;
+
+/// 100 ms of 8 kHz mono s16le starting at `start_ms`, a half-scale square wave or silence.
+fn packet(start_ms: u64, loud: bool) -> AudioPacket {
+    let sample: i16 = if loud { 16384 } else { 0 };
+    let audio = (0..800)
+        .flat_map(|n| {
+            let value = if n % 2 == 0 { sample } else { -sample };
+            value.to_le_bytes()
+        })
+        .collect();
+    AudioPacket {
+        format: Some(AudioFormat {
+            sampling_rate: 8000,
+            channels: Channels::Mono as i32,
+            format: SampleFormat::AudFmtS16 as i32,
+            mode: 0,
+        }),
+        timestamp: start_ms * 1000,
+        audio,
+    }
+}
+
+#[tokio::test]
+async fn detect_silence_finds_quiet_stretches() {
+    let puffer = StreamPuffer::new(0, 100, 0, 8000, 1, 0, 0);
+    // loud 0-100, silent 100-400, loud 400-500, silent 500-550, nothing 550-600, loud 600-700
+    for (start, loud) in [
+        (0, true),
+        (100, false),
+        (200, false),
+        (300, false),
+        (400, true),
+    ] {
+        puffer.push_audio(packet(start, loud)).await;
+    }
+    let mut short = packet(500, false);
+    short.audio.truncate(800); // 50 ms
+    puffer.push_audio(short).await;
+    puffer.push_audio(packet(600, true)).await;
+
+    let ranges = puffer
+        .detect_silence(-40.0, Duration::from_millis(200))
+        .await;
+    assert_eq!(ranges, vec![(100, 400)]);
+
+    let ranges = puffer
+        .detect_silence(-40.0, Duration::from_millis(20))
+        .await;
+    assert_eq!(ranges, vec![(100, 400), (500, 550)]);
+
+    // everything is "silent" once the threshold is above the signal
+    let ranges = puffer.detect_silence(0.0, Duration::from_millis(200)).await;
+    assert_eq!(ranges, vec![(0, 700)]);
+}
+
+#[tokio::test]
+async fn save_range_rejects_empty_and_outside_ranges() {
+    let puffer = StreamPuffer::new(100, 0, 10, 8000, 1, 2, 2);
+    for ms in (1000..2000).step_by(100) {
+        puffer
+            .push_video(Image {
+                image: vec![0; 2 * 2 * 3],
+                timestamp_us: ms * 1000,
+                ..Default::default()
+            })
+            .await;
+    }
+    let out = tempfile::tempdir().unwrap();
+    let path = out.path().join("range.mp4");
+
+    for (start, end) in [(1500, 1500), (1600, 1200), (0, 900), (2500, 3000)] {
+        let err = puffer
+            .save_range_to_mp4(&path, start, end)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ro_grpc::Error::Encode(_)), "{}", err);
+    }
+    // inside the buffer but between two frames
+    assert!(puffer.save_range_to_mp4(&path, 1510, 1590).await.is_err());
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn metrics_count_evictions_and_rate_limiting() {
+    let puffer = StreamPuffer::new(3, 2, 10, 8000, 1, 0, 0);
+    for ms in 0..5u64 {
+        puffer
+            .push_video(Image {
+                image: vec![0; 2 * 2 * 3],
+                timestamp_us: ms * 100_000,
+                ..Default::default()
+            })
+            .await;
+    }
+    for start in [0, 100, 200] {
+        puffer.push_audio(packet(start, false)).await;
+    }
+    let m = puffer.metrics();
+    assert_eq!(m.frames_received, 5);
+    assert_eq!(m.frames_rate_limited, 0);
+    assert_eq!(m.frames_evicted, 2);
+    assert_eq!((m.frames_buffered, m.frames_capacity), (3, 3));
+    assert_eq!(m.audio_chunks_received, 3);
+    assert_eq!(m.audio_chunks_evicted, 1);
+    assert_eq!(m.audio_bytes, 3 * 1600);
+    assert_eq!((m.audio_chunks_buffered, m.audio_chunks_capacity), (2, 2));
+
+    // 10 fps keeps one frame per 100 ms
+    puffer.set_drop_excess_frames(true);
+    for us in [1_000_000, 1_020_000, 1_040_000] {
+        puffer
+            .push_video(Image {
+                image: vec![0; 2 * 2 * 3],
+                timestamp_us: us,
+                ..Default::default()
+            })
+            .await;
+    }
+    let m = puffer.metrics();
+    assert_eq!(m.frames_received, 8);
+    assert_eq!(m.frames_rate_limited, 2);
+    assert_eq!(m.frames_evicted, 3);
+}
+
+/// Start time in ms of the first video and first audio stream of the file at `path`.
+fn stream_starts_ms(path: &std::path::Path) -> (f64, f64) {
+    use ffmpeg_next::media::Type;
+    ffmpeg_next::init().unwrap();
+    let input = ffmpeg_next::format::input(&path).unwrap();
+    let start = |medium: Type| {
+        let stream = input
+            .streams()
+            .find(|s| s.parameters().medium() == medium)
+            .expect("stream missing from the output");
+        stream.start_time() as f64 * f64::from(stream.time_base()) * 1000.0
+    };
+    (start(Type::Video), start(Type::Audio))
+}
+
+#[tokio::test]
+async fn synchronized_streams_share_one_clock() {
+    // real emulator timestamps are Unix epoch us, whose ms value no longer fits in 32 bits
+    const START_MS: u64 = 1_700_000_000_000;
+    let puffer = StreamPuffer::new(100, 100, 10, 48000, 1, 16, 16);
+    for i in 0..20 {
+        puffer
+            .push_video(Image {
+                image: vec![0; 16 * 16 * 3],
+                timestamp_us: (START_MS + i * 100) * 1000,
+                ..Default::default()
+            })
+            .await;
+        puffer.push_audio(packet(START_MS + i * 100, i != 5)).await;
+    }
+
+    // audio timestamps come out on the frames' clock
+    let ranges = puffer
+        .detect_silence(-40.0, Duration::from_millis(50))
+        .await;
+    assert_eq!(ranges, vec![(START_MS + 500, START_MS + 600)]);
+
+    let out = tempfile::tempdir().unwrap();
+    let path = out.path().join("sync.mp4");
+    puffer
+        .save_range_to_mp4(&path, START_MS, START_MS + 1900)
+        .await
+        .unwrap();
+    let (video, audio) = stream_starts_ms(&path);
+    // AAC priming shifts audio by 1024 samples (~21 ms at 48 kHz)
+    assert!(
+        (video - audio).abs() <= 50.0,
+        "video starts at {} ms, audio at {} ms",
+        video,
+        audio
+    );
+}
+
+#[tokio::test]
+async fn late_audio_keeps_its_offset() {
+    const START_MS: u64 = 1_700_000_000_000;
+    let puffer = StreamPuffer::new(100, 100, 10, 48000, 1, 16, 16);
+    for i in 0..20 {
+        puffer
+            .push_video(Image {
+                image: vec![0; 16 * 16 * 3],
+                timestamp_us: (START_MS + i * 100) * 1000,
+                ..Default::default()
+            })
+            .await;
+    }
+    // audio only starts 500 ms into the video
+    for i in 5..20 {
+        puffer.push_audio(packet(START_MS + i * 100, true)).await;
+    }
+
+    let out = tempfile::tempdir().unwrap();
+    let path = out.path().join("late_audio.mp4");
+    let report = puffer.save_last_to_mp4(&path).await.unwrap();
+    // the video before the audio starts is kept
+    assert_eq!(report.frames_written, 20);
+    let (video, audio) = stream_starts_ms(&path);
+    let gap = audio - video;
+    assert!(
+        (gap - 500.0).abs() <= 50.0,
+        "audio starts {} ms after video, expected 500",
+        gap
+    );
+}
+
+#[tokio::test]
+async fn cancelled_save_reports_and_removes_partial_file() {
+    use ro_grpc::video::SaveOutcome;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    let puffer = StreamPuffer::new(100, 0, 10, 8000, 1, 16, 16);
+    for i in 0..20u64 {
+        puffer
+            .push_video(Image {
+                image: vec![0; 16 * 16 * 3],
+                timestamp_us: i * 100_000,
+                ..Default::default()
+            })
+            .await;
+    }
+    let out = tempfile::tempdir().unwrap();
+    let path = out.path().join("cancelled.mp4");
+
+    // abort from the progress callback once 5 frames are in
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = cancel.clone();
+    let outcome = puffer
+        .save_last_to_mp4_cancellable(&path, cancel, move |encoded, _| {
+            if encoded == 5 {
+                flag.store(true, Ordering::SeqCst);
+            }
+        })
+        .await
+        .unwrap();
+    assert_eq!(outcome, SaveOutcome::Cancelled);
+    assert!(!path.exists());
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let progress = seen.clone();
+    let outcome = puffer
+        .save_last_to_mp4_cancellable(&path, Arc::new(AtomicBool::new(false)), move |e, t| {
+            progress.lock().unwrap().push((e, t))
+        })
+        .await
+        .unwrap();
+    assert!(matches!(outcome, SaveOutcome::Completed(report) if report.frames_written == 20));
+    assert_eq!(seen.lock().unwrap().last(), Some(&(20, 20)));
+    assert!(path.exists());
+}
+
+#[test]
+fn audio_container_follows_extension() {
+    use ro_grpc::AudioContainer;
+    use std::path::Path;
+    let guess = |name: &str| AudioContainer::from_path(Path::new(name));
+    assert_eq!(guess("take.aac"), Some(AudioContainer::Aac));
+    assert_eq!(guess("take.M4A"), Some(AudioContainer::M4a));
+    assert_eq!(guess("take.ogg"), Some(AudioContainer::Opus));
+    assert_eq!(guess("take"), None);
+}
+
+#[tokio::test]
+async fn gif_and_contact_sheet_keep_frames_after_rotation() {
+    use ro_grpc::proto::image_format::ImgFormat;
+    use ro_grpc::proto::ImageFormat;
+
+    // portrait first, then rotated to landscape halfway through the buffer
+    let puffer = StreamPuffer::new(100, 0, 10, 8000, 1, 8, 16);
+    for i in 0..10u64 {
+        let (width, height) = if i < 5 { (8, 16) } else { (16, 8) };
+        puffer
+            .push_video(Image {
+                format: Some(ImageFormat {
+                    format: ImgFormat::Rgb888 as i32,
+                    width,
+                    height,
+                    ..Default::default()
+                }),
+                image: vec![255; (width * height * 3) as usize],
+                timestamp_us: i * 100_000,
+                ..Default::default()
+            })
+            .await;
+    }
+    let out = tempfile::tempdir().unwrap();
+
+    let gif_path = out.path().join("rotated.gif");
+    puffer.save_last_to_gif(&gif_path, 0, 10).await.unwrap();
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options
+        .read_info(std::fs::File::open(&gif_path).unwrap())
+        .unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (8, 16));
+    let mut frames = 0;
+    while decoder.read_next_frame().unwrap().is_some() {
+        frames += 1;
+    }
+    assert_eq!(frames, 10);
+
+    // landscape frames are letterboxed into the portrait thumbnails
+    let sheet_path = out.path().join("rotated.png");
+    puffer.save_contact_sheet(&sheet_path, 10, 1).await.unwrap();
+    let sheet = image::open(&sheet_path).unwrap().to_rgb8();
+    assert_eq!(sheet.dimensions(), (80, 16));
+    assert_eq!(sheet.get_pixel(4, 0).0, [255, 255, 255]);
+    assert_eq!(sheet.get_pixel(76, 0).0, [0, 0, 0]);
+    assert_eq!(sheet.get_pixel(76, 8).0, [255, 255, 255]);
+}
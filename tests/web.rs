@@ -0,0 +1,65 @@
+// Web bridge against the in-process fake emulator, run with `--features web,test-server`
+#![cfg(all(feature = "web", feature = "test-server"))]
+
+use std::net::SocketAddr;
+
+use ro_grpc::fake_emulator::spawn_fake_emulator;
+use ro_grpc::DeviceGrpcClient;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+async fn spawn_bridge() -> (SocketAddr, tokio::sync::oneshot::Sender<()>) {
+    let (addr, shutdown) = spawn_fake_emulator().await.expect("spawn fake emulator");
+    let client = DeviceGrpcClient::connect(format!("http://{}", addr))
+        .await
+        .expect("connect to fake emulator");
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let web_addr = listener.local_addr().unwrap();
+    let server = axum::Server::from_tcp(listener)
+        .unwrap()
+        .serve(ro_grpc::web::router(client).into_make_service());
+    tokio::spawn(server);
+    (web_addr, shutdown)
+}
+
+/// Send `request` and read until `done` accepts what came back.
+async fn exchange(addr: SocketAddr, request: &str, done: impl Fn(&[u8]) -> bool) -> Vec<u8> {
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(request.as_bytes()).await.unwrap();
+    let mut response = Vec::new();
+    let mut buf = [0u8; 4096];
+    while !done(&response) {
+        let n = conn.read(&mut buf).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+    response
+}
+
+#[tokio::test]
+async fn tap_answers_no_content() {
+    let (addr, _shutdown) = spawn_bridge().await;
+    let body = r#"{"x": 10, "y": 20}"#;
+    let request = format!(
+        "POST /tap HTTP/1.1\r\nHost: test\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let response = exchange(addr, &request, |_| false).await;
+    let response = String::from_utf8_lossy(&response);
+    assert!(response.starts_with("HTTP/1.1 204"), "{}", response);
+}
+
+#[tokio::test]
+async fn stream_serves_jpeg_parts() {
+    let (addr, _shutdown) = spawn_bridge().await;
+    let request = "GET /stream HTTP/1.1\r\nHost: test\r\n\r\n";
+    // SOI marker of the first JPEG part
+    let response = exchange(addr, request, |r| r.windows(2).any(|w| w == [0xff, 0xd8])).await;
+    let head = String::from_utf8_lossy(&response);
+    assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+    assert!(head.contains("multipart/x-mixed-replace; boundary=ro-grpc-frame"));
+    assert!(head.contains("Content-Type: image/jpeg"));
+}